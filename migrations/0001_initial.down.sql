@@ -0,0 +1,6 @@
+DROP TABLE IF EXISTS articles_reactions;
+DROP TABLE IF EXISTS reactions;
+DROP TABLE IF EXISTS users_followers;
+DROP TABLE IF EXISTS articles_comments;
+DROP TABLE IF EXISTS articles;
+DROP TABLE IF EXISTS users;
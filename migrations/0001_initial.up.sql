@@ -0,0 +1,60 @@
+CREATE TABLE IF NOT EXISTS users (
+    id serial4 PRIMARY KEY,
+    first_name varchar(64) NOT NULL,
+    last_name varchar(64) NOT NULL,
+    about varchar(256) NULL,
+    password varchar(64) NOT NULL,
+    login varchar(64) NOT NULL,
+    full_avatar bytea NULL,
+    crop_avatar bytea NULL,
+    date_registration timestamptz NOT NULL default now()::timestamp with time zone::timestamp
+);
+
+CREATE TABLE IF NOT EXISTS articles (
+    id serial4 PRIMARY KEY,
+    author_id int4 NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    image text NOT NULL,
+    title varchar(64) NOT NULL,
+    description varchar(1024) NOT NULL,
+    publish_date timestamptz NOT NULL default now()::timestamp with time zone::timestamp
+);
+
+CREATE TABLE IF NOT EXISTS articles_comments (
+    id serial4 PRIMARY KEY,
+    users_id int4 NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    articles_id int4 NOT NULL REFERENCES articles(id) ON DELETE CASCADE,
+    publish_date timestamptz NOT NULL default now()::timestamp with time zone::timestamp,
+    message varchar(1024) NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS users_followers (
+    id serial4 PRIMARY KEY,
+    users_author_id int4 NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    users_follower_id int4 NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    follow_date timestamptz NOT NULL default now()::timestamp with time zone::timestamp
+);
+
+CREATE TABLE IF NOT EXISTS reactions (
+    id serial4 PRIMARY KEY,
+    description varchar(16) NOT NULL
+);
+
+INSERT INTO reactions (description)
+SELECT 'Нравится'
+WHERE NOT EXISTS (
+    SELECT description FROM reactions WHERE description = 'Нравится'
+);
+
+INSERT INTO reactions (description)
+SELECT 'Не нравится'
+WHERE NOT EXISTS (
+    SELECT description FROM reactions WHERE description = 'Не нравится'
+);
+
+CREATE TABLE IF NOT EXISTS articles_reactions (
+    id serial4 PRIMARY KEY,
+    users_id int4 NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    articles_id int4 NOT NULL REFERENCES articles(id) ON DELETE CASCADE,
+    reactions_id int4 NOT NULL REFERENCES reactions(id) ON DELETE CASCADE,
+    date timestamptz NOT NULL default now()::timestamp with time zone::timestamp
+);
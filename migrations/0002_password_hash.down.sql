@@ -0,0 +1,2 @@
+-- Откат возможен только пока в базе нет хешей длиннее 64 символов
+ALTER TABLE users ALTER COLUMN password TYPE varchar(64);
@@ -0,0 +1,20 @@
+CREATE TABLE IF NOT EXISTS sessions (
+    id serial4 PRIMARY KEY,
+    users_id int4 NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    token_hash char(64) NOT NULL UNIQUE,
+    token_expires_at timestamptz NOT NULL,
+    device varchar(128) NULL,
+    created_at timestamptz NOT NULL default now()::timestamp with time zone::timestamp,
+    last_used_at timestamptz NOT NULL default now()::timestamp with time zone::timestamp,
+    expires_at timestamptz NOT NULL,
+    revoked_at timestamptz NULL
+);
+
+CREATE TABLE IF NOT EXISTS refresh_tokens (
+    id serial4 PRIMARY KEY,
+    sessions_id int4 NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+    token_hash char(64) NOT NULL UNIQUE,
+    created_at timestamptz NOT NULL default now()::timestamp with time zone::timestamp,
+    expires_at timestamptz NOT NULL,
+    used_at timestamptz NULL
+);
@@ -0,0 +1,14 @@
+ALTER TABLE users ADD COLUMN IF NOT EXISTS role varchar(16) NOT NULL default 'user';
+
+ALTER TABLE users DROP CONSTRAINT IF EXISTS users_role_check;
+ALTER TABLE users ADD CONSTRAINT users_role_check CHECK (role IN ('user', 'moderator', 'admin'));
+
+CREATE TABLE IF NOT EXISTS moderation_log (
+    id serial4 PRIMARY KEY,
+    actor_id int4 NULL REFERENCES users(id) ON DELETE SET NULL,
+    action varchar(64) NOT NULL,
+    target_type varchar(32) NOT NULL,
+    target_id int4 NOT NULL,
+    details text NULL,
+    date timestamptz NOT NULL default now()::timestamp with time zone::timestamp
+);
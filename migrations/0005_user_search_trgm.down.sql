@@ -0,0 +1,5 @@
+-- Расширение pg_trgm не удаляется: его могут использовать другие объекты базы данных
+DROP INDEX IF EXISTS users_about_trgm_idx;
+DROP INDEX IF EXISTS users_login_trgm_idx;
+DROP INDEX IF EXISTS users_last_name_trgm_idx;
+DROP INDEX IF EXISTS users_first_name_trgm_idx;
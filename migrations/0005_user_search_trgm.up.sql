@@ -0,0 +1,6 @@
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+CREATE INDEX IF NOT EXISTS users_first_name_trgm_idx ON users USING gin (lower(first_name) gin_trgm_ops);
+CREATE INDEX IF NOT EXISTS users_last_name_trgm_idx ON users USING gin (lower(last_name) gin_trgm_ops);
+CREATE INDEX IF NOT EXISTS users_login_trgm_idx ON users USING gin (lower(login) gin_trgm_ops);
+CREATE INDEX IF NOT EXISTS users_about_trgm_idx ON users USING gin (lower(about) gin_trgm_ops);
@@ -0,0 +1 @@
+ALTER TABLE articles ALTER COLUMN image TYPE text USING image::text;
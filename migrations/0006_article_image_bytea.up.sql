@@ -0,0 +1,2 @@
+-- Картинка записи хранилась в колонке text (hex-представление bytea), модель `Article` ожидает bytea
+ALTER TABLE articles ALTER COLUMN image TYPE bytea USING image::bytea;
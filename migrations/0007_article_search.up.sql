@@ -0,0 +1,8 @@
+-- Конфигурация russian стеммит кириллицу русским словарем, а латиницу английским
+ALTER TABLE articles ADD COLUMN IF NOT EXISTS search_vector tsvector GENERATED ALWAYS AS (
+    setweight(to_tsvector('russian', coalesce(title, '')), 'A') ||
+    setweight(to_tsvector('russian', coalesce(description, '')), 'B')
+) STORED;
+
+CREATE INDEX IF NOT EXISTS articles_search_vector_idx ON articles USING gin (search_vector);
+CREATE INDEX IF NOT EXISTS articles_author_id_publish_date_idx ON articles (author_id, publish_date);
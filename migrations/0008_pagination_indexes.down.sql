@@ -0,0 +1,4 @@
+DROP INDEX IF EXISTS users_followers_users_author_id_idx;
+DROP INDEX IF EXISTS users_followers_users_follower_id_idx;
+DROP INDEX IF EXISTS articles_comments_articles_id_publish_date_idx;
+DROP INDEX IF EXISTS articles_publish_date_id_idx;
@@ -0,0 +1,4 @@
+CREATE INDEX IF NOT EXISTS articles_publish_date_id_idx ON articles (publish_date, id);
+CREATE INDEX IF NOT EXISTS articles_comments_articles_id_publish_date_idx ON articles_comments (articles_id, publish_date, id);
+CREATE INDEX IF NOT EXISTS users_followers_users_follower_id_idx ON users_followers (users_follower_id);
+CREATE INDEX IF NOT EXISTS users_followers_users_author_id_idx ON users_followers (users_author_id);
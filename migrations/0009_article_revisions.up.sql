@@ -0,0 +1,14 @@
+ALTER TABLE articles ADD COLUMN IF NOT EXISTS edited_date timestamptz NULL;
+
+-- Предыдущие версии записи. Строка создается при каждом изменении или восстановлении записи
+CREATE TABLE IF NOT EXISTS article_revisions (
+    id serial4 PRIMARY KEY,
+    articles_id int4 NOT NULL REFERENCES articles(id) ON DELETE CASCADE,
+    editor_id int4 NULL REFERENCES users(id) ON DELETE SET NULL,
+    image bytea NOT NULL,
+    title varchar(64) NOT NULL,
+    description varchar(1024) NOT NULL,
+    date timestamptz NOT NULL default now()::timestamp with time zone::timestamp
+);
+
+CREATE INDEX IF NOT EXISTS article_revisions_articles_id_idx ON article_revisions (articles_id, id);
@@ -0,0 +1,6 @@
+-- Черновики и запланированные записи после отката становятся опубликованными
+DROP INDEX IF EXISTS articles_author_id_status_idx;
+DROP INDEX IF EXISTS articles_scheduled_publish_at_idx;
+ALTER TABLE articles DROP CONSTRAINT IF EXISTS articles_status_check;
+ALTER TABLE articles DROP COLUMN IF EXISTS publish_at;
+ALTER TABLE articles DROP COLUMN IF EXISTS status;
@@ -0,0 +1,11 @@
+ALTER TABLE articles ADD COLUMN IF NOT EXISTS status varchar(16) NOT NULL DEFAULT 'published';
+ALTER TABLE articles ADD COLUMN IF NOT EXISTS publish_at timestamptz NULL;
+
+ALTER TABLE articles DROP CONSTRAINT IF EXISTS articles_status_check;
+ALTER TABLE articles ADD CONSTRAINT articles_status_check CHECK (
+    status IN ('draft', 'scheduled', 'published')
+    AND (status != 'scheduled' OR publish_at IS NOT NULL)
+);
+
+CREATE INDEX IF NOT EXISTS articles_scheduled_publish_at_idx ON articles (publish_at) WHERE status = 'scheduled';
+CREATE INDEX IF NOT EXISTS articles_author_id_status_idx ON articles (author_id, status);
@@ -0,0 +1,3 @@
+DROP TABLE IF EXISTS users_tags;
+DROP TABLE IF EXISTS articles_tags;
+DROP TABLE IF EXISTS tags;
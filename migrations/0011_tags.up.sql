@@ -0,0 +1,23 @@
+CREATE TABLE IF NOT EXISTS tags (
+    id serial4 PRIMARY KEY,
+    name varchar(32) NOT NULL UNIQUE,
+    date timestamptz NOT NULL default now()::timestamp with time zone::timestamp
+);
+
+-- Поиск тегов по началу названия (автодополнение)
+CREATE INDEX IF NOT EXISTS tags_name_pattern_idx ON tags (name varchar_pattern_ops);
+
+CREATE TABLE IF NOT EXISTS articles_tags (
+    articles_id int4 NOT NULL REFERENCES articles(id) ON DELETE CASCADE,
+    tags_id int4 NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+    PRIMARY KEY (articles_id, tags_id)
+);
+
+CREATE INDEX IF NOT EXISTS articles_tags_tags_id_idx ON articles_tags (tags_id, articles_id);
+
+CREATE TABLE IF NOT EXISTS users_tags (
+    users_id int4 NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    tags_id int4 NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+    follow_date timestamptz NOT NULL default now()::timestamp with time zone::timestamp,
+    PRIMARY KEY (users_id, tags_id)
+);
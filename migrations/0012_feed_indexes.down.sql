@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS articles_reactions_articles_id_idx;
+DROP INDEX IF EXISTS articles_published_publish_date_id_idx;
@@ -0,0 +1,3 @@
+-- Общая лента и популярные записи выбирают только опубликованные записи по дате публикации
+CREATE INDEX IF NOT EXISTS articles_published_publish_date_id_idx ON articles (publish_date, id) WHERE status = 'published';
+CREATE INDEX IF NOT EXISTS articles_reactions_articles_id_idx ON articles_reactions (articles_id, reactions_id);
@@ -0,0 +1,18 @@
+ALTER TABLE users ADD COLUMN IF NOT EXISTS full_avatar bytea NULL;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS crop_avatar bytea NULL;
+UPDATE users SET crop_avatar = i.data FROM images AS i WHERE i.id = users.crop_avatar_id;
+UPDATE users SET full_avatar = i.data FROM images AS i WHERE i.id = users.full_avatar_id;
+ALTER TABLE users DROP COLUMN crop_avatar_id;
+ALTER TABLE users DROP COLUMN full_avatar_id;
+
+ALTER TABLE article_revisions ADD COLUMN IF NOT EXISTS image bytea NULL;
+UPDATE article_revisions SET image = i.data FROM images AS i WHERE i.id = article_revisions.image_id;
+ALTER TABLE article_revisions ALTER COLUMN image SET NOT NULL;
+ALTER TABLE article_revisions DROP COLUMN image_id;
+
+ALTER TABLE articles ADD COLUMN IF NOT EXISTS image bytea NULL;
+UPDATE articles SET image = i.data FROM images AS i WHERE i.id = articles.image_id;
+ALTER TABLE articles ALTER COLUMN image SET NOT NULL;
+ALTER TABLE articles DROP COLUMN image_id;
+
+DROP TABLE IF EXISTS images;
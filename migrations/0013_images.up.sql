@@ -0,0 +1,48 @@
+-- Картинки хранятся один раз на содержимое: `hash` - sha256 в hex, он же ETag
+CREATE TABLE IF NOT EXISTS images (
+    id serial4 PRIMARY KEY,
+    hash varchar(64) NOT NULL UNIQUE,
+    content_type varchar(64) NOT NULL,
+    data bytea NOT NULL,
+    date timestamptz NOT NULL default now()::timestamp with time zone::timestamp
+);
+
+WITH blobs AS (
+    SELECT image AS data FROM articles
+    UNION ALL
+    SELECT image FROM article_revisions
+    UNION ALL
+    SELECT crop_avatar FROM users WHERE crop_avatar IS NOT NULL
+    UNION ALL
+    SELECT full_avatar FROM users WHERE full_avatar IS NOT NULL
+)
+INSERT INTO images (hash, content_type, data)
+SELECT DISTINCT ON (encode(sha256(data), 'hex')) encode(sha256(data), 'hex'),
+    CASE
+        WHEN substring(data FROM 1 FOR 3) = '\xffd8ff'::bytea THEN 'image/jpeg'
+        WHEN substring(data FROM 1 FOR 8) = '\x89504e470d0a1a0a'::bytea THEN 'image/png'
+        WHEN substring(data FROM 1 FOR 4) = '\x47494638'::bytea THEN 'image/gif'
+        WHEN substring(data FROM 1 FOR 4) = '\x52494646'::bytea
+            AND substring(data FROM 9 FOR 4) = '\x57454250'::bytea THEN 'image/webp'
+        ELSE 'application/octet-stream'
+    END,
+    data
+FROM blobs
+ON CONFLICT (hash) DO NOTHING;
+
+ALTER TABLE articles ADD COLUMN IF NOT EXISTS image_id int4 NULL REFERENCES images(id);
+UPDATE articles SET image_id = i.id FROM images AS i WHERE i.hash = encode(sha256(articles.image), 'hex');
+ALTER TABLE articles ALTER COLUMN image_id SET NOT NULL;
+ALTER TABLE articles DROP COLUMN image;
+
+ALTER TABLE article_revisions ADD COLUMN IF NOT EXISTS image_id int4 NULL REFERENCES images(id);
+UPDATE article_revisions SET image_id = i.id FROM images AS i WHERE i.hash = encode(sha256(article_revisions.image), 'hex');
+ALTER TABLE article_revisions ALTER COLUMN image_id SET NOT NULL;
+ALTER TABLE article_revisions DROP COLUMN image;
+
+ALTER TABLE users ADD COLUMN IF NOT EXISTS crop_avatar_id int4 NULL REFERENCES images(id);
+ALTER TABLE users ADD COLUMN IF NOT EXISTS full_avatar_id int4 NULL REFERENCES images(id);
+UPDATE users SET crop_avatar_id = i.id FROM images AS i WHERE i.hash = encode(sha256(users.crop_avatar), 'hex');
+UPDATE users SET full_avatar_id = i.id FROM images AS i WHERE i.hash = encode(sha256(users.full_avatar), 'hex');
+ALTER TABLE users DROP COLUMN crop_avatar;
+ALTER TABLE users DROP COLUMN full_avatar;
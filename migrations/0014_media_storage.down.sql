@@ -0,0 +1,2 @@
+-- Откат возможен только если все картинки еще лежат в базе данных
+ALTER TABLE images ALTER COLUMN data SET NOT NULL;
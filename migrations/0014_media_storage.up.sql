@@ -0,0 +1,3 @@
+-- Содержимое картинок переезжает в хранилище файлов (`--migrate-media`).
+-- `data IS NULL` - картинка лежит в хранилище под ключом `hash`
+ALTER TABLE images ALTER COLUMN data DROP NOT NULL;
@@ -0,0 +1,15 @@
+-- Докачиваемые загрузки: клиент создает сессию с итоговым размером и присылает части по порядку.
+-- Части пишутся во временный файл (`MEDIA_STAGING_PATH`), пока загрузка не завершена, после завершения `image_id` - готовая картинка
+CREATE TABLE IF NOT EXISTS uploads (
+    id serial4 PRIMARY KEY,
+    users_id int4 NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    size int8 NOT NULL,
+    received int8 NOT NULL default 0,
+    image_id int4 NULL REFERENCES images(id) ON DELETE CASCADE,
+    created_at timestamptz NOT NULL default now()::timestamp with time zone::timestamp,
+    updated_at timestamptz NOT NULL default now()::timestamp with time zone::timestamp,
+    completed_at timestamptz NULL
+);
+
+CREATE INDEX IF NOT EXISTS uploads_updated_at_idx ON uploads (updated_at) WHERE image_id IS NULL;
+CREATE INDEX IF NOT EXISTS uploads_completed_at_idx ON uploads (completed_at) WHERE image_id IS NOT NULL;
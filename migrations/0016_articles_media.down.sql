@@ -0,0 +1,2 @@
+DROP TYPE IF EXISTS article_media_item;
+DROP TABLE IF EXISTS articles_media;
@@ -0,0 +1,22 @@
+-- Галерея записи: упорядоченный список картинок с подписью и alt текстом.
+-- Обложка для карточек в лентах остается в `articles.image_id`
+CREATE TABLE IF NOT EXISTS articles_media (
+    id serial4 PRIMARY KEY,
+    articles_id int4 NOT NULL REFERENCES articles(id) ON DELETE CASCADE,
+    image_id int4 NOT NULL REFERENCES images(id),
+    position int4 NOT NULL,
+    caption varchar(512) NULL,
+    alt varchar(256) NULL,
+    date timestamptz NOT NULL default now()::timestamp with time zone::timestamp,
+    -- отложенная проверка, чтобы при изменении порядка позиции можно было менять местами
+    CONSTRAINT articles_media_position_key UNIQUE (articles_id, position) DEFERRABLE INITIALLY DEFERRED
+);
+
+-- Элемент галереи в выборках записей: `ARRAY(SELECT ROW(...)::article_media_item ...)`
+CREATE TYPE article_media_item AS (
+    id int4,
+    image int4,
+    position int4,
+    caption varchar,
+    alt varchar
+);
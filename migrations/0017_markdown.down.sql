@@ -0,0 +1,15 @@
+ALTER TABLE articles DROP COLUMN IF EXISTS search_vector;
+ALTER TABLE articles ADD COLUMN search_vector tsvector GENERATED ALWAYS AS (
+    setweight(to_tsvector('russian', coalesce(title, '')), 'A') ||
+    setweight(to_tsvector('russian', coalesce(description, '')), 'B')
+) STORED;
+
+CREATE INDEX IF NOT EXISTS articles_search_vector_idx ON articles USING gin (search_vector);
+
+ALTER TABLE article_revisions DROP COLUMN IF EXISTS crop_description;
+ALTER TABLE article_revisions DROP COLUMN IF EXISTS description_text;
+ALTER TABLE article_revisions DROP COLUMN IF EXISTS description_html;
+
+ALTER TABLE articles DROP COLUMN IF EXISTS crop_description;
+ALTER TABLE articles DROP COLUMN IF EXISTS description_text;
+ALTER TABLE articles DROP COLUMN IF EXISTS description_html;
@@ -0,0 +1,50 @@
+-- `description` теперь исходный текст в Markdown. Рядом хранятся отрисованный и очищенный HTML,
+-- текст без разметки (для поиска) и превью, обрезанное по границе слова. Все три формы считает сервер.
+ALTER TABLE articles ADD COLUMN IF NOT EXISTS description_html text NULL;
+ALTER TABLE articles ADD COLUMN IF NOT EXISTS description_text text NULL;
+ALTER TABLE articles ADD COLUMN IF NOT EXISTS crop_description varchar(256) NULL;
+
+ALTER TABLE article_revisions ADD COLUMN IF NOT EXISTS description_html text NULL;
+ALTER TABLE article_revisions ADD COLUMN IF NOT EXISTS description_text text NULL;
+ALTER TABLE article_revisions ADD COLUMN IF NOT EXISTS crop_description varchar(256) NULL;
+
+-- Старые описания - обычный текст: HTML получается экранированием, превью - обрезкой по последнему пробелу
+CREATE FUNCTION pg_temp.plain_html(value text) RETURNS text AS $$
+    SELECT '<p>' || replace(replace(replace(replace(value, '&', '&amp;'), '<', '&lt;'), '>', '&gt;'), E'\n', '<br>') || '</p>';
+$$ LANGUAGE sql IMMUTABLE;
+
+CREATE FUNCTION pg_temp.plain_crop(value text) RETURNS text AS $$
+    SELECT CASE
+        WHEN char_length(v) <= 150 THEN v
+        WHEN substr(v, 151, 1) = ' ' THEN left(v, 150) || '...'
+        ELSE coalesce(nullif(regexp_replace(left(v, 150), '\s+\S*$', ''), ''), left(v, 150)) || '...'
+    END
+    FROM (SELECT trim(regexp_replace(value, '\s+', ' ', 'g')) AS v) AS o;
+$$ LANGUAGE sql IMMUTABLE;
+
+UPDATE articles
+SET description_html = pg_temp.plain_html(description),
+    description_text = description,
+    crop_description = pg_temp.plain_crop(description);
+
+UPDATE article_revisions
+SET description_html = pg_temp.plain_html(description),
+    description_text = description,
+    crop_description = pg_temp.plain_crop(description);
+
+ALTER TABLE articles ALTER COLUMN description_html SET NOT NULL;
+ALTER TABLE articles ALTER COLUMN description_text SET NOT NULL;
+ALTER TABLE articles ALTER COLUMN crop_description SET NOT NULL;
+
+ALTER TABLE article_revisions ALTER COLUMN description_html SET NOT NULL;
+ALTER TABLE article_revisions ALTER COLUMN description_text SET NOT NULL;
+ALTER TABLE article_revisions ALTER COLUMN crop_description SET NOT NULL;
+
+-- Поиск по тексту без разметки, иначе в индекс и сниппеты попадают `**`, `#` и ссылки
+ALTER TABLE articles DROP COLUMN IF EXISTS search_vector;
+ALTER TABLE articles ADD COLUMN search_vector tsvector GENERATED ALWAYS AS (
+    setweight(to_tsvector('russian', coalesce(title, '')), 'A') ||
+    setweight(to_tsvector('russian', coalesce(description_text, '')), 'B')
+) STORED;
+
+CREATE INDEX IF NOT EXISTS articles_search_vector_idx ON articles USING gin (search_vector);
@@ -0,0 +1,9 @@
+ALTER TABLE article_revisions DROP COLUMN IF EXISTS reading_time;
+ALTER TABLE articles DROP COLUMN IF EXISTS reading_time;
+
+ALTER TABLE article_revisions DROP COLUMN IF EXISTS summary;
+ALTER TABLE articles DROP COLUMN IF EXISTS summary;
+
+-- Тела длиннее 1024 символов обрезаются, иначе откат невозможен
+ALTER TABLE article_revisions ALTER COLUMN description TYPE varchar(1024) USING left(description, 1024);
+ALTER TABLE articles ALTER COLUMN description TYPE varchar(1024) USING left(description, 1024);
@@ -0,0 +1,22 @@
+-- Длинные записи: тело без ограничения длины. Большие значения `text` PostgreSQL сжимает и хранит
+-- отдельно от строки (TOAST), поэтому выборки лент, в которых нет полей тела, их не читают
+ALTER TABLE articles ALTER COLUMN description TYPE text;
+ALTER TABLE article_revisions ALTER COLUMN description TYPE text;
+
+-- Краткое описание, которое автор задает сам вместо вычисленного `crop_description`
+ALTER TABLE articles ADD COLUMN IF NOT EXISTS summary varchar(512) NULL;
+ALTER TABLE article_revisions ADD COLUMN IF NOT EXISTS summary varchar(512) NULL;
+
+-- Время чтения в минутах, 200 слов в минуту
+ALTER TABLE articles ADD COLUMN IF NOT EXISTS reading_time int4 NULL;
+ALTER TABLE article_revisions ADD COLUMN IF NOT EXISTS reading_time int4 NULL;
+
+CREATE FUNCTION pg_temp.reading_time(value text) RETURNS int4 AS $$
+    SELECT greatest(1, ceil(coalesce(array_length(regexp_split_to_array(nullif(trim(value), ''), '\s+'), 1), 0) / 200.0))::int4;
+$$ LANGUAGE sql IMMUTABLE;
+
+UPDATE articles SET reading_time = pg_temp.reading_time(description_text);
+UPDATE article_revisions SET reading_time = pg_temp.reading_time(description_text);
+
+ALTER TABLE articles ALTER COLUMN reading_time SET NOT NULL;
+ALTER TABLE article_revisions ALTER COLUMN reading_time SET NOT NULL;
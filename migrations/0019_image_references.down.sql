@@ -0,0 +1,6 @@
+DROP INDEX IF EXISTS users_full_avatar_id_idx;
+DROP INDEX IF EXISTS users_crop_avatar_id_idx;
+DROP INDEX IF EXISTS uploads_image_id_idx;
+DROP INDEX IF EXISTS article_revisions_image_id_idx;
+DROP INDEX IF EXISTS articles_media_image_id_idx;
+DROP INDEX IF EXISTS articles_image_id_idx;
@@ -0,0 +1,7 @@
+-- Индексы ссылок на картинки: `GET /images/{id}` проверяет, опубликована ли картинка и кто ее владелец
+CREATE INDEX IF NOT EXISTS articles_image_id_idx ON articles (image_id);
+CREATE INDEX IF NOT EXISTS articles_media_image_id_idx ON articles_media (image_id);
+CREATE INDEX IF NOT EXISTS article_revisions_image_id_idx ON article_revisions (image_id) WHERE image_id IS NOT NULL;
+CREATE INDEX IF NOT EXISTS uploads_image_id_idx ON uploads (image_id) WHERE image_id IS NOT NULL;
+CREATE INDEX IF NOT EXISTS users_crop_avatar_id_idx ON users (crop_avatar_id) WHERE crop_avatar_id IS NOT NULL;
+CREATE INDEX IF NOT EXISTS users_full_avatar_id_idx ON users (full_avatar_id) WHERE full_avatar_id IS NOT NULL;
@@ -0,0 +1,61 @@
+pub mod password {
+    use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
+    use argon2::password_hash::{Error, SaltString};
+    use argon2::password_hash::rand_core::OsRng;
+
+    /// Результат проверки пароля
+    #[derive(Clone, Copy, Eq, PartialEq, Debug)]
+    pub enum Verification {
+        /// Пароль совпал с хешем Argon2id
+        Valid,
+        /// Пароль совпал, но в базе он хранится в открытом виде и его нужно перехешировать
+        ValidLegacy,
+        /// Пароль не совпал
+        Invalid,
+    }
+
+    /// Хеширование пароля алгоритмом Argon2id со случайной солью
+    /// ### Принимает:
+    /// Пароль в открытом виде
+    ///
+    /// ### Возвращает:
+    /// Если [`Ok`], то строка в формате PHC. При ошибки [`argon2::password_hash::Error`]
+    pub fn hash_password(password: &str) -> Result<String, Error> {
+        let salt = SaltString::generate(&mut OsRng);
+        let hash = Argon2::default().hash_password(password.as_bytes(), &salt)?;
+
+        Ok(hash.to_string())
+    }
+
+    /// Проверка пароля по значению из базы данных.
+    ///
+    /// Если значение из базы не является строкой PHC, то считаем что это старая запись
+    /// с паролем в открытом виде и сравниваем за постоянное время.
+    /// ### Принимает:
+    /// Пароль в открытом виде, значение колонки `users.password`
+    ///
+    /// ### Возвращает:
+    /// [`Verification`]
+    pub fn verify_password(password: &str, stored: &str) -> Verification {
+        match PasswordHash::new(stored) {
+            Ok(hash) => match Argon2::default().verify_password(password.as_bytes(), &hash) {
+                Ok(_) => Verification::Valid,
+                Err(_) => Verification::Invalid,
+            },
+            Err(_) => match constant_time_eq(password.as_bytes(), stored.as_bytes()) {
+                true => Verification::ValidLegacy,
+                false => Verification::Invalid,
+            },
+        }
+    }
+
+    fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+        if a.len() != b.len() {
+            return false;
+        }
+
+        a.iter()
+            .zip(b.iter())
+            .fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+    }
+}
@@ -0,0 +1,301 @@
+pub mod policy {
+    use crate::auth::auth_session::session::AuthUser;
+    use crate::error::api_error::{ApiError, ErrorCode};
+    use crate::logger::log::{Level, log};
+    use crate::postgresql::models::model_user::user::Role;
+    use crate::postgresql::postgresql_manager::Connect;
+
+    /// Действие над ресурсом, которое выполняет аутентифицированный пользователь
+    #[derive(Clone, Copy, Eq, Hash, PartialEq, Debug)]
+    pub enum Action {
+        /// Изменение профиля пользователя (аватар и т.д.)
+        UpdateProfile { user_id: i32 },
+        /// Подписка на автора или отписка от него
+        FollowUser { author_id: i32 },
+        /// Публикация новой записи
+        InsertArticle,
+        /// Изменение записи, просмотр и восстановление ее версий
+        EditArticle { article_id: i32 },
+        /// Удаление записи
+        RemoveArticle { article_id: i32 },
+        /// Комментирование записи
+        InsertComment { article_id: i32 },
+        /// Удаление комментария
+        RemoveComment { comment_id: i32 },
+        /// Установка или удаление своей реакции на запись
+        ReactArticle { article_id: i32 },
+        /// Изменение роли пользователя
+        SetUserRole { user_id: i32 },
+        /// Просмотр журнала модерации
+        ViewModerationLog,
+    }
+
+    impl Action {
+        /// Название действия, тип и ID объекта для журнала модерации
+        fn audit_target(&self) -> (&'static str, &'static str, i32) {
+            match *self {
+                Action::UpdateProfile { user_id } => ("update_profile", "user", user_id),
+                Action::FollowUser { author_id } => ("follow_user", "user", author_id),
+                Action::InsertArticle => ("insert_article", "article", 0),
+                Action::EditArticle { article_id } => ("edit_article", "article", article_id),
+                Action::RemoveArticle { article_id } => ("remove_article", "article", article_id),
+                Action::InsertComment { article_id } => ("insert_comment", "article", article_id),
+                Action::RemoveComment { comment_id } => ("remove_comment", "comment", comment_id),
+                Action::ReactArticle { article_id } => ("react_article", "article", article_id),
+                Action::SetUserRole { user_id } => ("set_user_role", "user", user_id),
+                Action::ViewModerationLog => ("view_moderation_log", "moderation_log", 0),
+            }
+        }
+    }
+
+    /// Основание, по которому действие разрешено
+    #[derive(Clone, Copy, Eq, Hash, PartialEq, Debug)]
+    pub enum Grant {
+        /// Пользователь действует над своими ресурсами
+        Allowed,
+        /// Пользователь действует над чужими ресурсами благодаря своей роли - действие нужно записать в журнал
+        Privileged,
+    }
+
+    /// Проверка, может ли пользователь выполнить действие
+    /// ### Принимает:
+    /// Подключение к базе данных, аутентифицированного пользователя, действие
+    ///
+    /// ### Возвращает:
+    /// Если [`Ok`], то основание [`Grant`]. Иначе [`ApiError`]: 403, если действие запрещено, 404, если ресурс не найден
+    pub async fn authorize(conn: &Connect, actor: &AuthUser, action: Action) -> Result<Grant, ApiError> {
+        match action {
+            Action::UpdateProfile { user_id } => {
+                if !conn.exist_user_by_id(user_id).await.map_err(database)? {
+                    return Err(ApiError::new(ErrorCode::UserNotFound, "User not found!"));
+                }
+
+                owner_or_role(actor, is_same_user(actor, user_id), Role::Admin, "You can not change another user!")
+            },
+            Action::FollowUser { author_id } => {
+                if !conn.exist_user_by_id(author_id).await.map_err(database)? {
+                    return Err(ApiError::new(ErrorCode::UserNotFound, "User not found!"));
+                }
+
+                require(!is_same_user(actor, author_id), "You can not follow yourself!")
+            },
+            Action::InsertArticle => Ok(Grant::Allowed),
+            Action::EditArticle { article_id } => {
+                if !conn.exist_article(article_id).await.map_err(database)? {
+                    return Err(ApiError::new(ErrorCode::ArticleNotFound, "Article not found!"));
+                }
+
+                let is_author = conn.is_user_author_article(actor.id, article_id).await.map_err(database)?;
+                require(is_author, "You are not author this article!")
+            },
+            Action::RemoveArticle { article_id } => {
+                if !conn.exist_article(article_id).await.map_err(database)? {
+                    return Err(ApiError::new(ErrorCode::ArticleNotFound, "Article not found!"));
+                }
+
+                let is_author = conn.is_user_author_article(actor.id, article_id).await.map_err(database)?;
+                owner_or_role(actor, is_author, Role::Moderator, "You are not author this article!")
+            },
+            Action::RemoveComment { comment_id } => {
+                let author_id = match conn.get_comment_author_id(comment_id).await.map_err(database)? {
+                    Some(o) => o,
+                    None => return Err(ApiError::new(ErrorCode::CommentNotFound, "Comment not found!")),
+                };
+
+                owner_or_role(actor, is_same_user(actor, author_id), Role::Moderator, "You are not author this comment!")
+            },
+            Action::InsertComment { article_id } | Action::ReactArticle { article_id } => {
+                match conn.exist_published_article(article_id).await.map_err(database)? {
+                    true => Ok(Grant::Allowed),
+                    false => Err(ApiError::new(ErrorCode::ArticleNotFound, "Article not found!")),
+                }
+            },
+            Action::SetUserRole { user_id } => {
+                require_role(actor, Role::Admin)?;
+
+                if !conn.exist_user_by_id(user_id).await.map_err(database)? {
+                    return Err(ApiError::new(ErrorCode::UserNotFound, "User not found!"));
+                }
+
+                require(!is_same_user(actor, user_id), "You can not change your own role!")
+                    .map(|_| Grant::Privileged)
+            },
+            Action::ViewModerationLog => require_role(actor, Role::Admin),
+        }
+    }
+
+    /// Запись действия в журнал модерации, если оно было выполнено благодаря роли.
+    ///
+    /// Ошибки только логируются, так-как само действие уже выполнено.
+    pub async fn audit(conn: &Connect, actor: &AuthUser, action: Action, grant: Grant, details: Option<&str>) {
+        if grant != Grant::Privileged {
+            return;
+        }
+
+        let (name, target_type, target_id) = action.audit_target();
+
+        if let Err(e) = conn.insert_moderation_record(actor.id, name, target_type, target_id, details).await {
+            log(Level::Error, "[Policy][audit] >>> conn.insert_moderation_record",
+                &format!("Handle: {}", e)
+            );
+        }
+    }
+
+    /// Является ли пользователь владельцем ресурса с указанным ID пользователя
+    pub fn is_same_user(actor: &AuthUser, user_id: i32) -> bool {
+        actor.id == user_id
+    }
+
+    /// Имеет ли пользователь роль не ниже указанной
+    pub fn has_role(actor: &AuthUser, role: Role) -> bool {
+        match role {
+            Role::User => true,
+            Role::Moderator => actor.role.can_moderate(),
+            Role::Admin => actor.role == Role::Admin,
+        }
+    }
+
+    fn owner_or_role(actor: &AuthUser, is_owner: bool, role: Role, message: &'static str) -> Result<Grant, ApiError> {
+        if is_owner {
+            return Ok(Grant::Allowed);
+        }
+
+        match has_role(actor, role) {
+            true => Ok(Grant::Privileged),
+            false => Err(ApiError::forbidden(message)),
+        }
+    }
+
+    fn require_role(actor: &AuthUser, role: Role) -> Result<Grant, ApiError> {
+        match has_role(actor, role) {
+            true => Ok(Grant::Privileged),
+            false => Err(ApiError::forbidden("Forbidden!")),
+        }
+    }
+
+    fn require(allowed: bool, message: &'static str) -> Result<Grant, ApiError> {
+        match allowed {
+            true => Ok(Grant::Allowed),
+            false => Err(ApiError::forbidden(message)),
+        }
+    }
+
+    fn database(e: sqlx::Error) -> ApiError {
+        ApiError::internal("[Policy][authorize]", e)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::{authorize, Action, Grant};
+        use actix_web::ResponseError;
+        use actix_web::http::StatusCode;
+        use crate::error::api_error::{ApiError, ErrorCode};
+        use crate::postgresql::models::model_user::user::Role;
+        use crate::testing::fixtures::{connect, insert_article, insert_comment, insert_user};
+
+        fn assert_forbidden(result: Result<Grant, ApiError>) {
+            match result {
+                Err(e) => {
+                    assert_eq!(e.code, ErrorCode::Forbidden);
+                    assert_eq!(e.status_code(), StatusCode::FORBIDDEN);
+                },
+                Ok(o) => panic!("expected 403, got {:?}", o),
+            }
+        }
+
+        #[actix_web::test]
+        async fn user_can_not_update_another_profile() {
+            let Some(conn) = connect().await else { return };
+            let owner = insert_user(&conn, Role::User).await;
+            let other = insert_user(&conn, Role::User).await;
+
+            let action = Action::UpdateProfile { user_id: owner.auth.id };
+
+            assert_forbidden(authorize(&conn, &other.auth, action).await);
+            assert_eq!(authorize(&conn, &owner.auth, action).await, Ok(Grant::Allowed));
+        }
+
+        #[actix_web::test]
+        async fn moderator_can_not_update_another_profile() {
+            let Some(conn) = connect().await else { return };
+            let owner = insert_user(&conn, Role::User).await;
+            let moderator = insert_user(&conn, Role::Moderator).await;
+
+            assert_forbidden(authorize(&conn, &moderator.auth, Action::UpdateProfile { user_id: owner.auth.id }).await);
+        }
+
+        #[actix_web::test]
+        async fn user_can_not_remove_another_article() {
+            let Some(conn) = connect().await else { return };
+            let author = insert_user(&conn, Role::User).await;
+            let other = insert_user(&conn, Role::User).await;
+            let article_id = insert_article(&conn, author.auth.id).await;
+
+            let action = Action::RemoveArticle { article_id };
+
+            assert_forbidden(authorize(&conn, &other.auth, action).await);
+            assert_eq!(authorize(&conn, &author.auth, action).await, Ok(Grant::Allowed));
+        }
+
+        #[actix_web::test]
+        async fn removing_missing_article_is_not_found() {
+            let Some(conn) = connect().await else { return };
+            let user = insert_user(&conn, Role::User).await;
+
+            let result = authorize(&conn, &user.auth, Action::RemoveArticle { article_id: i32::MAX }).await;
+            assert_eq!(result.map_err(|e| e.code), Err(ErrorCode::ArticleNotFound));
+        }
+
+        #[actix_web::test]
+        async fn user_can_not_follow_self() {
+            let Some(conn) = connect().await else { return };
+            let user = insert_user(&conn, Role::User).await;
+            let author = insert_user(&conn, Role::User).await;
+
+            assert_forbidden(authorize(&conn, &user.auth, Action::FollowUser { author_id: user.auth.id }).await);
+            assert_eq!(authorize(&conn, &user.auth, Action::FollowUser { author_id: author.auth.id }).await, Ok(Grant::Allowed));
+        }
+
+        #[actix_web::test]
+        async fn user_can_not_remove_another_comment() {
+            let Some(conn) = connect().await else { return };
+            let author = insert_user(&conn, Role::User).await;
+            let other = insert_user(&conn, Role::User).await;
+            let article_id = insert_article(&conn, other.auth.id).await;
+            let comment_id = insert_comment(&conn, author.auth.id, article_id).await;
+
+            let action = Action::RemoveComment { comment_id };
+
+            // автор записи тоже не может удалять чужие комментарии
+            assert_forbidden(authorize(&conn, &other.auth, action).await);
+            assert_eq!(authorize(&conn, &author.auth, action).await, Ok(Grant::Allowed));
+        }
+
+        #[actix_web::test]
+        async fn nobody_but_author_can_edit_article() {
+            let Some(conn) = connect().await else { return };
+            let author = insert_user(&conn, Role::User).await;
+            let other = insert_user(&conn, Role::User).await;
+            let admin = insert_user(&conn, Role::Admin).await;
+            let article_id = insert_article(&conn, author.auth.id).await;
+
+            let action = Action::EditArticle { article_id };
+
+            assert_forbidden(authorize(&conn, &other.auth, action).await);
+            assert_forbidden(authorize(&conn, &admin.auth, action).await);
+            assert_eq!(authorize(&conn, &author.auth, action).await, Ok(Grant::Allowed));
+        }
+
+        #[actix_web::test]
+        async fn moderator_removes_another_content_with_privilege() {
+            let Some(conn) = connect().await else { return };
+            let author = insert_user(&conn, Role::User).await;
+            let moderator = insert_user(&conn, Role::Moderator).await;
+            let article_id = insert_article(&conn, author.auth.id).await;
+            let comment_id = insert_comment(&conn, author.auth.id, article_id).await;
+
+            assert_eq!(authorize(&conn, &moderator.auth, Action::RemoveArticle { article_id }).await, Ok(Grant::Privileged));
+            assert_eq!(authorize(&conn, &moderator.auth, Action::RemoveComment { comment_id }).await, Ok(Grant::Privileged));
+        }
+    }
+}
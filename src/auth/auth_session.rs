@@ -0,0 +1,118 @@
+pub mod session {
+    use std::future::Future;
+    use std::pin::Pin;
+    use actix_web::{web, FromRequest, HttpRequest};
+    use actix_web::dev::Payload;
+    use actix_web::http::header::AUTHORIZATION;
+    use base64::Engine;
+    use base64::engine::general_purpose;
+    use rand::RngCore;
+    use rand::rngs::OsRng;
+    use sha2::{Digest, Sha256};
+    use crate::error::api_error::ApiError;
+    use crate::postgresql::postgresql_manager::Connect;
+    use crate::postgresql::models::model_session::session::SessionTokens;
+    use crate::postgresql::models::model_user::user::Role;
+    use chrono::{DateTime, Duration, Utc};
+    use serde::Serialize;
+
+    /// Время жизни токена доступа - 1 час
+    pub const ACCESS_TOKEN_LIFETIME_MINUTES: i64 = 60;
+
+    /// Время жизни refresh токена (и сессии без активности) - 180 дней
+    pub const REFRESH_TOKEN_LIFETIME_DAYS: i64 = 180;
+
+    const TOKEN_SIZE_BYTES: usize = 32;
+
+    /// Пара токенов, которая отдается клиенту
+    #[derive(Clone, Eq, Hash, PartialEq, Debug, Serialize)]
+    pub struct TokenPair {
+        pub access_token: String,
+        pub expires_at: DateTime<Utc>,
+        pub refresh_token: String,
+        pub refresh_expires_at: DateTime<Utc>,
+    }
+
+    /// Аутентифицированный пользователь, извлекается из заголовка `Authorization: Bearer <token>`
+    #[derive(Clone, Copy, Eq, PartialEq, Debug)]
+    pub struct AuthUser {
+        pub id: i32,
+        pub session_id: i32,
+        pub role: Role,
+    }
+
+    /// Генерация нового непрозрачного токена
+    ///
+    /// ### Возвращает:
+    /// Случайную строку в base64 (url-safe, без паддинга)
+    fn generate_token() -> String {
+        let mut bytes = [0u8; TOKEN_SIZE_BYTES];
+        OsRng.fill_bytes(&mut bytes);
+
+        general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+    }
+
+    /// Выдача новой пары токенов
+    ///
+    /// ### Возвращает:
+    /// Кортеж из `TokenPair` для клиента и `SessionTokens` (хеши) для базы данных
+    pub fn issue_tokens() -> (TokenPair, SessionTokens) {
+        let now = Utc::now();
+        let pair = TokenPair {
+            access_token: generate_token(),
+            expires_at: now + Duration::minutes(ACCESS_TOKEN_LIFETIME_MINUTES),
+            refresh_token: generate_token(),
+            refresh_expires_at: now + Duration::days(REFRESH_TOKEN_LIFETIME_DAYS),
+        };
+
+        let tokens = SessionTokens {
+            access_hash: hash_token(&pair.access_token),
+            access_expires_at: pair.expires_at,
+            refresh_hash: hash_token(&pair.refresh_token),
+            refresh_expires_at: pair.refresh_expires_at,
+        };
+
+        (pair, tokens)
+    }
+
+    /// Хеш токена, который хранится в базе данных вместо самого токена
+    ///
+    /// ### Возвращает:
+    /// SHA-256 в hex (64 символа)
+    pub fn hash_token(token: &str) -> String {
+        format!("{:x}", Sha256::digest(token.as_bytes()))
+    }
+
+    fn bearer_token(req: &HttpRequest) -> Option<String> {
+        let header = req.headers().get(AUTHORIZATION)?.to_str().ok()?;
+        let token = header.strip_prefix("Bearer ")?.trim();
+
+        match token.is_empty() {
+            true => None,
+            false => Some(token.to_string()),
+        }
+    }
+
+    impl FromRequest for AuthUser {
+        type Error = ApiError;
+        type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+        fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+            let conn = req.app_data::<web::Data<Connect>>().cloned();
+            let token = bearer_token(req);
+
+            Box::pin(async move {
+                let (conn, token) = match (conn, token) {
+                    (Some(conn), Some(token)) => (conn, token),
+                    _ => return Err(ApiError::unauthorized()),
+                };
+
+                match conn.get_session_by_token_hash(&hash_token(&token)).await {
+                    Ok(o) => Ok(AuthUser { id: o.user_id, session_id: o.session_id, role: o.role }),
+                    Err(sqlx::Error::RowNotFound) => Err(ApiError::unauthorized()),
+                    Err(e) => Err(ApiError::internal("[AuthUser] >>> conn.get_session_by_token_hash", e)),
+                }
+            })
+        }
+    }
+}
@@ -0,0 +1,3 @@
+pub mod auth_password;
+pub mod auth_session;
+pub mod auth_policy;
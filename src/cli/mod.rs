@@ -0,0 +1,47 @@
+pub mod command {
+    /// Режим запуска приложения, выбирается флагами командной строки
+    #[derive(Clone, Copy, Eq, PartialEq, Debug)]
+    pub enum Command {
+        /// Применить миграции и запустить HTTP сервер (по умолчанию)
+        Serve,
+        /// `--migrate` - применить все миграции и выйти
+        Migrate,
+        /// `--rollback [N]` - откатить N последних миграций (по умолчанию 1) и выйти
+        Rollback(usize),
+        /// `--migrations-status` - вывести состояние миграций и выйти
+        MigrationsStatus,
+        /// `--migrate-media` - перенести картинки из базы данных в хранилище файлов и выйти
+        MigrateMedia,
+    }
+
+    pub const USAGE: &str = "\
+Usage: xamarin-news-api [--migrate | --rollback [N] | --migrations-status | --migrate-media]
+
+    (no flags)            apply pending migrations and start the HTTP server
+    --migrate             apply pending migrations and exit
+    --rollback [N]        roll back the last N migrations (default 1) and exit
+    --migrations-status   print applied and pending migrations and exit
+    --migrate-media       move images stored in the database to the media storage and exit";
+
+    /// Разбор аргументов командной строки (без имени программы)
+    ///
+    /// ### Возвращает:
+    /// Если [`Ok`], то [`Command`]. Иначе текст ошибки
+    pub fn parse_args<I>(args: I) -> Result<Command, String>
+    where I: IntoIterator<Item = String> {
+        let args = args.into_iter().collect::<Vec<String>>();
+
+        match args.iter().map(String::as_str).collect::<Vec<&str>>().as_slice() {
+            [] => Ok(Command::Serve),
+            ["--migrate"] => Ok(Command::Migrate),
+            ["--rollback"] => Ok(Command::Rollback(1)),
+            ["--rollback", steps] => match steps.parse::<usize>() {
+                Ok(o) if o > 0 => Ok(Command::Rollback(o)),
+                _ => Err(format!("Invalid number of migrations to roll back: {}", steps)),
+            },
+            ["--migrations-status"] => Ok(Command::MigrationsStatus),
+            ["--migrate-media"] => Ok(Command::MigrateMedia),
+            _ => Err(format!("Unknown arguments: {}", args.join(" "))),
+        }
+    }
+}
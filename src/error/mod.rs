@@ -0,0 +1,239 @@
+pub mod api_error {
+    use std::fmt;
+    use actix_web::{HttpResponse, ResponseError};
+    use actix_web::http::StatusCode;
+    use serde_json::{json, Map, Value};
+    use validator::{ValidationError, ValidationErrors};
+    use crate::logger::log::{Level, log};
+
+    /// Стабильный машиночитаемый код ошибки, который получает клиент в поле `code`
+    #[derive(Clone, Copy, Eq, Hash, PartialEq, Debug)]
+    pub enum ErrorCode {
+        InvalidBody,
+        InvalidQuery,
+        ValidationFailed,
+        InvalidCredentials,
+        InvalidRefreshToken,
+        Unauthorized,
+        Forbidden,
+        NotFound,
+        UserNotFound,
+        ArticleNotFound,
+        RevisionNotFound,
+        TagNotFound,
+        ImageNotFound,
+        UploadNotFound,
+        MediaNotFound,
+        CommentNotFound,
+        ReactionNotFound,
+        SessionNotFound,
+        LoginTaken,
+        AlreadyFollowing,
+        NotFollowing,
+        ReactionExists,
+        AlreadyPublished,
+        UploadOffsetMismatch,
+        UploadIncomplete,
+        UploadCompleted,
+        GalleryFull,
+        PayloadTooLarge,
+        InvalidImage,
+        ImageTooLarge,
+        Internal,
+    }
+
+    impl ErrorCode {
+        pub fn as_str(&self) -> &'static str {
+            match self {
+                ErrorCode::InvalidBody => "invalid_body",
+                ErrorCode::InvalidQuery => "invalid_query",
+                ErrorCode::ValidationFailed => "validation_failed",
+                ErrorCode::InvalidCredentials => "invalid_credentials",
+                ErrorCode::InvalidRefreshToken => "invalid_refresh_token",
+                ErrorCode::Unauthorized => "unauthorized",
+                ErrorCode::Forbidden => "forbidden",
+                ErrorCode::NotFound => "not_found",
+                ErrorCode::UserNotFound => "user_not_found",
+                ErrorCode::ArticleNotFound => "article_not_found",
+                ErrorCode::RevisionNotFound => "revision_not_found",
+                ErrorCode::TagNotFound => "tag_not_found",
+                ErrorCode::ImageNotFound => "image_not_found",
+                ErrorCode::UploadNotFound => "upload_not_found",
+                ErrorCode::MediaNotFound => "media_not_found",
+                ErrorCode::CommentNotFound => "comment_not_found",
+                ErrorCode::ReactionNotFound => "reaction_not_found",
+                ErrorCode::SessionNotFound => "session_not_found",
+                ErrorCode::LoginTaken => "login_taken",
+                ErrorCode::AlreadyFollowing => "already_following",
+                ErrorCode::NotFollowing => "not_following",
+                ErrorCode::ReactionExists => "reaction_exists",
+                ErrorCode::AlreadyPublished => "already_published",
+                ErrorCode::UploadOffsetMismatch => "upload_offset_mismatch",
+                ErrorCode::UploadIncomplete => "upload_incomplete",
+                ErrorCode::UploadCompleted => "upload_completed",
+                ErrorCode::GalleryFull => "gallery_full",
+                ErrorCode::PayloadTooLarge => "payload_too_large",
+                ErrorCode::InvalidImage => "invalid_image",
+                ErrorCode::ImageTooLarge => "image_too_large",
+                ErrorCode::Internal => "internal_error",
+            }
+        }
+
+        pub fn status(&self) -> StatusCode {
+            match self {
+                ErrorCode::InvalidBody
+                | ErrorCode::InvalidQuery
+                | ErrorCode::ValidationFailed
+                | ErrorCode::InvalidImage => StatusCode::BAD_REQUEST,
+                ErrorCode::InvalidCredentials
+                | ErrorCode::InvalidRefreshToken
+                | ErrorCode::Unauthorized => StatusCode::UNAUTHORIZED,
+                ErrorCode::Forbidden => StatusCode::FORBIDDEN,
+                ErrorCode::NotFound
+                | ErrorCode::UserNotFound
+                | ErrorCode::ArticleNotFound
+                | ErrorCode::RevisionNotFound
+                | ErrorCode::TagNotFound
+                | ErrorCode::ImageNotFound
+                | ErrorCode::UploadNotFound
+                | ErrorCode::MediaNotFound
+                | ErrorCode::CommentNotFound
+                | ErrorCode::ReactionNotFound
+                | ErrorCode::SessionNotFound => StatusCode::NOT_FOUND,
+                ErrorCode::LoginTaken
+                | ErrorCode::AlreadyFollowing
+                | ErrorCode::NotFollowing
+                | ErrorCode::ReactionExists
+                | ErrorCode::AlreadyPublished
+                | ErrorCode::UploadOffsetMismatch
+                | ErrorCode::UploadIncomplete
+                | ErrorCode::UploadCompleted
+                | ErrorCode::GalleryFull => StatusCode::CONFLICT,
+                ErrorCode::PayloadTooLarge
+                | ErrorCode::ImageTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+                ErrorCode::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+            }
+        }
+    }
+
+    /// Ошибка API. Отдается клиенту как
+    /// `{"status": "error", "code": "...", "message": "..."}` с соответствующим HTTP статусом.
+    /// Для ошибок валидации добавляется `errors` - список ошибок по каждому полю
+    #[derive(Clone, Eq, PartialEq, Debug)]
+    pub struct ApiError {
+        pub code: ErrorCode,
+        pub message: String,
+        pub errors: Option<Value>,
+    }
+
+    impl ApiError {
+        pub fn new<T>(code: ErrorCode, message: T) -> Self
+        where T: Into<String> {
+            ApiError { code, message: message.into(), errors: None }
+        }
+
+        pub fn bad_request<T>(message: T) -> Self
+        where T: Into<String> {
+            ApiError::new(ErrorCode::InvalidBody, message)
+        }
+
+        pub fn unauthorized() -> Self {
+            ApiError::new(ErrorCode::Unauthorized, "Unauthorized!")
+        }
+
+        pub fn forbidden<T>(message: T) -> Self
+        where T: Into<String> {
+            ApiError::new(ErrorCode::Forbidden, message)
+        }
+
+        pub fn payload_too_large() -> Self {
+            ApiError::new(ErrorCode::PayloadTooLarge, "Request overflow!")
+        }
+
+        /// Внутренняя ошибка сервера. Подробности логируются, клиенту отдается только код.
+        /// ### Принимает:
+        /// Источник ошибки для лога (например `[GET][get-articles] >>> conn.get_articles`), саму ошибку
+        pub fn internal<E>(source: &str, error: E) -> Self
+        where E: fmt::Display {
+            log(Level::Error, source, &format!("Handle: {}", error));
+
+            ApiError::new(ErrorCode::Internal, "Error")
+        }
+    }
+
+    impl fmt::Display for ApiError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{}: {}", self.code.as_str(), self.message)
+        }
+    }
+
+    impl ResponseError for ApiError {
+        fn status_code(&self) -> StatusCode {
+            self.code.status()
+        }
+
+        fn error_response(&self) -> HttpResponse {
+            let mut body = json!({
+                "status": "error",
+                "code": self.code.as_str(),
+                "message": self.message,
+            });
+
+            if let Some(errors) = &self.errors {
+                body["errors"] = errors.clone();
+            }
+
+            HttpResponse::build(self.status_code()).json(body)
+        }
+    }
+
+    impl From<ValidationErrors> for ApiError {
+        fn from(errors: ValidationErrors) -> Self {
+            let mut fields = Map::new();
+            for (field, field_errors) in errors.field_errors() {
+                let list = field_errors.iter()
+                    .map(|e| json!({
+                        "code": e.code,
+                        "message": validation_message(e),
+                    }))
+                    .collect::<Vec<Value>>();
+
+                fields.insert(field.to_string(), Value::Array(list));
+            }
+
+            ApiError {
+                code: ErrorCode::ValidationFailed,
+                message: "Validation failed!".to_string(),
+                errors: Some(Value::Object(fields)),
+            }
+        }
+    }
+
+    fn validation_message(error: &ValidationError) -> String {
+        if let Some(message) = &error.message {
+            return message.to_string();
+        }
+
+        let min = error.params.get("min").map(format_param);
+        let max = error.params.get("max").map(format_param);
+
+        match (error.code.as_ref(), min, max) {
+            ("length", Some(min), Some(max)) => format!("Length must be between {} and {}", min, max),
+            ("length", Some(min), None) => format!("Length must be at least {}", min),
+            ("length", None, Some(max)) => format!("Length must be at most {}", max),
+            ("range", Some(min), Some(max)) => format!("Value must be between {} and {}", min, max),
+            ("range", Some(min), None) => format!("Value must be at least {}", min),
+            ("range", None, Some(max)) => format!("Value must be at most {}", max),
+            ("regex", _, _) => "Invalid format".to_string(),
+            (code, _, _) => code.to_string(),
+        }
+    }
+
+    /// Границы `range` приходят как `f64` - целые числа выводим без дробной части
+    fn format_param(value: &Value) -> String {
+        match value.as_f64() {
+            Some(o) if o.fract() == 0.0 => format!("{}", o as i64),
+            _ => value.to_string(),
+        }
+    }
+}
@@ -1,8 +1,14 @@
 use actix_web::{HttpServer, App, web, HttpResponse};
 use dotenv::dotenv;
 use crate::postgresql::postgresql_manager::Connect;
+use crate::cli::command::{parse_args, Command, USAGE};
+use crate::error::api_error::{ApiError, ErrorCode};
+use crate::tasks::background::{spawn_scheduled_publisher, spawn_upload_collector};
+use crate::storage::storage_media::media::{migrate_database_media, storage_from_env, MediaStorage};
+use crate::storage::storage_avatar::avatar::AvatarSettings;
+use crate::storage::storage_staging::staging::Staging;
+use std::time::Duration;
 use crate::services::service_user::user::{
-    get_profile_avatar,
     insert_user,
     login_user,
     set_profile_avatar,
@@ -18,19 +24,87 @@ use crate::services::service_user::user::{
 use crate::services::service_article::article::{
     insert_article,
     remove_article,
+    get_article,
     get_articles,
+    get_latest_articles,
+    get_trending_articles,
+    search_articles,
+    publish_article,
+    get_drafts,
+    update_article,
+    get_article_revisions,
+    restore_article_revision,
     get_articles_from_user,
     get_article_comments,
     insert_article_comment,
+    remove_article_comment,
     insert_reaction_for_article,
     remove_reaction_for_article,
     get_reaction_for_article_by_user,
+    add_article_media,
+    reorder_article_media,
+    remove_article_media,
+    FeedSettings,
 };
 
+use crate::services::service_session::session::{
+    refresh_token,
+    logout,
+    get_sessions,
+    revoke_session,
+    revoke_all_sessions,
+};
+
+use crate::services::service_tag::tag::{
+    autocomplete_tags,
+    get_trending_tags,
+    get_articles_by_tag,
+    get_followed_tags,
+    follow_tag,
+    unfollow_tag,
+};
+
+use crate::services::service_image::image::get_image;
+
+use crate::services::service_upload::upload::{
+    create_upload,
+    upload_chunk,
+    get_upload,
+    finalize_upload,
+};
+
+use crate::services::service_admin::admin::{
+    set_user_role,
+    get_moderation_log,
+};
+
+/// Как часто фоновая задача публикует запланированные записи, если не задано `PUBLISH_SCHEDULER_INTERVAL_SECONDS`
+const DEFAULT_PUBLISH_SCHEDULER_INTERVAL_SECONDS: u64 = 30;
+
+/// Как часто удаляются устаревшие загрузки, если не задано `UPLOAD_COLLECTOR_INTERVAL_SECONDS`
+const DEFAULT_UPLOAD_COLLECTOR_INTERVAL_SECONDS: u64 = 600;
+
+/// Сколько живет незавершенная загрузка после последнего изменения, если не задано `UPLOAD_TTL_HOURS`
+const DEFAULT_UPLOAD_TTL_HOURS: i64 = 24;
+
+/// Сколько действует media ID завершенной загрузки, если не задано `UPLOAD_MEDIA_TTL_DAYS`
+const DEFAULT_UPLOAD_MEDIA_TTL_DAYS: i64 = 30;
+
+/// За сколько последних часов выбираются популярные записи, если не задано `TRENDING_WINDOW_HOURS`
+const DEFAULT_TRENDING_WINDOW_HOURS: i64 = 72;
+
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     dotenv().ok();
 
+    let command = match parse_args(std::env::args().skip(1)) {
+        Ok(o) => o,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            std::process::exit(2);
+        },
+    };
+
     let localhost = std::env::var("LOCALHOST").expect("LOCALHOST is invalid!");
     let localhost_port = std::env::var("LOCALHOST_PORT").expect("LOCALHOST_PORT is invalid!")
         .parse::<u16>()
@@ -50,18 +124,106 @@ async fn main() -> std::io::Result<()> {
 
     println!("[PostgresSQL] Connected!");
 
-    postgres.create_tables()
+    let storage = storage_from_env().expect("[Media] Invalid media storage config!");
+
+    match command {
+        Command::Serve => (),
+        Command::Migrate => {
+            let applied = postgres.migrate_up()
+                .await
+                .expect("[PostgresSQL] Error apply migrations!");
+
+            println!("[PostgresSQL] Applied migrations: {:?}", applied);
+            return Ok(());
+        },
+        Command::Rollback(steps) => {
+            let reverted = postgres.migrate_down(steps)
+                .await
+                .expect("[PostgresSQL] Error roll back migrations!");
+
+            println!("[PostgresSQL] Rolled back migrations: {:?}", reverted);
+            return Ok(());
+        },
+        Command::MigrationsStatus => {
+            let status = postgres.migration_status()
+                .await
+                .expect("[PostgresSQL] Error get migrations status!");
+
+            for migration in status {
+                match migration.applied_at {
+                    Some(o) => println!("{:>4} {:<32} applied {}", migration.version, migration.name, o),
+                    None => println!("{:>4} {:<32} pending", migration.version, migration.name),
+                }
+            }
+            return Ok(());
+        },
+        Command::MigrateMedia => {
+            let migrated = migrate_database_media(&postgres, storage.as_ref())
+                .await
+                .expect("[Media] Error migrate images to media storage!");
+
+            println!("[Media] Migrated images: {}", migrated);
+            return Ok(());
+        },
+    }
+
+    let applied = postgres.migrate_up()
         .await
-        .expect("[PostgresSQL] Error create tables!");
+        .expect("[PostgresSQL] Error apply migrations!");
+
+    if !applied.is_empty() {
+        println!("[PostgresSQL] Applied migrations: {:?}", applied);
+    }
+
+    let publish_interval = std::env::var("PUBLISH_SCHEDULER_INTERVAL_SECONDS")
+        .ok()
+        .and_then(|o| o.parse::<u64>().ok())
+        .unwrap_or(DEFAULT_PUBLISH_SCHEDULER_INTERVAL_SECONDS);
+
+    spawn_scheduled_publisher(postgres.clone(), Duration::from_secs(publish_interval));
+
+    let upload_collector_interval = std::env::var("UPLOAD_COLLECTOR_INTERVAL_SECONDS")
+        .ok()
+        .and_then(|o| o.parse::<u64>().ok())
+        .unwrap_or(DEFAULT_UPLOAD_COLLECTOR_INTERVAL_SECONDS);
+    let upload_ttl_hours = std::env::var("UPLOAD_TTL_HOURS")
+        .ok()
+        .and_then(|o| o.parse::<i64>().ok())
+        .unwrap_or(DEFAULT_UPLOAD_TTL_HOURS);
+    let upload_media_ttl_days = std::env::var("UPLOAD_MEDIA_TTL_DAYS")
+        .ok()
+        .and_then(|o| o.parse::<i64>().ok())
+        .unwrap_or(DEFAULT_UPLOAD_MEDIA_TTL_DAYS);
+
+    let staging = web::Data::new(Staging::from_env());
+
+    spawn_upload_collector(
+        postgres.clone(),
+        staging.clone(),
+        Duration::from_secs(upload_collector_interval),
+        chrono::Duration::hours(upload_ttl_hours),
+        chrono::Duration::days(upload_media_ttl_days),
+    );
+
+    let trending_window_hours = std::env::var("TRENDING_WINDOW_HOURS")
+        .ok()
+        .and_then(|o| o.parse::<i64>().ok())
+        .unwrap_or(DEFAULT_TRENDING_WINDOW_HOURS);
 
     let data = web::Data::new(postgres);
+    let storage: web::Data<dyn MediaStorage> = web::Data::from(storage);
+    let feed_settings = web::Data::new(FeedSettings { trending_window_hours });
+    let avatar_settings = web::Data::new(AvatarSettings::from_env());
 
     HttpServer::new(move || {
         App::new()
             .app_data(data.clone())
+            .app_data(storage.clone())
+            .app_data(staging.clone())
+            .app_data(feed_settings.clone())
+            .app_data(avatar_settings.clone())
             // User service
             .service(insert_user)
-            .service(get_profile_avatar)
             .service(login_user)
             .service(set_profile_avatar)
             .service(user_info)
@@ -71,18 +233,56 @@ async fn main() -> std::io::Result<()> {
             .service(remove_following_user)
             .service(get_popular_users)
             .service(find_user_by_key_words)
+            // Session service
+            .service(refresh_token)
+            .service(logout)
+            .service(get_sessions)
+            .service(revoke_session)
+            .service(revoke_all_sessions)
             // Article service
             .service(insert_article)
             .service(remove_article)
+            .service(get_article)
             .service(get_articles)
+            .service(get_latest_articles)
+            .service(get_trending_articles)
+            .service(search_articles)
+            .service(publish_article)
+            .service(get_drafts)
+            .service(update_article)
+            .service(get_article_revisions)
+            .service(restore_article_revision)
+            .service(add_article_media)
+            .service(reorder_article_media)
+            .service(remove_article_media)
             .service(get_articles_from_user)
             .service(insert_article_comment)
+            .service(remove_article_comment)
             .service(get_article_comments)
             .service(insert_reaction_for_article)
             .service(remove_reaction_for_article)
             .service(get_reaction_for_article_by_user)
-            .default_service(web::to(|| {
-                HttpResponse::NotFound()
+            // Tag service
+            .service(autocomplete_tags)
+            .service(get_trending_tags)
+            .service(get_articles_by_tag)
+            .service(get_followed_tags)
+            .service(follow_tag)
+            .service(unfollow_tag)
+            // Image service
+            .service(get_image)
+            // Upload service
+            .service(create_upload)
+            .service(upload_chunk)
+            .service(get_upload)
+            .service(finalize_upload)
+            // Admin service
+            .service(web::scope("/admin")
+                .service(set_user_role)
+                .service(get_moderation_log)
+            )
+            .default_service(web::to(|| async {
+                Err::<HttpResponse, ApiError>(ApiError::new(ErrorCode::NotFound, "Not found!"))
             }))
         })
         .bind((localhost, localhost_port))?
@@ -92,4 +292,14 @@ async fn main() -> std::io::Result<()> {
 
 mod postgresql;
 mod services;
-mod logger;
\ No newline at end of file
+mod logger;
+mod auth;
+mod cli;
+mod error;
+mod search;
+mod tasks;
+mod storage;
+mod markdown;
+
+#[cfg(test)]
+mod testing;
@@ -0,0 +1,212 @@
+pub mod render {
+    use std::collections::HashSet;
+    use once_cell::sync::Lazy;
+    use pulldown_cmark::{html, Event, Options, Parser, TagEnd};
+
+    /// Максимальная длина `crop_description` в символах, без многоточия
+    pub const CROP_DESCRIPTION_LENGTH: usize = 150;
+
+    /// Скорость чтения для `reading_time`, слов в минуту
+    pub const WORDS_PER_MINUTE: usize = 200;
+
+    /// Разрешенные схемы ссылок и картинок, остальные (`javascript:`, `data:` и т.д.) вырезаются
+    const URL_SCHEMES: [&str; 3] = ["http", "https", "mailto"];
+
+    static SANITIZER: Lazy<ammonia::Builder<'static>> = Lazy::new(|| {
+        let mut builder = ammonia::Builder::default();
+        builder
+            .url_schemes(HashSet::from(URL_SCHEMES))
+            .link_rel(Some("noopener noreferrer nofollow"));
+        builder
+    });
+
+    /// Описание записи во всех хранимых формах
+    #[derive(Clone, Eq, Hash, PartialEq, Debug)]
+    pub struct RenderedMarkdown {
+        /// HTML без скриптов, обработчиков событий и опасных ссылок
+        pub html: String,
+        /// Текст без разметки, блоки разделены переводом строки
+        pub text: String,
+        /// Начало текста для превью, обрезанное по границе слова
+        pub crop: String,
+        /// Время чтения в минутах, не меньше одной
+        pub reading_time: i32,
+    }
+
+    /// Отрисовка Markdown в безопасный HTML и текст без разметки
+    /// ### Принимает:
+    /// Исходный текст в Markdown
+    ///
+    /// ### Возвращает:
+    /// [`RenderedMarkdown`]
+    pub fn render_markdown(source: &str) -> RenderedMarkdown {
+        let options = Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH;
+
+        let mut unsafe_html = String::new();
+        html::push_html(&mut unsafe_html, Parser::new_ext(source, options));
+
+        let text = plain_text(Parser::new_ext(source, options));
+        let crop = crop_text(&text, CROP_DESCRIPTION_LENGTH);
+        let reading_time = text.split_whitespace().count().div_ceil(WORDS_PER_MINUTE).max(1);
+
+        RenderedMarkdown {
+            html: SANITIZER.clean(&unsafe_html).to_string(),
+            text,
+            crop,
+            reading_time: i32::try_from(reading_time).unwrap_or(i32::MAX),
+        }
+    }
+
+    /// Текст из событий Markdown. Встроенный HTML пропускается
+    fn plain_text<'a>(events: impl Iterator<Item = Event<'a>>) -> String {
+        let mut text = String::new();
+
+        for event in events {
+            match event {
+                Event::Text(o) | Event::Code(o) => text.push_str(&o),
+                Event::SoftBreak | Event::HardBreak => text.push(' '),
+                Event::End(TagEnd::Paragraph | TagEnd::Heading(_) | TagEnd::Item | TagEnd::CodeBlock | TagEnd::TableRow | TagEnd::TableHead) => {
+                    text.push('\n')
+                },
+                Event::End(TagEnd::TableCell) => text.push(' '),
+                _ => (),
+            }
+        }
+
+        text.lines()
+            .map(str::trim)
+            .filter(|o| !o.is_empty())
+            .collect::<Vec<&str>>()
+            .join("\n")
+    }
+
+    /// Превью: пробелы схлопываются, текст длиннее `length` обрезается по последнему целому слову
+    fn crop_text(text: &str, length: usize) -> String {
+        let text = text.split_whitespace().collect::<Vec<&str>>().join(" ");
+
+        if text.chars().count() <= length {
+            return text;
+        }
+
+        let cut = text.char_indices().nth(length).map_or(text.len(), |o| o.0);
+        let head = &text[..cut];
+
+        // если обрезка пришлась на середину слова, то слово отбрасывается целиком
+        let crop = match text[cut..].starts_with(' ') {
+            true => head,
+            false => head.rfind(' ').map_or(head, |o| &head[..o]),
+        };
+
+        format!("{}...", crop.trim_end_matches(|o: char| o.is_whitespace() || ",.;:!?-—".contains(o)))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn script_is_removed() {
+            let rendered = render_markdown("<script>alert(1)</script>\n\nText");
+
+            assert!(!rendered.html.contains("<script"), "{}", rendered.html);
+            assert!(!rendered.html.contains("alert(1)"), "{}", rendered.html);
+            assert!(rendered.html.contains("Text"));
+        }
+
+        #[test]
+        fn event_handlers_are_removed() {
+            let rendered = render_markdown("<img src=\"https://example.com/a.png\" onerror=\"alert(1)\">");
+
+            assert!(!rendered.html.contains("onerror"), "{}", rendered.html);
+            assert!(rendered.html.contains("src=\"https://example.com/a.png\""), "{}", rendered.html);
+        }
+
+        #[test]
+        fn javascript_links_are_removed() {
+            let rendered = render_markdown("[click](javascript:alert(1)) <a href=\"JavaScript:alert(1)\">html</a>");
+
+            assert!(!rendered.html.to_lowercase().contains("javascript:"), "{}", rendered.html);
+            assert!(rendered.html.contains("click"));
+        }
+
+        #[test]
+        fn data_urls_are_removed() {
+            let rendered = render_markdown("![image](data:image/png;base64,AAAA) [page](data:text/html,<script>alert(1)</script>)");
+
+            assert!(!rendered.html.contains("data:"), "{}", rendered.html);
+            assert!(!rendered.html.contains("<script"), "{}", rendered.html);
+        }
+
+        #[test]
+        fn links_get_rel() {
+            let rendered = render_markdown("[site](https://example.com)");
+
+            assert!(rendered.html.contains("href=\"https://example.com\""), "{}", rendered.html);
+            assert!(rendered.html.contains("rel=\"noopener noreferrer nofollow\""), "{}", rendered.html);
+        }
+
+        #[test]
+        fn text_skips_markup_and_html() {
+            let rendered = render_markdown("# Title\n\nSome **bold** <b>html</b> and `code`");
+
+            assert_eq!(rendered.text, "Title\nSome bold html and code");
+        }
+
+        #[test]
+        fn crop_keeps_text_of_exact_length() {
+            let text = "a".repeat(CROP_DESCRIPTION_LENGTH);
+
+            assert_eq!(crop_text(&text, CROP_DESCRIPTION_LENGTH), text);
+        }
+
+        #[test]
+        fn crop_at_word_boundary() {
+            let word = "a".repeat(CROP_DESCRIPTION_LENGTH);
+            let text = format!("{} b", word);
+
+            assert_eq!(crop_text(&text, CROP_DESCRIPTION_LENGTH), format!("{}...", word));
+        }
+
+        #[test]
+        fn crop_drops_cut_word() {
+            let text = format!("{} bcdef", "a".repeat(CROP_DESCRIPTION_LENGTH - 2));
+
+            assert_eq!(crop_text(&text, CROP_DESCRIPTION_LENGTH), format!("{}...", "a".repeat(CROP_DESCRIPTION_LENGTH - 2)));
+        }
+
+        #[test]
+        fn crop_of_single_long_word() {
+            let text = "x".repeat(200);
+
+            assert_eq!(crop_text(&text, CROP_DESCRIPTION_LENGTH), format!("{}...", "x".repeat(CROP_DESCRIPTION_LENGTH)));
+        }
+
+        #[test]
+        fn crop_of_cyrillic_text() {
+            // 6 символов на слово с пробелом, 25 слов ровно заполняют 150 символов
+            let text = "слово ".repeat(30);
+            let crop = crop_text(&text, CROP_DESCRIPTION_LENGTH);
+
+            assert_eq!(crop, format!("{}...", vec!["слово"; 25].join(" ")));
+            assert!(crop.chars().count() <= CROP_DESCRIPTION_LENGTH + 3);
+        }
+
+        #[test]
+        fn crop_trims_punctuation_and_collapses_whitespace() {
+            let text = format!("{},   {}", "a".repeat(CROP_DESCRIPTION_LENGTH - 10), "b".repeat(20));
+
+            assert_eq!(crop_text(&text, CROP_DESCRIPTION_LENGTH), format!("{}...", "a".repeat(CROP_DESCRIPTION_LENGTH - 10)));
+        }
+
+        #[test]
+        fn reading_time_of_empty_body() {
+            assert_eq!(render_markdown("").reading_time, 1);
+        }
+
+        #[test]
+        fn reading_time_rounds_up() {
+            assert_eq!(render_markdown(&"word ".repeat(WORDS_PER_MINUTE)).reading_time, 1);
+            assert_eq!(render_markdown(&"word ".repeat(WORDS_PER_MINUTE + 1)).reading_time, 2);
+        }
+    }
+}
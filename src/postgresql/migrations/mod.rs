@@ -0,0 +1,244 @@
+pub mod migration {
+    use std::fmt;
+    use chrono::{DateTime, Utc};
+    use serde::Serialize;
+    use sqlx::{Executor, Row};
+    use crate::postgresql::postgresql_manager::Connect;
+
+    /// Ключ advisory-блокировки, чтобы два экземпляра сервера не применяли миграции одновременно
+    const MIGRATION_LOCK_KEY: i64 = 0x0078_616d_6172_696e;
+
+    /// Версионированная миграция схемы базы данных
+    pub struct Migration {
+        pub version: i64,
+        pub name: &'static str,
+        pub up: &'static str,
+        pub down: &'static str,
+    }
+
+    macro_rules! migration {
+        ($version:expr, $name:literal) => {
+            Migration {
+                version: $version,
+                name: $name,
+                up: include_str!(concat!("../../../migrations/", $name, ".up.sql")),
+                down: include_str!(concat!("../../../migrations/", $name, ".down.sql")),
+            }
+        };
+    }
+
+    /// Все миграции по порядку. Новые миграции добавляются только в конец.
+    pub const MIGRATIONS: &[Migration] = &[
+        migration!(1, "0001_initial"),
+        migration!(2, "0002_password_hash"),
+        migration!(3, "0003_sessions"),
+        migration!(4, "0004_roles"),
+        migration!(5, "0005_user_search_trgm"),
+        migration!(6, "0006_article_image_bytea"),
+        migration!(7, "0007_article_search"),
+        migration!(8, "0008_pagination_indexes"),
+        migration!(9, "0009_article_revisions"),
+        migration!(10, "0010_article_status"),
+        migration!(11, "0011_tags"),
+        migration!(12, "0012_feed_indexes"),
+        migration!(13, "0013_images"),
+        migration!(14, "0014_media_storage"),
+        migration!(15, "0015_uploads"),
+        migration!(16, "0016_articles_media"),
+        migration!(17, "0017_markdown"),
+        migration!(18, "0018_long_articles"),
+        migration!(19, "0019_image_references"),
+    ];
+
+    /// Версия схемы, которую поддерживает эта сборка
+    pub fn latest_version() -> i64 {
+        MIGRATIONS.last().map_or(0, |o| o.version)
+    }
+
+    #[derive(Clone, Eq, PartialEq, Debug, Serialize)]
+    pub struct MigrationStatus {
+        pub version: i64,
+        pub name: &'static str,
+        pub applied_at: Option<DateTime<Utc>>,
+    }
+
+    #[derive(Debug)]
+    pub enum MigrationError {
+        /// Схема базы данных новее, чем эта сборка умеет работать
+        NewerSchema { database: i64, supported: i64 },
+        /// Миграция не найдена среди известных этой сборке
+        UnknownVersion(i64),
+        Database(sqlx::Error),
+    }
+
+    impl fmt::Display for MigrationError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                MigrationError::NewerSchema { database, supported } => write!(f,
+                    "database schema version {} is newer than supported version {}", database, supported
+                ),
+                MigrationError::UnknownVersion(version) => write!(f,
+                    "database has applied migration {} unknown to this build", version
+                ),
+                MigrationError::Database(e) => write!(f, "{}", e),
+            }
+        }
+    }
+
+    impl From<sqlx::Error> for MigrationError {
+        fn from(e: sqlx::Error) -> Self {
+            MigrationError::Database(e)
+        }
+    }
+
+    impl Connect {
+        /// Создаем таблицу учета миграций, если ее нет
+        async fn create_migrations_table(&self) -> Result<(), sqlx::Error> {
+            self.pool.execute("
+                CREATE TABLE IF NOT EXISTS schema_migrations (
+                    version int8 PRIMARY KEY,
+                    name varchar(128) NOT NULL,
+                    applied_at timestamptz NOT NULL default now()::timestamp with time zone::timestamp
+                );
+            ").await?;
+
+            Ok(())
+        }
+
+        /// Получение примененных версий миграций
+        ///
+        /// ### Возвращает:
+        /// Если [`Ok`], то `Vec<(i64, DateTime<Utc>)>` по возрастанию версии. При ошибки [`sqlx::Error`]
+        async fn applied_migrations(&self) -> Result<Vec<(i64, DateTime<Utc>)>, sqlx::Error> {
+            self.create_migrations_table().await?;
+
+            let rows = sqlx::query("
+                SELECT version, applied_at
+                FROM schema_migrations
+                ORDER BY version;
+            ")
+                .fetch_all(&self.pool).await?;
+
+            rows.iter()
+                .map(|o| Ok((o.try_get("version")?, o.try_get("applied_at")?)))
+                .collect()
+        }
+
+        /// Проверка, что схема базы данных не новее, чем поддерживает эта сборка
+        ///
+        /// ### Возвращает:
+        /// Если [`Ok`], то текущая версия схемы. Иначе [`MigrationError`]
+        pub async fn check_schema(&self) -> Result<i64, MigrationError> {
+            let applied = self.applied_migrations().await?;
+            let current = applied.last().map_or(0, |o| o.0);
+
+            if current > latest_version() {
+                return Err(MigrationError::NewerSchema { database: current, supported: latest_version() });
+            }
+
+            if let Some((version, _)) = applied.iter().find(|(v, _)| !MIGRATIONS.iter().any(|m| m.version == *v)) {
+                return Err(MigrationError::UnknownVersion(*version));
+            }
+
+            Ok(current)
+        }
+
+        /// Применение всех непримененных миграций, каждая в своей транзакции
+        ///
+        /// ### Возвращает:
+        /// Если [`Ok`], то список примененных версий. Иначе [`MigrationError`]
+        pub async fn migrate_up(&self) -> Result<Vec<i64>, MigrationError> {
+            self.check_schema().await?;
+
+            let mut applied = Vec::new();
+
+            for migration in MIGRATIONS {
+                let mut tx = self.pool.begin().await?;
+
+                sqlx::query("SELECT pg_advisory_xact_lock($1);")
+                    .bind(MIGRATION_LOCK_KEY)
+                    .execute(&mut tx).await?;
+
+                let exists = sqlx::query("SELECT 1 FROM schema_migrations WHERE version = $1;")
+                    .bind(migration.version)
+                    .fetch_optional(&mut tx).await?
+                    .is_some();
+
+                if exists {
+                    continue;
+                }
+
+                tx.execute(migration.up).await?;
+
+                sqlx::query("INSERT INTO schema_migrations (version, name) VALUES ($1, $2);")
+                    .bind(migration.version)
+                    .bind(migration.name)
+                    .execute(&mut tx).await?;
+
+                tx.commit().await?;
+                applied.push(migration.version);
+            }
+
+            Ok(applied)
+        }
+
+        /// Откат последних примененных миграций
+        /// ### Принимает:
+        /// Количество миграций для отката
+        ///
+        /// ### Возвращает:
+        /// Если [`Ok`], то список откаченных версий. Иначе [`MigrationError`]
+        pub async fn migrate_down(&self, steps: usize) -> Result<Vec<i64>, MigrationError> {
+            self.check_schema().await?;
+
+            let mut reverted = Vec::new();
+
+            for _ in 0..steps {
+                let mut tx = self.pool.begin().await?;
+
+                sqlx::query("SELECT pg_advisory_xact_lock($1);")
+                    .bind(MIGRATION_LOCK_KEY)
+                    .execute(&mut tx).await?;
+
+                let row = sqlx::query("SELECT max(version) AS version FROM schema_migrations;")
+                    .fetch_one(&mut tx).await?;
+
+                let version: i64 = match row.try_get::<Option<i64>, _>("version")? {
+                    Some(o) => o,
+                    None => break,
+                };
+
+                let migration = MIGRATIONS.iter()
+                    .find(|o| o.version == version)
+                    .ok_or(MigrationError::UnknownVersion(version))?;
+
+                tx.execute(migration.down).await?;
+
+                sqlx::query("DELETE FROM schema_migrations WHERE version = $1;")
+                    .bind(version)
+                    .execute(&mut tx).await?;
+
+                tx.commit().await?;
+                reverted.push(version);
+            }
+
+            Ok(reverted)
+        }
+
+        /// Состояние всех известных миграций
+        ///
+        /// ### Возвращает:
+        /// Если [`Ok`], то `Vec<MigrationStatus>`. При ошибки [`sqlx::Error`]
+        pub async fn migration_status(&self) -> Result<Vec<MigrationStatus>, sqlx::Error> {
+            let applied = self.applied_migrations().await?;
+
+            Ok(MIGRATIONS.iter()
+                .map(|m| MigrationStatus {
+                    version: m.version,
+                    name: m.name,
+                    applied_at: applied.iter().find(|o| o.0 == m.version).map(|o| o.1),
+                })
+                .collect())
+        }
+    }
+}
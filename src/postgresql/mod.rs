@@ -1,21 +1,62 @@
 pub mod postgresql_manager {
-    use base64::Engine;
-    use base64::engine::general_purpose;
-    use sqlx::{Executor, Pool, Postgres, postgres::PgPoolOptions, Row};
+    use std::future::Future;
+    use chrono::{DateTime, Utc};
+    use sqlx::{Pool, Postgres, Transaction, postgres::PgPoolOptions, Row};
     use super::models;
     use models::model_user::user::{
         User,
+        FullUser,
         RegisterUser,
     };
-    use crate::logger::log::{Level, log};
     use crate::postgresql::models::model_article::article::{
         Article,
+        ArticleMedia,
+        ArticleRevision,
+        ArticleSearch,
         Comment,
+        FoundArticle,
+        FullArticle,
         InsertArticle,
+        InsertArticleMedia,
         InsertComment,
-        InsertReaction
+        InsertReaction,
+        TrendingArticle,
+        UpdateArticle,
+        MAX_ARTICLE_MEDIA,
+    };
+    use crate::postgresql::models::model_user::user::{
+        FoundUser,
+        PopularUser,
+        Role,
+    };
+    use crate::search::transliteration::spellings as spellings_of;
+    use crate::markdown::render::RenderedMarkdown;
+    use crate::postgresql::models::model_session::session::{
+        RefreshOutcome,
+        Session,
+        SessionTokens,
+        SessionUser,
+    };
+    use crate::postgresql::models::model_moderation::moderation::ModerationRecord;
+    use crate::postgresql::models::model_image::image::{
+        Image,
+        StoredImage,
+        VisibleImage,
+    };
+    use crate::postgresql::models::model_upload::upload::{
+        AppendChunk,
+        Upload,
+    };
+    use crate::postgresql::models::model_tag::tag::{
+        normalize_tags,
+        Tag,
+    };
+    use crate::postgresql::models::model_page::page::{
+        DateCursor,
+        FollowersCursor,
+        Page,
+        PageRequest,
     };
-    use crate::postgresql::models::model_user::user::PopularUser;
 
 
     #[derive(Clone)]
@@ -57,78 +98,10 @@ pub mod postgresql_manager {
             Ok(Connect { pool })
         }
 
-        ///Создаем все нужные таблицы, если их нет.
-        pub async fn create_tables(&self) -> Result<(), sqlx::Error> {
-            self.pool.execute(r#"
-                CREATE TABLE IF NOT EXISTS users (
-                    id serial4 PRIMARY KEY,
-                    first_name varchar(64) NOT NULL,
-                    last_name varchar(64) NOT NULL,
-                    about varchar(256) NULL,
-                    password varchar(64) NOT NULL,
-                    login varchar(64) NOT NULL,
-                    full_avatar bytea NULL,
-                    crop_avatar bytea NULL,
-                    date_registration timestamptz NOT NULL default now()::timestamp with time zone::timestamp
-                );
-
-                CREATE TABLE IF NOT EXISTS articles (
-                    id serial4 PRIMARY KEY,
-                    author_id int4 NOT NULL REFERENCES users(id) ON DELETE CASCADE,
-                    image text NOT NULL,
-                    title varchar(64) NOT NULL,
-                    description varchar(1024) NOT NULL,
-                    publish_date timestamptz NOT NULL default now()::timestamp with time zone::timestamp
-                );
-
-                CREATE TABLE IF NOT EXISTS articles_comments (
-                    id serial4 PRIMARY KEY,
-                    users_id int4 NOT NULL REFERENCES users(id) ON DELETE CASCADE,
-                    articles_id int4 NOT NULL REFERENCES articles(id) ON DELETE CASCADE,
-                    publish_date timestamptz NOT NULL default now()::timestamp with time zone::timestamp,
-                    message varchar(1024) NOT NULL
-                );
-
-                CREATE TABLE IF NOT EXISTS users_followers (
-                    id serial4 PRIMARY KEY,
-                    users_author_id int4 NOT NULL REFERENCES users(id) ON DELETE CASCADE,
-                    users_follower_id int4 NOT NULL REFERENCES users(id) ON DELETE CASCADE,
-                    follow_date timestamptz NOT NULL default now()::timestamp with time zone::timestamp
-                );
-
-                CREATE TABLE IF NOT EXISTS reactions (
-                    id serial4 PRIMARY KEY,
-                    description varchar(16) NOT NULL
-                );
-
-                INSERT INTO reactions (description)
-                SELECT 'Нравится'
-                WHERE NOT EXISTS (
-                    SELECT description FROM reactions WHERE description = 'Нравится'
-                );
-
-                INSERT INTO reactions (description)
-                SELECT 'Не нравится'
-                WHERE NOT EXISTS (
-                    SELECT description FROM reactions WHERE description = 'Не нравится'
-                );
-
-                CREATE TABLE IF NOT EXISTS articles_reactions (
-                    id serial4 PRIMARY KEY,
-                    users_id int4 NOT NULL REFERENCES users(id) ON DELETE CASCADE,
-                    articles_id int4 NOT NULL REFERENCES articles(id) ON DELETE CASCADE,
-                    reactions_id int4 NOT NULL REFERENCES reactions(id) ON DELETE CASCADE,
-                    date timestamptz NOT NULL default now()::timestamp with time zone::timestamp
-                );
-            "#).await?;
-
-            Ok(())
-        }
-
         /// Создаем пользователя в базе данных
         ///
         /// ### Принимает:
-        /// Структуру `RegisterUser`
+        /// Структуру `RegisterUser`, в поле `password` которой уже лежит хеш пароля
         ///
         /// ### Возвращает:
         /// Если [`Ok`], то `()`. При ошибки [`sqlx::Error`]
@@ -168,73 +141,530 @@ pub mod postgresql_manager {
             Ok(row.unwrap().try_get::<i32, _>("id").is_ok())
         }
 
-        /// Получение данных пользователя по логину и паролю.
+        /// Проверяет, есть ли пользователь с указанным ID в базе данных.
         /// ### Принимает:
-        /// Логин и пароль пользователя
+        /// ID пользователя
         ///
         /// ### Возвращает:
-        /// Если [`Ok`], то вернется структура `User`. При ошибки [`sqlx::Error`]
-        pub async fn get_user_by_login_and_pass(&self, login: &str, password: &str) -> Result<User, sqlx::Error> {
-            let row = sqlx::query_as::<_, User>("
-                SELECT id AS user_id, first_name, last_name, about, password, login, full_avatar, crop_avatar, date_registration
-                FROM users
-                WHERE login = $1 AND password = $2
+        /// Если [`Ok`], то `true` - пользователь найден, иначе `false`. В случае ошибки вернется [`slqx::Error`]
+        pub async fn exist_user_by_id(&self, user_id: i32) -> Result<bool, sqlx::Error> {
+            let row = sqlx::query("
+                SELECT EXISTS (SELECT 1 FROM users WHERE id = $1) AS exists;
             ")
-                .bind(login)
-                .bind(password)
+                .bind(user_id)
                 .fetch_one(&self.pool).await?;
 
-            Ok(row)
+            row.try_get("exists")
         }
 
-        /// Получение аватарок пользователя
+        /// Получение полных данных пользователя (вместе с хешем пароля) по логину.
         /// ### Принимает:
         /// Логин пользователя
         ///
         /// ### Возвращает:
-        /// Если [`Ok`], то кортеж из двух String. При ошибки [`sqlx::Error`]
-        pub async fn get_avatar_by_login(&self, login: &str) -> Result<(String, String), sqlx::Error> {
-            let row = sqlx::query("
-                SELECT crop_avatar, full_avatar
+        /// Если [`Ok`], то вернется структура `FullUser`. При ошибки [`sqlx::Error`]
+        pub async fn get_full_user_by_login(&self, login: &str) -> Result<FullUser, sqlx::Error> {
+            let row = sqlx::query_as::<_, FullUser>("
+                SELECT id AS user_id, first_name, last_name, about, password, login, full_avatar_id AS full_avatar, crop_avatar_id AS crop_avatar, date_registration
                 FROM users
                 WHERE login = $1
             ")
                 .bind(login)
                 .fetch_one(&self.pool).await?;
 
-            let crop_avatar: String = match row.try_get("crop_avatar") {
-                Ok(o) => o,
-                Err(_) => return Ok((String::new(), String::new())),
-            };
+            Ok(row)
+        }
 
-            let full_avatar: String = match row.try_get("full_avatar") {
-                Ok(o) => o,
-                Err(_) => return Ok((String::new(), String::new())),
-            };
+        /// Установка нового хеша пароля для пользователя
+        /// ### Принимает:
+        /// ID пользователя, хеш пароля
+        ///
+        /// ### Возвращает:
+        /// Если [`Ok`], то `()`. При ошибки [`sqlx::Error`]
+        pub async fn set_user_password(&self, user_id: i32, password_hash: &str) -> Result<(), sqlx::Error> {
+            let _ = sqlx::query("
+                UPDATE users
+                SET password = $2
+                WHERE id = $1;
+            ")
+                .bind(user_id)
+                .bind(password_hash)
+                .execute(&self.pool).await?;
 
-            Ok((crop_avatar, full_avatar))
+            Ok(())
         }
 
         /// Установка новых аватаров для пользователя
         /// ### Принимает:
-        /// Логин пользователя, маленький аватар и большой аватар
+        /// ID пользователя, маленький аватар и большой аватар, уже сохраненные в хранилище файлов
+        ///
+        /// ### Возвращает:
+        /// Если [`Ok`], то `()`. Если пользователь не найден, то [`sqlx::Error::RowNotFound`]. При ошибки [`sqlx::Error`]
+        pub async fn set_avatar_by_id(&self, user_id: i32, crop_avatar: &StoredImage, full_avatar: &StoredImage) -> Result<(), sqlx::Error> {
+            let mut tx = self.pool.begin().await?;
+
+            let crop_avatar_id = insert_image(&mut tx, crop_avatar).await?;
+            let full_avatar_id = insert_image(&mut tx, full_avatar).await?;
+
+            let result = sqlx::query("
+                UPDATE users
+                SET crop_avatar_id = $2, full_avatar_id = $3
+                WHERE id = $1;
+            ")
+                .bind(user_id)
+                .bind(crop_avatar_id)
+                .bind(full_avatar_id)
+                .execute(&mut tx).await?;
+
+            if result.rows_affected() == 0 {
+                return Err(sqlx::Error::RowNotFound);
+            }
+
+            tx.commit().await
+        }
+
+        /// Получение картинки, если она доступна пользователю: картинка аватара или опубликованной записи доступна всем,
+        /// картинка черновика, запланированной записи, ревизии или загрузки - только автору
+        /// ### Принимает:
+        /// ID картинки, ID текущего пользователя
+        ///
+        /// ### Возвращает:
+        /// Если [`Ok`], то `Option<VisibleImage>` - `None` если картинка не найдена или недоступна. При ошибки [`sqlx::Error`]
+        pub async fn get_image(&self, image_id: i32, viewer_id: Option<i32>) -> Result<Option<VisibleImage>, sqlx::Error> {
+            let row = sqlx::query_as::<_, VisibleImage>("
+                SELECT i.id, i.hash, i.content_type, i.data, v.public
+                FROM images AS i
+                CROSS JOIN LATERAL (
+                    SELECT EXISTS (
+                        SELECT 1 FROM users AS u WHERE u.crop_avatar_id = i.id OR u.full_avatar_id = i.id
+                    ) OR EXISTS (
+                        SELECT 1 FROM articles AS a WHERE a.image_id = i.id AND a.status = 'published'
+                    ) OR EXISTS (
+                        SELECT 1 FROM articles_media AS m
+                        JOIN articles AS a ON a.id = m.articles_id
+                        WHERE m.image_id = i.id AND a.status = 'published'
+                    ) AS public
+                ) AS v
+                WHERE i.id = $1
+                AND (
+                    v.public
+                    OR EXISTS (SELECT 1 FROM articles AS a WHERE a.image_id = i.id AND a.author_id = $2)
+                    OR EXISTS (
+                        SELECT 1 FROM articles_media AS m
+                        JOIN articles AS a ON a.id = m.articles_id
+                        WHERE m.image_id = i.id AND a.author_id = $2
+                    )
+                    OR EXISTS (
+                        SELECT 1 FROM article_revisions AS r
+                        JOIN articles AS a ON a.id = r.articles_id
+                        WHERE r.image_id = i.id AND a.author_id = $2
+                    )
+                    OR EXISTS (SELECT 1 FROM uploads AS up WHERE up.image_id = i.id AND up.users_id = $2)
+                );
+            ")
+                .bind(image_id)
+                .bind(viewer_id)
+                .fetch_optional(&self.pool).await?;
+
+            Ok(row)
+        }
+
+        /// Получение картинок, содержимое которых еще лежит в базе данных
+        /// ### Принимает:
+        /// Максимальное количество картинок
+        ///
+        /// ### Возвращает:
+        /// Если [`Ok`], то `Vec<Image>`. При ошибки [`sqlx::Error`]
+        pub async fn get_images_with_data(&self, limit: i64) -> Result<Vec<Image>, sqlx::Error> {
+            let rows = sqlx::query_as::<_, Image>("
+                SELECT id, hash, content_type, data
+                FROM images
+                WHERE data IS NOT NULL
+                ORDER BY id
+                LIMIT $1;
+            ")
+                .bind(limit)
+                .fetch_all(&self.pool).await?;
+
+            Ok(rows)
+        }
+
+        /// Удаление содержимого картинки из базы данных, после того как она перенесена в хранилище файлов
+        /// ### Принимает:
+        /// ID картинки
         ///
         /// ### Возвращает:
         /// Если [`Ok`], то `()`. При ошибки [`sqlx::Error`]
-        pub async fn set_avatar_by_login(&self, login: &str, crop_avatar: &Vec<u8>, full_avatar: &Vec<u8>) -> Result<(), sqlx::Error> {
+        pub async fn clear_image_data(&self, image_id: i32) -> Result<(), sqlx::Error> {
             let _ = sqlx::query("
-                UPDATE users
-                SET crop_avatar = $2, full_avatar = $3
-                WHERE login = $1;
+                UPDATE images
+                SET data = NULL
+                WHERE id = $1;
             ")
-                .bind(login)
-                .bind(crop_avatar)
-                .bind(full_avatar)
-                .fetch_one(&self.pool).await?;
+                .bind(image_id)
+                .execute(&self.pool).await?;
 
             Ok(())
         }
 
+        /// Создание сессии докачиваемой загрузки
+        /// ### Принимает:
+        /// ID пользователя, итоговый размер в байтах
+        ///
+        /// ### Возвращает:
+        /// Если [`Ok`], то [`Upload`]. При ошибки [`sqlx::Error`]
+        pub async fn insert_upload(&self, user_id: i32, size: i64) -> Result<Upload, sqlx::Error> {
+            let row = sqlx::query_as::<_, Upload>("
+                INSERT INTO uploads (users_id, size)
+                VALUES ($1, $2)
+                RETURNING id, size, received, image_id AS media_id, created_at, updated_at;
+            ")
+                .bind(user_id)
+                .bind(size)
+                .fetch_one(&self.pool).await?;
+
+            Ok(row)
+        }
+
+        /// Получение загрузки пользователя
+        /// ### Принимает:
+        /// ID пользователя, ID загрузки
+        ///
+        /// ### Возвращает:
+        /// Если [`Ok`], то `Option<Upload>`. При ошибки [`sqlx::Error`]
+        pub async fn get_upload(&self, user_id: i32, upload_id: i32) -> Result<Option<Upload>, sqlx::Error> {
+            let row = sqlx::query_as::<_, Upload>("
+                SELECT id, size, received, image_id AS media_id, created_at, updated_at
+                FROM uploads
+                WHERE id = $1 AND users_id = $2;
+            ")
+                .bind(upload_id)
+                .bind(user_id)
+                .fetch_optional(&self.pool).await?;
+
+            Ok(row)
+        }
+
+        /// Добавление части к загрузке. Части принимаются строго по порядку: смещение части
+        /// должно совпадать с количеством уже полученных байт.
+        /// Часть записывается `write` вне транзакции, затем `received` меняется, только если он не изменился
+        /// за время записи и загрузка не завершена. Чтобы параллельные части одной загрузки не писали в файл
+        /// одновременно, вызывающий держит [`Staging::lock_upload`](crate::storage::storage_staging::staging::Staging::lock_upload)
+        /// ### Принимает:
+        /// ID пользователя, ID загрузки, смещение части, размер части, запись части в файл загрузки
+        ///
+        /// ### Возвращает:
+        /// Если [`Ok`], то [`AppendChunk`]. При ошибки [`sqlx::Error`], ошибка записи - [`sqlx::Error::Io`]
+        pub async fn append_upload_chunk<W>(&self, user_id: i32, upload_id: i32, offset: i64, length: i64, write: W) -> Result<AppendChunk, sqlx::Error>
+        where
+            W: Future<Output = std::io::Result<()>>,
+        {
+            let upload = self.get_upload(user_id, upload_id).await?;
+
+            if let Some(rejected) = reject_upload_chunk(upload.as_ref(), offset, length) {
+                return Ok(rejected);
+            }
+
+            write.await?;
+
+            let upload = sqlx::query_as::<_, Upload>("
+                UPDATE uploads
+                SET received = received + $4, updated_at = now()
+                WHERE id = $1 AND users_id = $2 AND received = $3 AND image_id IS NULL
+                RETURNING id, size, received, image_id AS media_id, created_at, updated_at;
+            ")
+                .bind(upload_id)
+                .bind(user_id)
+                .bind(offset)
+                .bind(length)
+                .fetch_optional(&self.pool).await?;
+
+            match upload {
+                Some(o) => Ok(AppendChunk::Accepted(o)),
+                // загрузку изменил, завершил или удалил другой запрос, пока часть записывалась
+                None => {
+                    let upload = self.get_upload(user_id, upload_id).await?;
+
+                    Ok(reject_upload_chunk(upload.as_ref(), offset, length)
+                        .unwrap_or(AppendChunk::OffsetMismatch(offset)))
+                },
+            }
+        }
+
+        /// Завершение загрузки: картинка записывается в `images`
+        /// ### Принимает:
+        /// ID пользователя, ID загрузки, картинку из хранилища файлов
+        ///
+        /// ### Возвращает:
+        /// Если [`Ok`], то ID картинки (media ID) или [`None`], если загрузка уже завершена
+        /// или не найдена. При ошибки [`sqlx::Error`]
+        pub async fn complete_upload(&self, user_id: i32, upload_id: i32, image: &StoredImage) -> Result<Option<i32>, sqlx::Error> {
+            let mut tx = self.pool.begin().await?;
+
+            let image_id = insert_image(&mut tx, image).await?;
+
+            let result = sqlx::query("
+                UPDATE uploads
+                SET image_id = $3, completed_at = now(), updated_at = now()
+                WHERE id = $1 AND users_id = $2 AND image_id IS NULL;
+            ")
+                .bind(upload_id)
+                .bind(user_id)
+                .bind(image_id)
+                .execute(&mut tx).await?;
+
+            if result.rows_affected() == 0 {
+                return Ok(None);
+            }
+
+            tx.commit().await?;
+
+            Ok(Some(image_id))
+        }
+
+        /// Картинка из завершенной загрузки пользователя
+        /// ### Принимает:
+        /// ID пользователя, media ID
+        ///
+        /// ### Возвращает:
+        /// Если [`Ok`], то `Option<StoredImage>`. При ошибки [`sqlx::Error`]
+        pub async fn get_upload_image(&self, user_id: i32, media_id: i32) -> Result<Option<StoredImage>, sqlx::Error> {
+            let row = sqlx::query_as::<_, StoredImage>("
+                SELECT i.hash, i.content_type
+                FROM uploads AS u
+                JOIN images AS i ON i.id = u.image_id
+                WHERE u.image_id = $1 AND u.users_id = $2
+                LIMIT 1;
+            ")
+                .bind(media_id)
+                .bind(user_id)
+                .fetch_optional(&self.pool).await?;
+
+            Ok(row)
+        }
+
+        /// Удаление устаревших загрузок. Брошенные загрузки удаляются после последнего изменения,
+        /// завершенные - намного позже, чтобы их media ID можно было использовать в записях
+        /// ### Принимает:
+        /// Момент, раньше которого незавершенная загрузка считается брошенной,
+        /// момент, раньше которого истекает media ID завершенной загрузки
+        ///
+        /// ### Возвращает:
+        /// Если [`Ok`], то количество удаленных загрузок. При ошибки [`sqlx::Error`]
+        pub async fn remove_expired_uploads(&self, incomplete_before: DateTime<Utc>, completed_before: DateTime<Utc>) -> Result<u64, sqlx::Error> {
+            let result = sqlx::query("
+                DELETE FROM uploads
+                WHERE (image_id IS NULL AND updated_at < $1)
+                    OR (image_id IS NOT NULL AND completed_at < $2);
+            ")
+                .bind(incomplete_before)
+                .bind(completed_before)
+                .execute(&self.pool).await?;
+
+            Ok(result.rows_affected())
+        }
+
+        /// Создание сессии пользователя вместе с первым refresh токеном
+        /// ### Принимает:
+        /// ID пользователя, название устройства, хеши выданных токенов `SessionTokens`
+        ///
+        /// ### Возвращает:
+        /// Если [`Ok`], то ID сессии. При ошибки [`sqlx::Error`]
+        pub async fn insert_session(&self, user_id: i32, device: Option<&str>, tokens: &SessionTokens) -> Result<i32, sqlx::Error> {
+            let mut tx = self.pool.begin().await?;
+
+            let row = sqlx::query("
+                INSERT INTO sessions (users_id, token_hash, token_expires_at, device, expires_at)
+                VALUES ($1, $2, $3, $4, $5)
+                RETURNING id;
+            ")
+                .bind(user_id)
+                .bind(&tokens.access_hash)
+                .bind(tokens.access_expires_at)
+                .bind(device)
+                .bind(tokens.refresh_expires_at)
+                .fetch_one(&mut tx).await?;
+
+            let session_id: i32 = row.try_get("id")?;
+
+            let _ = sqlx::query("
+                INSERT INTO refresh_tokens (sessions_id, token_hash, expires_at)
+                VALUES ($1, $2, $3);
+            ")
+                .bind(session_id)
+                .bind(&tokens.refresh_hash)
+                .bind(tokens.refresh_expires_at)
+                .execute(&mut tx).await?;
+
+            tx.commit().await?;
+
+            Ok(session_id)
+        }
+
+        /// Получение действующей сессии по хешу токена доступа.
+        /// Заодно обновляет время последнего использования сессии.
+        /// ### Принимает:
+        /// Хеш токена доступа
+        ///
+        /// ### Возвращает:
+        /// Если [`Ok`], то структура `SessionUser`. Если сессия не найдена, истекла или отозвана - [`sqlx::Error::RowNotFound`]
+        pub async fn get_session_by_token_hash(&self, token_hash: &str) -> Result<SessionUser, sqlx::Error> {
+            let row = sqlx::query_as::<_, SessionUser>("
+                UPDATE sessions AS s
+                SET last_used_at = now()
+                FROM users AS u
+                WHERE s.token_hash = $1 AND s.token_expires_at > now() AND s.revoked_at IS NULL
+                AND s.users_id = u.id
+                RETURNING s.id AS session_id, s.users_id AS user_id, u.role;
+            ")
+                .bind(token_hash)
+                .fetch_one(&self.pool).await?;
+
+            Ok(row)
+        }
+
+        /// Обмен refresh токена на новую пару токенов (ротация).
+        ///
+        /// Если refresh токен уже был использован ранее, то считаем его украденным
+        /// и отзываем всю сессию, к которой он относится.
+        /// ### Принимает:
+        /// Хеш предъявленного refresh токена, хеши новых токенов `SessionTokens`
+        ///
+        /// ### Возвращает:
+        /// Если [`Ok`], то `RefreshOutcome`. При ошибки [`sqlx::Error`]
+        pub async fn rotate_refresh_token(&self, refresh_hash: &str, tokens: &SessionTokens) -> Result<RefreshOutcome, sqlx::Error> {
+            let mut tx = self.pool.begin().await?;
+
+            let row = sqlx::query("
+                SELECT rt.id, rt.sessions_id, s.users_id,
+                       rt.used_at IS NOT NULL AS used,
+                       rt.expires_at <= now() OR s.revoked_at IS NOT NULL OR s.expires_at <= now() AS expired
+                FROM refresh_tokens AS rt, sessions AS s
+                WHERE rt.token_hash = $1 AND rt.sessions_id = s.id
+                FOR UPDATE;
+            ")
+                .bind(refresh_hash)
+                .fetch_one(&mut tx).await;
+
+            let row = match row {
+                Ok(o) => o,
+                Err(sqlx::Error::RowNotFound) => return Ok(RefreshOutcome::Invalid),
+                Err(e) => return Err(e),
+            };
+
+            let refresh_id: i32 = row.try_get("id")?;
+            let session_id: i32 = row.try_get("sessions_id")?;
+            let user_id: i32 = row.try_get("users_id")?;
+
+            if row.try_get::<bool, _>("expired")? {
+                return Ok(RefreshOutcome::Invalid);
+            }
+
+            if row.try_get::<bool, _>("used")? {
+                let _ = sqlx::query("
+                    UPDATE sessions
+                    SET revoked_at = now()
+                    WHERE id = $1;
+                ")
+                    .bind(session_id)
+                    .execute(&mut tx).await?;
+
+                tx.commit().await?;
+
+                return Ok(RefreshOutcome::Reused { session_id, user_id });
+            }
+
+            let _ = sqlx::query("
+                UPDATE refresh_tokens
+                SET used_at = now()
+                WHERE id = $1;
+            ")
+                .bind(refresh_id)
+                .execute(&mut tx).await?;
+
+            let _ = sqlx::query("
+                INSERT INTO refresh_tokens (sessions_id, token_hash, expires_at)
+                VALUES ($1, $2, $3);
+            ")
+                .bind(session_id)
+                .bind(&tokens.refresh_hash)
+                .bind(tokens.refresh_expires_at)
+                .execute(&mut tx).await?;
+
+            let _ = sqlx::query("
+                UPDATE sessions
+                SET token_hash = $2, token_expires_at = $3, expires_at = $4, last_used_at = now()
+                WHERE id = $1;
+            ")
+                .bind(session_id)
+                .bind(&tokens.access_hash)
+                .bind(tokens.access_expires_at)
+                .bind(tokens.refresh_expires_at)
+                .execute(&mut tx).await?;
+
+            tx.commit().await?;
+
+            Ok(RefreshOutcome::Rotated { session_id, user_id })
+        }
+
+        /// Получение активных сессий пользователя
+        /// ### Принимает:
+        /// ID пользователя
+        ///
+        /// ### Возвращает:
+        /// Если [`Ok`], то `Vec<Session>`. При ошибки [`sqlx::Error`]
+        pub async fn get_active_sessions(&self, user_id: i32) -> Result<Vec<Session>, sqlx::Error> {
+            let rows = sqlx::query_as::<_, Session>("
+                SELECT id AS session_id, users_id AS user_id, device, created_at, last_used_at, expires_at
+                FROM sessions
+                WHERE users_id = $1 AND revoked_at IS NULL AND expires_at > now()
+                ORDER BY last_used_at DESC;
+            ")
+                .bind(user_id)
+                .fetch_all(&self.pool).await?;
+
+            Ok(rows)
+        }
+
+        /// Отзыв сессии пользователя
+        /// ### Принимает:
+        /// ID пользователя (владельца сессии), ID сессии
+        ///
+        /// ### Возвращает:
+        /// Если [`Ok`], то `true` - сессия отозвана, `false` - активная сессия не найдена. При ошибки [`sqlx::Error`]
+        pub async fn revoke_session(&self, user_id: i32, session_id: i32) -> Result<bool, sqlx::Error> {
+            let result = sqlx::query("
+                UPDATE sessions
+                SET revoked_at = now()
+                WHERE id = $1 AND users_id = $2 AND revoked_at IS NULL;
+            ")
+                .bind(session_id)
+                .bind(user_id)
+                .execute(&self.pool).await?;
+
+            Ok(result.rows_affected() > 0)
+        }
+
+        /// Отзыв всех сессий пользователя
+        /// ### Принимает:
+        /// ID пользователя, ID сессии которую нужно оставить (если есть)
+        ///
+        /// ### Возвращает:
+        /// Если [`Ok`], то количество отозванных сессий. При ошибки [`sqlx::Error`]
+        pub async fn revoke_all_sessions(&self, user_id: i32, keep_session_id: Option<i32>) -> Result<u64, sqlx::Error> {
+            let result = sqlx::query("
+                UPDATE sessions
+                SET revoked_at = now()
+                WHERE users_id = $1 AND revoked_at IS NULL
+                AND ($2::int4 IS NULL OR id != $2);
+            ")
+                .bind(user_id)
+                .bind(keep_session_id)
+                .execute(&self.pool).await?;
+
+            Ok(result.rows_affected())
+        }
+
         /// Получение данных пользователя по ID
         /// ### Принимает:
         /// ID пользователя
@@ -243,7 +673,7 @@ pub mod postgresql_manager {
         /// Если [`Ok`], то структура `User`. При ошибки [`sqlx::Error`]
         pub async fn get_user_info_by_id(&self, id: i32) -> Result<User, sqlx::Error> {
             let row = sqlx::query_as::<_, User>("
-                SELECT id AS user_id, first_name, last_name, about, password, login, full_avatar, crop_avatar, date_registration
+                SELECT id AS user_id, first_name, last_name, about, login, full_avatar_id AS full_avatar, crop_avatar_id AS crop_avatar, date_registration
                 FROM users
                 WHERE id = $1
             ")
@@ -341,79 +771,513 @@ pub mod postgresql_manager {
             Ok(())
         }
 
-        /// Создаем запись в базе данных
+        /// Создаем запись в базе данных вместе с тегами
         ///
         /// ### Принимает:
-        /// Структуру `Article`
+        /// ID автора, структуру `InsertArticle`, картинку записи, уже сохраненную в хранилище файлов,
+        /// отрисованное описание
         ///
         /// ### Возвращает:
         /// Если [`Ok`], то `()`. При ошибки [`sqlx::Error`]
-        pub async fn insert_article(&self, article: &InsertArticle) -> Result<(), sqlx::Error> {
-            let image = general_purpose::STANDARD.decode(&article.image).unwrap();
-            let _ = sqlx::query("
+        pub async fn insert_article(&self, author_id: i32, article: &InsertArticle, image: &StoredImage, description: &RenderedMarkdown) -> Result<(), sqlx::Error> {
+            let mut tx = self.pool.begin().await?;
+
+            let image_id = insert_image(&mut tx, image).await?;
+
+            let row = sqlx::query("
                 INSERT INTO articles
-                (author_id, image, title, description)
-                VALUES($1, $2, $3, $4);
+                (author_id, image_id, title, description, description_html, description_text, crop_description, summary, reading_time, status, publish_at)
+                VALUES($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+                RETURNING id;
             ")
-                .bind(article.author_id)
-                .bind(&image)
+                .bind(author_id)
+                .bind(image_id)
                 .bind(&article.title)
                 .bind(&article.description)
-                .execute(&self.pool).await?;
-            Ok(())
+                .bind(&description.html)
+                .bind(&description.text)
+                .bind(&description.crop)
+                .bind(&article.summary)
+                .bind(description.reading_time)
+                .bind(article.status)
+                .bind(article.publish_at)
+                .fetch_one(&mut tx).await?;
+
+            set_article_tags(&mut tx, row.try_get("id")?, &article.tags).await?;
+
+            tx.commit().await
         }
 
-        /// Получить записи из базы данных на основе подписок пользователя
+        /// Публикация черновика или запланированной записи.
+        ///
+        /// Без `publish_at` запись публикуется сразу, иначе планируется на указанное время
         /// ### Принимает:
-        /// ID пользователя
+        ///
+        /// ID записи, время публикации
         ///
         /// ### Возвращает:
-        /// Если [`Ok`], то `Vec<Article>`. При ошибки [`sqlx::Error`]
-        pub async fn get_articles(&self, user_id: i32) -> Result<Vec<Article>, sqlx::Error> {
-            let mut articles = sqlx::query_as::<_, Article>("
-                SELECT a.id AS article_id, image, title, description AS full_description,
-                CONCAT(LEFT(description, 150), '...') AS crop_description, publish_date,
-                u.id AS user_id, first_name, last_name, about, password, login, full_avatar, crop_avatar, date_registration
-                FROM articles AS a, users AS u, users_followers AS uf
-                WHERE uf.users_author_id = u.id AND uf.users_follower_id = $1 AND a.author_id = u.id;
+        /// Если [`Ok`], то `true` - статус изменен, `false` - запись уже опубликована. При ошибки [`sqlx::Error`]
+        pub async fn publish_article(&self, article_id: i32, publish_at: Option<DateTime<Utc>>) -> Result<bool, sqlx::Error> {
+            let result = sqlx::query("
+                UPDATE articles
+                SET status = CASE WHEN $2::timestamptz IS NULL THEN 'published' ELSE 'scheduled' END,
+                    publish_at = $2,
+                    publish_date = CASE WHEN $2::timestamptz IS NULL THEN now() ELSE publish_date END
+                WHERE id = $1 AND status != 'published';
             ")
-                .bind(user_id)
-                .fetch_all(&self.pool)
-                .await?;
-
-            for article in &mut articles {
-                let (likes, dislikes) = self.get_reactions_from_article(article.id).await.unwrap();
-                article.likes = likes;
-                article.dislikes = dislikes;
-            }
+                .bind(article_id)
+                .bind(publish_at)
+                .execute(&self.pool).await?;
 
-            Ok(articles)
+            Ok(result.rows_affected() > 0)
         }
 
-        /// Получить данные об записи
-        /// ### Принимает:
-        /// ID записи
+        /// Публикация запланированных записей, время которых наступило.
+        /// Дата публикации записи - момент фактической публикации, а не `publish_at`: задача запускается с интервалом,
+        /// и запись с датой в прошлом оказалась бы позади курсора уже загруженных страниц ленты
         ///
         /// ### Возвращает:
-        /// Если [`Ok`], то структура `Article`. При ошибки [`sqlx::Error`]
-        pub async fn get_article_info(&self, article_id: i32) -> Result<Article, sqlx::Error> {
-            let mut article = sqlx::query_as::<_, Article>("
-                SELECT id AS article_id, author_id, image, title,
-                description AS full_description, concat(left(description, 150), '...') AS crop_description, publish_date
-                FROM articles AS a
-                WHERE id = $1;
+        /// Если [`Ok`], то количество опубликованных записей. При ошибки [`sqlx::Error`]
+        pub async fn publish_scheduled_articles(&self) -> Result<u64, sqlx::Error> {
+            let result = sqlx::query("
+                UPDATE articles
+                SET status = 'published', publish_date = now()
+                WHERE status = 'scheduled' AND publish_at <= now();
             ")
-                .bind(article_id)
-                .fetch_one(&self.pool)
-                .await?;
+                .execute(&self.pool).await?;
+
+            Ok(result.rows_affected())
+        }
 
-            let (likes, dislikes) = self.get_reactions_from_article(article.id).await.unwrap();
-            article.likes = likes;
-            article.dislikes = dislikes;
+        /// Получение записи вместе с текстом. Черновики и запланированные записи видит только автор
+        /// ### Принимает:
+        /// ID записи, ID текущего пользователя (для его реакции и доступа к черновикам)
+        ///
+        /// ### Возвращает:
+        /// Если [`Ok`], то `FullArticle` или [`None`], если запись не найдена. При ошибки [`sqlx::Error`]
+        pub async fn get_article(&self, article_id: i32, viewer_id: Option<i32>) -> Result<Option<FullArticle>, sqlx::Error> {
+            let select = article_select(2, ARTICLE_BODY_COLUMNS, "");
+            let query = format!("{select}
+                WHERE a.id = $1 AND (a.status = 'published' OR a.author_id = $2);
+            ");
+
+            let article = sqlx::query_as::<_, FullArticle>(&query)
+                .bind(article_id)
+                .bind(viewer_id)
+                .fetch_optional(&self.pool)
+                .await?;
 
             Ok(article)
         }
 
+        /// Получение черновиков и запланированных записей пользователя, от новых к старым
+        /// ### Принимает:
+        ///
+        /// ID пользователя, запрос страницы с курсором по `publish_date, id`
+        ///
+        /// ### Возвращает:
+        /// Если [`Ok`], то `Page<Article>`. При ошибки [`sqlx::Error`]
+        pub async fn get_drafts(&self, user_id: i32, page: &PageRequest<DateCursor>) -> Result<Page<Article>, sqlx::Error> {
+            let select = article_select(1, "", "");
+            let query = format!("{select}
+                WHERE a.author_id = $1 AND a.status != 'published'
+                AND ($2::timestamptz IS NULL OR (a.publish_date, a.id) < ($2, $3))
+                ORDER BY a.publish_date DESC, a.id DESC
+                LIMIT $4;
+            ");
+
+            let articles = sqlx::query_as::<_, Article>(&query)
+                .bind(user_id)
+                .bind(page.cursor.map(|o| o.publish_date))
+                .bind(page.cursor.map(|o| o.id))
+                .bind(page.limit + 1)
+                .fetch_all(&self.pool).await?;
+
+            Ok(Page::from_rows(articles, page.limit, |o| DateCursor { publish_date: o.publish_date, id: o.id }))
+        }
+
+        /// Получить записи из базы данных на основе подписок пользователя на авторов и теги, от новых к старым
+        /// ### Принимает:
+        /// ID пользователя, ID текущего пользователя (для его реакции), запрос страницы с курсором по `publish_date, id`
+        ///
+        /// ### Возвращает:
+        /// Если [`Ok`], то `Page<Article>`. При ошибки [`sqlx::Error`]
+        pub async fn get_articles(&self, user_id: i32, viewer_id: Option<i32>, page: &PageRequest<DateCursor>) -> Result<Page<Article>, sqlx::Error> {
+            let select = article_select(5, "", "");
+            let query = format!("{select}
+                WHERE a.status = 'published'
+                AND (
+                    EXISTS (
+                        SELECT 1 FROM users_followers AS uf
+                        WHERE uf.users_author_id = a.author_id AND uf.users_follower_id = $1
+                    )
+                    OR EXISTS (
+                        SELECT 1 FROM articles_tags AS ats
+                        JOIN users_tags AS ut ON ut.tags_id = ats.tags_id
+                        WHERE ats.articles_id = a.id AND ut.users_id = $1
+                    )
+                )
+                AND ($2::timestamptz IS NULL OR (a.publish_date, a.id) < ($2, $3))
+                ORDER BY a.publish_date DESC, a.id DESC
+                LIMIT $4;
+            ");
+
+            let articles = sqlx::query_as::<_, Article>(&query)
+                .bind(user_id)
+                .bind(page.cursor.map(|o| o.publish_date))
+                .bind(page.cursor.map(|o| o.id))
+                .bind(page.limit + 1)
+                .bind(viewer_id)
+                .fetch_all(&self.pool)
+                .await?;
+
+            Ok(Page::from_rows(articles, page.limit, |o| DateCursor { publish_date: o.publish_date, id: o.id }))
+        }
+
+        /// Получить все опубликованные записи, от новых к старым
+        /// ### Принимает:
+        /// ID текущего пользователя (для его реакции), запрос страницы с курсором по `publish_date, id`
+        ///
+        /// ### Возвращает:
+        /// Если [`Ok`], то `Page<Article>`. При ошибки [`sqlx::Error`]
+        pub async fn get_latest_articles(&self, viewer_id: Option<i32>, page: &PageRequest<DateCursor>) -> Result<Page<Article>, sqlx::Error> {
+            let select = article_select(4, "", "");
+            let query = format!("{select}
+                WHERE a.status = 'published'
+                AND ($1::timestamptz IS NULL OR (a.publish_date, a.id) < ($1, $2))
+                ORDER BY a.publish_date DESC, a.id DESC
+                LIMIT $3;
+            ");
+
+            let articles = sqlx::query_as::<_, Article>(&query)
+                .bind(page.cursor.map(|o| o.publish_date))
+                .bind(page.cursor.map(|o| o.id))
+                .bind(page.limit + 1)
+                .bind(viewer_id)
+                .fetch_all(&self.pool)
+                .await?;
+
+            Ok(Page::from_rows(articles, page.limit, |o| DateCursor { publish_date: o.publish_date, id: o.id }))
+        }
+
+        /// Получить популярные записи, опубликованные начиная с `since`.
+        ///
+        /// Оценка записи: `(лайки - дизлайки + вес комментария * комментарии + 1) / (возраст в часах + 2) ^ гравитация`,
+        /// поэтому новые записи с реакциями поднимаются выше старых
+        /// ### Принимает:
+        /// ID текущего пользователя (для его реакции), начало периода, максимальное количество записей
+        ///
+        /// ### Возвращает:
+        /// Если [`Ok`], то `Vec<TrendingArticle>`, по убыванию оценки. При ошибки [`sqlx::Error`]
+        pub async fn get_trending_articles(&self, viewer_id: Option<i32>, since: DateTime<Utc>, limit: i64) -> Result<Vec<TrendingArticle>, sqlx::Error> {
+            let select = article_select(5, "s.score", "
+                CROSS JOIN LATERAL (
+                    SELECT CAST(
+                        (rs.likes - rs.dislikes + $3 * cs.comments + 1)
+                        / power(GREATEST(EXTRACT(EPOCH FROM now() - a.publish_date), 0) / 3600 + 2, $4)
+                    AS float8) AS score
+                ) AS s
+            ");
+            let query = format!("{select}
+                WHERE a.status = 'published' AND a.publish_date >= $1
+                ORDER BY s.score DESC, a.publish_date DESC, a.id DESC
+                LIMIT $2;
+            ");
+
+            let rows = sqlx::query_as::<_, TrendingArticle>(&query)
+                .bind(since)
+                .bind(limit)
+                .bind(TRENDING_COMMENT_WEIGHT)
+                .bind(TRENDING_GRAVITY)
+                .bind(viewer_id)
+                .fetch_all(&self.pool).await?;
+
+            Ok(rows)
+        }
+
+        /// Изменение записи. Текущая версия записи сохраняется в `article_revisions`
+        /// ### Принимает:
+        ///
+        /// ID пользователя, который изменяет запись, структуру `UpdateArticle`,
+        /// новую картинку записи (если передана), уже сохраненную в хранилище файлов,
+        /// отрисованное новое описание (если передано)
+        ///
+        /// ### Возвращает:
+        /// Если [`Ok`], то `()`. При ошибки [`sqlx::Error`]
+        pub async fn update_article(&self, editor_id: i32, article: &UpdateArticle, image: Option<&StoredImage>, description: Option<&RenderedMarkdown>) -> Result<(), sqlx::Error> {
+            let mut tx = self.pool.begin().await?;
+
+            save_article_revision(&mut tx, editor_id, article.article_id).await?;
+
+            let image_id = match image {
+                Some(o) => Some(insert_image(&mut tx, o).await?),
+                None => None,
+            };
+
+            sqlx::query("
+                UPDATE articles
+                SET image_id = COALESCE($2, image_id),
+                    title = COALESCE($3, title),
+                    description = COALESCE($4, description),
+                    description_html = COALESCE($5, description_html),
+                    description_text = COALESCE($6, description_text),
+                    crop_description = COALESCE($7, crop_description),
+                    reading_time = COALESCE($8, reading_time),
+                    summary = CASE WHEN $9::varchar IS NULL THEN summary ELSE NULLIF(btrim($9), '') END,
+                    edited_date = now()
+                WHERE id = $1;
+            ")
+                .bind(article.article_id)
+                .bind(image_id)
+                .bind(&article.title)
+                .bind(&article.description)
+                .bind(description.map(|o| &o.html))
+                .bind(description.map(|o| &o.text))
+                .bind(description.map(|o| &o.crop))
+                .bind(description.map(|o| o.reading_time))
+                .bind(&article.summary)
+                .execute(&mut tx).await?;
+
+            if let Some(tags) = &article.tags {
+                set_article_tags(&mut tx, article.article_id, tags).await?;
+            }
+
+            tx.commit().await
+        }
+
+        /// Получение предыдущих версий записи, от новых к старым
+        /// ### Принимает:
+        ///
+        /// ID записи
+        ///
+        /// ### Возвращает:
+        /// Если [`Ok`], то `Vec<ArticleRevision>`. При ошибки [`sqlx::Error`]
+        pub async fn get_article_revisions(&self, article_id: i32) -> Result<Vec<ArticleRevision>, sqlx::Error> {
+            let rows = sqlx::query_as::<_, ArticleRevision>("
+                SELECT id, articles_id AS article_id, editor_id, image_id AS image, title, description, summary, date
+                FROM article_revisions
+                WHERE articles_id = $1
+                ORDER BY id DESC;
+            ")
+                .bind(article_id)
+                .fetch_all(&self.pool).await?;
+
+            Ok(rows)
+        }
+
+        /// Восстановление записи из предыдущей версии. Текущая версия записи тоже сохраняется в `article_revisions`
+        /// ### Принимает:
+        ///
+        /// ID пользователя, который восстанавливает запись, ID записи, ID версии
+        ///
+        /// ### Возвращает:
+        /// Если [`Ok`], то `true` - запись восстановлена, `false` - версия не найдена. При ошибки [`sqlx::Error`]
+        pub async fn restore_article_revision(&self, editor_id: i32, article_id: i32, revision_id: i32) -> Result<bool, sqlx::Error> {
+            let mut tx = self.pool.begin().await?;
+
+            let row = sqlx::query("
+                SELECT EXISTS (SELECT 1 FROM article_revisions WHERE id = $1 AND articles_id = $2) AS exists;
+            ")
+                .bind(revision_id)
+                .bind(article_id)
+                .fetch_one(&mut tx).await?;
+
+            if !row.try_get::<bool, _>("exists")? {
+                return Ok(false);
+            }
+
+            save_article_revision(&mut tx, editor_id, article_id).await?;
+
+            sqlx::query("
+                UPDATE articles AS a
+                SET image_id = ar.image_id,
+                    title = ar.title,
+                    description = ar.description,
+                    description_html = ar.description_html,
+                    description_text = ar.description_text,
+                    crop_description = ar.crop_description,
+                    summary = ar.summary,
+                    reading_time = ar.reading_time,
+                    edited_date = now()
+                FROM article_revisions AS ar
+                WHERE a.id = $1 AND ar.id = $2;
+            ")
+                .bind(article_id)
+                .bind(revision_id)
+                .execute(&mut tx).await?;
+
+            tx.commit().await?;
+
+            Ok(true)
+        }
+
+        /// Добавление картинки в конец галереи записи
+        /// ### Принимает:
+        /// Параметры картинки `InsertArticleMedia`, картинку из хранилища файлов
+        ///
+        /// ### Возвращает:
+        /// Если [`Ok`], то добавленная [`ArticleMedia`] или [`None`], если в галерее уже `MAX_ARTICLE_MEDIA` картинок.
+        /// При ошибки [`sqlx::Error`]
+        pub async fn insert_article_media(&self, media: &InsertArticleMedia, image: &StoredImage) -> Result<Option<ArticleMedia>, sqlx::Error> {
+            let mut tx = self.pool.begin().await?;
+
+            // блокировка записи, чтобы параллельные добавления не получили одну позицию
+            sqlx::query("
+                SELECT id FROM articles WHERE id = $1 FOR UPDATE;
+            ")
+                .bind(media.article_id)
+                .execute(&mut tx).await?;
+
+            let row = sqlx::query("
+                SELECT COUNT(*) AS count, COALESCE(MAX(position) + 1, 0) AS position
+                FROM articles_media
+                WHERE articles_id = $1;
+            ")
+                .bind(media.article_id)
+                .fetch_one(&mut tx).await?;
+
+            if row.try_get::<i64, _>("count")? >= MAX_ARTICLE_MEDIA as i64 {
+                return Ok(None);
+            }
+
+            let image_id = insert_image(&mut tx, image).await?;
+
+            let item = sqlx::query_as::<_, ArticleMedia>("
+                INSERT INTO articles_media (articles_id, image_id, position, caption, alt)
+                VALUES ($1, $2, $3, $4, $5)
+                RETURNING id, image_id AS image, position, caption, alt;
+            ")
+                .bind(media.article_id)
+                .bind(image_id)
+                .bind(row.try_get::<i32, _>("position")?)
+                .bind(&media.caption)
+                .bind(&media.alt)
+                .fetch_one(&mut tx).await?;
+
+            touch_article(&mut tx, media.article_id).await?;
+
+            tx.commit().await?;
+
+            Ok(Some(item))
+        }
+
+        /// Изменение порядка галереи записи
+        /// ### Принимает:
+        /// ID записи, ID всех картинок галереи в новом порядке
+        ///
+        /// ### Возвращает:
+        /// Если [`Ok`], то `true` - порядок изменен, `false` - список не совпадает с галереей. При ошибки [`sqlx::Error`]
+        pub async fn reorder_article_media(&self, article_id: i32, items: &[i32]) -> Result<bool, sqlx::Error> {
+            let mut tx = self.pool.begin().await?;
+
+            sqlx::query("
+                SELECT id FROM articles WHERE id = $1 FOR UPDATE;
+            ")
+                .bind(article_id)
+                .execute(&mut tx).await?;
+
+            let rows = sqlx::query("
+                SELECT id
+                FROM articles_media
+                WHERE articles_id = $1
+                ORDER BY id;
+            ")
+                .bind(article_id)
+                .fetch_all(&mut tx).await?;
+
+            let mut current = rows.iter()
+                .map(|o| o.try_get::<i32, _>("id"))
+                .collect::<Result<Vec<i32>, sqlx::Error>>()?;
+            current.sort_unstable();
+
+            let mut requested = items.to_vec();
+            requested.sort_unstable();
+
+            if current != requested {
+                return Ok(false);
+            }
+
+            sqlx::query("
+                UPDATE articles_media AS m
+                SET position = o.ord - 1
+                FROM unnest($2::int4[]) WITH ORDINALITY AS o(id, ord)
+                WHERE m.id = o.id AND m.articles_id = $1;
+            ")
+                .bind(article_id)
+                .bind(items)
+                .execute(&mut tx).await?;
+
+            touch_article(&mut tx, article_id).await?;
+
+            tx.commit().await?;
+
+            Ok(true)
+        }
+
+        /// Удаление картинки из галереи записи
+        /// ### Принимает:
+        /// ID записи, ID картинки в галерее
+        ///
+        /// ### Возвращает:
+        /// Если [`Ok`], то `true` - картинка удалена, `false` - картинки нет в галерее. При ошибки [`sqlx::Error`]
+        pub async fn remove_article_media(&self, article_id: i32, item_id: i32) -> Result<bool, sqlx::Error> {
+            let mut tx = self.pool.begin().await?;
+
+            let result = sqlx::query("
+                DELETE FROM articles_media
+                WHERE id = $1 AND articles_id = $2;
+            ")
+                .bind(item_id)
+                .bind(article_id)
+                .execute(&mut tx).await?;
+
+            if result.rows_affected() == 0 {
+                return Ok(false);
+            }
+
+            touch_article(&mut tx, article_id).await?;
+
+            tx.commit().await?;
+
+            Ok(true)
+        }
+
+        /// Полнотекстовый поиск записей по заголовку и описанию (русский и английский стемминг)
+        /// ### Принимает:
+        ///
+        /// ID текущего пользователя (для его реакции), структуру `ArticleSearch` - поисковый запрос (синтаксис `websearch_to_tsquery`), фильтры по автору и дате
+        ///
+        /// ### Возвращает:
+        /// Если [`Ok`], то `Vec<FoundArticle>`, отсортированный по релевантности. При ошибки [`sqlx::Error`]
+        pub async fn search_articles(&self, viewer_id: Option<i32>, search: &ArticleSearch) -> Result<Vec<FoundArticle>, sqlx::Error> {
+            let select = article_select(6, format!("
+                ts_rank(a.search_vector, q.query) AS rank,
+                ts_headline('russian', title, q.query, 'StartSel=<b>, StopSel=</b>, HighlightAll=true') AS title_highlight,
+                ts_headline('russian', left(description_text, {SEARCH_SNIPPET_SOURCE_LENGTH}), q.query, 'StartSel=<b>, StopSel=</b>, MaxFragments=2, MaxWords=30, MinWords=10') AS snippet
+            ").as_str(), "CROSS JOIN websearch_to_tsquery('russian', $1) AS q(query)");
+            let query = format!("{select}
+                WHERE a.search_vector @@ q.query
+                AND a.status = 'published'
+                AND ($2::int4 IS NULL OR a.author_id = $2)
+                AND ($3::timestamptz IS NULL OR a.publish_date >= $3)
+                AND ($4::timestamptz IS NULL OR a.publish_date < $4)
+                ORDER BY rank DESC, a.publish_date DESC, a.id DESC
+                LIMIT $5;
+            ");
+
+            let articles = sqlx::query_as::<_, FoundArticle>(&query)
+                .bind(&search.query)
+                .bind(search.author_id)
+                .bind(search.from)
+                .bind(search.to)
+                .bind(search.limit)
+                .bind(viewer_id)
+                .fetch_all(&self.pool)
+                .await?;
+
+            Ok(articles)
+        }
+
         /// Удалить запись из базы данных
         /// ### Принимает:
         ///
@@ -433,6 +1297,47 @@ pub mod postgresql_manager {
             Ok(())
         }
 
+        /// Удалить комментарий из базы данных
+        /// ### Принимает:
+        ///
+        /// ID комментария
+        ///
+        /// ### Возвращает:
+        /// Если [`Ok`], то `()`. При ошибки [`sqlx::Error`]
+        pub async fn remove_comment(&self, comment_id: i32) -> Result<(), sqlx::Error> {
+            let _ = sqlx::query("
+                DELETE FROM
+                articles_comments
+                WHERE id = $1;
+            ")
+                .bind(comment_id)
+                .execute(&self.pool).await?;
+
+            Ok(())
+        }
+
+        /// Получение ID автора комментария
+        /// ### Принимает:
+        ///
+        /// ID комментария
+        ///
+        /// ### Возвращает:
+        /// Если [`Ok`], то `Option<i32>` - `None` если комментарий не найден. При ошибки [`sqlx::Error`]
+        pub async fn get_comment_author_id(&self, comment_id: i32) -> Result<Option<i32>, sqlx::Error> {
+            let row = sqlx::query("
+                SELECT users_id
+                FROM articles_comments
+                WHERE id = $1;
+            ")
+                .bind(comment_id)
+                .fetch_optional(&self.pool).await?;
+
+            match row {
+                Some(row) => Ok(Some(row.try_get("users_id")?)),
+                None => Ok(None),
+            }
+        }
+
         /// Проверка, является ли пользовать создателем записи
         /// ### Принимает:
         ///
@@ -447,126 +1352,304 @@ pub mod postgresql_manager {
                 WHERE author_id = $1 AND id = $2;
             ")
                 .bind(user_id)
+                .bind(article_id)
+                .fetch_one(&self.pool).await;
+
+            if let Err(sqlx::Error::RowNotFound) = row {
+                return Ok(false);
+            }
+
+            Ok(row?.try_get::<i32, _>("id").is_ok())
+        }
+
+        /// Проверяет, есть ли запись в базе данных
+        /// ### Принимает:
+        ///
+        /// ID записи
+        ///
+        /// ### Возвращает:
+        /// Если [`Ok`], то `true` - запись найдена, иначе `false`. При ошибки [`sqlx::Error`]
+        pub async fn exist_article(&self, article_id: i32) -> Result<bool, sqlx::Error> {
+            let row = sqlx::query("
+                SELECT EXISTS (SELECT 1 FROM articles WHERE id = $1) AS exists;
+            ")
                 .bind(article_id)
                 .fetch_one(&self.pool).await?;
 
-            Ok(row.try_get::<i32, _>("id").is_ok())
+            row.try_get("exists")
         }
 
-        /// Получение записей определенного пользователя
+        /// Проверяет, есть ли опубликованная запись в базе данных
         /// ### Принимает:
         ///
-        /// ID пользователя
+        /// ID записи
         ///
         /// ### Возвращает:
-        /// Если [`Ok`], то `Vec<Article>`. При ошибки [`sqlx::Error`]
-        pub async fn get_articles_from_user(&self, user_id: i32) -> Result<Vec<Article>, sqlx::Error> {
-            let mut articles = sqlx::query_as::<_, Article>("
-                SELECT a.id AS article_id, image, title, description AS full_description,
-                       CONCAT(LEFT(description, 150), '...') AS crop_description, publish_date,
-                       u.id AS user_id, first_name, last_name, about, password, login, full_avatar, crop_avatar, date_registration
-                FROM articles as a, users as u
-                WHERE a.author_id = u.id
-                  AND u.id = $1
-                GROUP BY a.id, u.id
-                ORDER BY a.id DESC;
+        /// Если [`Ok`], то `true` - запись найдена и опубликована, иначе `false`. При ошибки [`sqlx::Error`]
+        pub async fn exist_published_article(&self, article_id: i32) -> Result<bool, sqlx::Error> {
+            let row = sqlx::query("
+                SELECT EXISTS (SELECT 1 FROM articles WHERE id = $1 AND status = 'published') AS exists;
             ")
+                .bind(article_id)
+                .fetch_one(&self.pool).await?;
+
+            row.try_get("exists")
+        }
+
+        /// Получение записей определенного пользователя, от новых к старым
+        /// ### Принимает:
+        ///
+        /// ID автора, ID текущего пользователя (для его реакции), запрос страницы с курсором по `publish_date, id`
+        ///
+        /// ### Возвращает:
+        /// Если [`Ok`], то `Page<Article>`. При ошибки [`sqlx::Error`]
+        pub async fn get_articles_from_user(&self, user_id: i32, viewer_id: Option<i32>, page: &PageRequest<DateCursor>) -> Result<Page<Article>, sqlx::Error> {
+            let select = article_select(5, "", "");
+            let query = format!("{select}
+                WHERE a.author_id = $1
+                  AND a.status = 'published'
+                  AND ($2::timestamptz IS NULL OR (a.publish_date, a.id) < ($2, $3))
+                ORDER BY a.publish_date DESC, a.id DESC
+                LIMIT $4;
+            ");
+
+            let articles = sqlx::query_as::<_, Article>(&query)
                 .bind(user_id)
+                .bind(page.cursor.map(|o| o.publish_date))
+                .bind(page.cursor.map(|o| o.id))
+                .bind(page.limit + 1)
+                .bind(viewer_id)
                 .fetch_all(&self.pool).await?;
 
-            for article in &mut articles {
-                let (likes, dislikes) = self.get_reactions_from_article(article.id).await.unwrap();
-                article.likes = likes;
-                article.dislikes = dislikes;
+            Ok(Page::from_rows(articles, page.limit, |o| DateCursor { publish_date: o.publish_date, id: o.id }))
+        }
+
+        /// Получение опубликованных записей с тегом, от новых к старым
+        /// ### Принимает:
+        ///
+        /// Тег (уже приведенный через `normalize_tag`), ID текущего пользователя (для его реакции), запрос страницы с курсором по `publish_date, id`
+        ///
+        /// ### Возвращает:
+        /// Если [`Ok`], то `Page<Article>`. При ошибки [`sqlx::Error`]
+        pub async fn get_articles_by_tag(&self, tag: &str, viewer_id: Option<i32>, page: &PageRequest<DateCursor>) -> Result<Page<Article>, sqlx::Error> {
+            let select = article_select(5, "", "");
+            let query = format!("{select}
+                WHERE a.status = 'published'
+                AND EXISTS (
+                    SELECT 1 FROM articles_tags AS ats
+                    JOIN tags AS t ON t.id = ats.tags_id
+                    WHERE ats.articles_id = a.id AND t.name = $1
+                )
+                AND ($2::timestamptz IS NULL OR (a.publish_date, a.id) < ($2, $3))
+                ORDER BY a.publish_date DESC, a.id DESC
+                LIMIT $4;
+            ");
+
+            let articles = sqlx::query_as::<_, Article>(&query)
+                .bind(tag)
+                .bind(page.cursor.map(|o| o.publish_date))
+                .bind(page.cursor.map(|o| o.id))
+                .bind(page.limit + 1)
+                .bind(viewer_id)
+                .fetch_all(&self.pool).await?;
+
+            Ok(Page::from_rows(articles, page.limit, |o| DateCursor { publish_date: o.publish_date, id: o.id }))
+        }
+
+        /// Автодополнение тегов по началу названия. Сначала теги с большим количеством записей
+        /// ### Принимает:
+        ///
+        /// Начало названия тега (уже приведенное через `normalize_tag`), максимальное количество тегов
+        ///
+        /// ### Возвращает:
+        /// Если [`Ok`], то `Vec<Tag>`. При ошибки [`sqlx::Error`]
+        pub async fn autocomplete_tags(&self, prefix: &str, limit: i64) -> Result<Vec<Tag>, sqlx::Error> {
+            let rows = sqlx::query_as::<_, Tag>("
+                SELECT t.id, t.name, COUNT(a.id) AS articles
+                FROM tags AS t
+                LEFT JOIN articles_tags AS ats ON ats.tags_id = t.id
+                LEFT JOIN articles AS a ON a.id = ats.articles_id AND a.status = 'published'
+                WHERE t.name LIKE $1
+                GROUP BY t.id
+                ORDER BY articles DESC, t.name
+                LIMIT $2;
+            ")
+                .bind(format!("{}%", escape_like(prefix)))
+                .bind(limit)
+                .fetch_all(&self.pool).await?;
+
+            Ok(rows)
+        }
+
+        /// Популярные теги: количество записей с тегом, опубликованных начиная с `since`
+        /// ### Принимает:
+        ///
+        /// Начало периода, максимальное количество тегов
+        ///
+        /// ### Возвращает:
+        /// Если [`Ok`], то `Vec<Tag>`, по убыванию количества записей. При ошибки [`sqlx::Error`]
+        pub async fn get_trending_tags(&self, since: DateTime<Utc>, limit: i64) -> Result<Vec<Tag>, sqlx::Error> {
+            let rows = sqlx::query_as::<_, Tag>("
+                SELECT t.id, t.name, COUNT(a.id) AS articles
+                FROM tags AS t
+                JOIN articles_tags AS ats ON ats.tags_id = t.id
+                JOIN articles AS a ON a.id = ats.articles_id
+                WHERE a.status = 'published' AND a.publish_date >= $1
+                GROUP BY t.id
+                ORDER BY articles DESC, t.name
+                LIMIT $2;
+            ")
+                .bind(since)
+                .bind(limit)
+                .fetch_all(&self.pool).await?;
+
+            Ok(rows)
+        }
+
+        /// Получение ID тега по названию
+        /// ### Принимает:
+        ///
+        /// Тег (уже приведенный через `normalize_tag`)
+        ///
+        /// ### Возвращает:
+        /// Если [`Ok`], то `Option<i32>` - `None` если тег не найден. При ошибки [`sqlx::Error`]
+        pub async fn get_tag_id(&self, name: &str) -> Result<Option<i32>, sqlx::Error> {
+            let row = sqlx::query("
+                SELECT id
+                FROM tags
+                WHERE name = $1;
+            ")
+                .bind(name)
+                .fetch_optional(&self.pool).await?;
+
+            match row {
+                Some(row) => Ok(Some(row.try_get("id")?)),
+                None => Ok(None),
             }
+        }
 
-            Ok(articles)
+        /// Подписка пользователя на тег
+        /// ### Принимает:
+        ///
+        /// ID пользователя, ID тега
+        ///
+        /// ### Возвращает:
+        /// Если [`Ok`], то `true` - подписка создана, `false` - пользователь уже подписан. При ошибки [`sqlx::Error`]
+        pub async fn follow_tag(&self, user_id: i32, tag_id: i32) -> Result<bool, sqlx::Error> {
+            let result = sqlx::query("
+                INSERT INTO users_tags (users_id, tags_id)
+                VALUES($1, $2)
+                ON CONFLICT DO NOTHING;
+            ")
+                .bind(user_id)
+                .bind(tag_id)
+                .execute(&self.pool).await?;
+
+            Ok(result.rows_affected() > 0)
+        }
+
+        /// Отписка пользователя от тега
+        /// ### Принимает:
+        ///
+        /// ID пользователя, ID тега
+        ///
+        /// ### Возвращает:
+        /// Если [`Ok`], то `true` - подписка удалена, `false` - пользователь не был подписан. При ошибки [`sqlx::Error`]
+        pub async fn unfollow_tag(&self, user_id: i32, tag_id: i32) -> Result<bool, sqlx::Error> {
+            let result = sqlx::query("
+                DELETE FROM users_tags
+                WHERE users_id = $1 AND tags_id = $2;
+            ")
+                .bind(user_id)
+                .bind(tag_id)
+                .execute(&self.pool).await?;
+
+            Ok(result.rows_affected() > 0)
+        }
+
+        /// Получение тегов, на которые подписан пользователь
+        /// ### Принимает:
+        ///
+        /// ID пользователя
+        ///
+        /// ### Возвращает:
+        /// Если [`Ok`], то `Vec<Tag>`. При ошибки [`sqlx::Error`]
+        pub async fn get_followed_tags(&self, user_id: i32) -> Result<Vec<Tag>, sqlx::Error> {
+            let rows = sqlx::query_as::<_, Tag>("
+                SELECT t.id, t.name, COUNT(a.id) AS articles
+                FROM users_tags AS ut
+                JOIN tags AS t ON t.id = ut.tags_id
+                LEFT JOIN articles_tags AS ats ON ats.tags_id = t.id
+                LEFT JOIN articles AS a ON a.id = ats.articles_id AND a.status = 'published'
+                WHERE ut.users_id = $1
+                GROUP BY t.id
+                ORDER BY t.name;
+            ")
+                .bind(user_id)
+                .fetch_all(&self.pool).await?;
+
+            Ok(rows)
         }
 
         /// Создаем комментарий к записи в базе данных
         ///
         /// ### Принимает:
-        /// Структуру `InsertComment`
+        /// ID автора комментария, структуру `InsertComment`
         ///
         /// ### Возвращает:
         /// Если [`Ok`], то `()`. При ошибки [`sqlx::Error`]
-        pub async fn insert_comment_to_article(&self, comment: &InsertComment) -> Result<(), sqlx::Error> {
+        pub async fn insert_comment_to_article(&self, user_id: i32, comment: &InsertComment) -> Result<(), sqlx::Error> {
             let _ = sqlx::query("
                 INSERT INTO articles_comments
                 (users_id, articles_id, message)
                 VALUES($1, $2, $3);
             ")
-                .bind(comment.user_id)
+                .bind(user_id)
                 .bind(comment.article_id)
                 .bind(&comment.message)
                 .execute(&self.pool).await?;
             Ok(())
         }
 
-        /// Получение комментариев к записи
+        /// Получение комментариев к записи, от старых к новым
         /// ### Принимает:
         ///
-        /// ID записи
+        /// ID записи, запрос страницы с курсором по `publish_date, id`
         ///
         /// ### Возвращает:
-        /// Если [`Ok`], то `Vec<Comment>`. При ошибки [`sqlx::Error`]
-        pub async fn get_comments_from_article(&self, article_id: i32) -> Result<Vec<Comment>, sqlx::Error> {
-            let row = sqlx::query_as::<_, Comment>("
-                SELECT ac.id AS id, u.id AS user_id, first_name, last_name, about, crop_avatar, full_avatar, date_registration, message, publish_date
+        /// Если [`Ok`], то `Page<Comment>`. При ошибки [`sqlx::Error`]
+        pub async fn get_comments_from_article(&self, article_id: i32, page: &PageRequest<DateCursor>) -> Result<Page<Comment>, sqlx::Error> {
+            let rows = sqlx::query_as::<_, Comment>("
+                SELECT ac.id AS id, u.id AS user_id, first_name, last_name, about, crop_avatar_id AS crop_avatar, full_avatar_id AS full_avatar, date_registration, message, publish_date
                 FROM articles_comments AS ac, users AS u
-                WHERE ac.users_id = u.id AND ac.articles_id = $1;
+                WHERE ac.users_id = u.id AND ac.articles_id = $1
+                AND ($2::timestamptz IS NULL OR (ac.publish_date, ac.id) > ($2, $3))
+                ORDER BY ac.publish_date, ac.id
+                LIMIT $4;
             ")
                 .bind(article_id)
+                .bind(page.cursor.map(|o| o.publish_date))
+                .bind(page.cursor.map(|o| o.id))
+                .bind(page.limit + 1)
                 .fetch_all(&self.pool).await?;
 
-            Ok(row)
-        }
-
-        /// Получение реакций к записи
-        /// ### Принимает:
-        ///
-        /// ID записи
-        ///
-        /// ### Возвращает:
-        /// Если [`Ok`], то `(i64, i64)`. При ошибки [`sqlx::Error`]
-        pub async fn get_reactions_from_article(&self, article_id: i32) -> Result<(i64, i64), sqlx::Error> {
-            let row = sqlx::query("
-                SELECT COUNT(ar.id)
-                FROM articles_reactions AS ar, reactions AS r
-                WHERE ar.articles_id = $1 AND ar.reactions_id = r.id AND r.description = 'Нравится';
-            ")
-                .bind(article_id)
-                .fetch_one(&self.pool).await?;
-
-            let likes: i64 = row.try_get("count").unwrap();
-
-            let row = sqlx::query("
-                SELECT COUNT(ar.id)
-                FROM articles_reactions AS ar, reactions AS r
-                WHERE ar.articles_id = $1 AND ar.reactions_id = r.id AND r.description = 'Не нравится';
-            ")
-                .bind(article_id)
-                .fetch_one(&self.pool).await?;
-
-            let dislikes: i64 = row.try_get("count").unwrap();
-
-            Ok((likes, dislikes))
+            Ok(Page::from_rows(rows, page.limit, |o| DateCursor { publish_date: o.publish_date, id: o.id }))
         }
 
         /// Создание реакции к записи
         /// ### Принимает:
         ///
-        /// Структуру `InsertReaction`
+        /// ID пользователя, структуру `InsertReaction`
         ///
         /// ### Возвращает:
         /// Если [`Ok`], то `()`. При ошибки [`sqlx::Error`]
-        pub async fn insert_reaction_for_article(&self, reaction: &InsertReaction) -> Result<(), sqlx::Error> {
+        pub async fn insert_reaction_for_article(&self, user_id: i32, reaction: &InsertReaction) -> Result<(), sqlx::Error> {
             let _ = sqlx::query("
                 INSERT INTO articles_reactions
                 (users_id, articles_id, reactions_id)
                 VALUES($1, $2, (SELECT id FROM reactions WHERE description = $3));
             ")
-                .bind(reaction.user_id)
+                .bind(user_id)
                 .bind(reaction.article_id)
                 .bind(&reaction.reaction)
                 .execute(&self.pool).await?;
@@ -577,16 +1660,16 @@ pub mod postgresql_manager {
         /// Удаление реакции к записи
         /// ### Принимает:
         ///
-        /// Структуру `InsertReaction`
+        /// ID пользователя, структуру `InsertReaction`
         ///
         /// ### Возвращает:
         /// Если [`Ok`], то `()`. При ошибки [`sqlx::Error`]
-        pub async fn remove_reaction_for_article(&self, reaction: &InsertReaction) -> Result<(), sqlx::Error> {
+        pub async fn remove_reaction_for_article(&self, user_id: i32, reaction: &InsertReaction) -> Result<(), sqlx::Error> {
             let _ = sqlx::query("
                 DELETE FROM articles_reactions AS ar
                 WHERE ar.users_id = $1 AND ar.articles_id = $2
             ")
-                .bind(reaction.user_id)
+                .bind(user_id)
                 .bind(reaction.article_id)
                 .execute(&self.pool).await?;
 
@@ -596,27 +1679,38 @@ pub mod postgresql_manager {
         /// Проверка существования реакции к записи
         /// ### Принимает:
         ///
-        /// Структуру `InsertReaction`
+        /// ID пользователя, структуру `InsertReaction`
         ///
         /// ### Возвращает:
         /// Если [`Ok`], то `bool`. При ошибки [`sqlx::Error`]
-        pub async fn exists_reaction_for_article(&self, reaction: &InsertReaction) -> Result<bool, sqlx::Error> {
+        pub async fn exists_reaction_for_article(&self, user_id: i32, reaction: &InsertReaction) -> Result<bool, sqlx::Error> {
             let row = sqlx::query("
                 SELECT ar.id
                 FROM articles_reactions AS ar
                 WHERE ar.users_id = $1 AND ar.articles_id = $2
             ")
-                .bind(reaction.user_id)
+                .bind(user_id)
                 .bind(reaction.article_id)
-                .fetch_one(&self.pool).await;
+                .fetch_optional(&self.pool).await?;
 
-            if let Err(sqlx::Error::RowNotFound) = row {
-                return Ok(false);
-            }
+            Ok(row.is_some())
+        }
 
-            let row = row.unwrap();
+        /// Проверяет, есть ли реакция с таким названием в таблице `reactions`
+        /// ### Принимает:
+        ///
+        /// Название реакции
+        ///
+        /// ### Возвращает:
+        /// Если [`Ok`], то `true` - реакция найдена, иначе `false`. При ошибки [`sqlx::Error`]
+        pub async fn exist_reaction(&self, name: &str) -> Result<bool, sqlx::Error> {
+            let row = sqlx::query("
+                SELECT EXISTS (SELECT 1 FROM reactions WHERE description = $1) AS exists;
+            ")
+                .bind(name)
+                .fetch_one(&self.pool).await?;
 
-            Ok(row.try_get::<i32, _>("id").is_ok())
+            row.try_get("exists")
         }
 
         /// Получить реакцию пользователя на запись
@@ -634,107 +1728,714 @@ pub mod postgresql_manager {
             ")
                 .bind(user_id)
                 .bind(article_id)
-                .fetch_one(&self.pool).await;
+                .fetch_optional(&self.pool).await?;
 
-            if let Err(sqlx::Error::RowNotFound) = row {
-                return Ok(None);
-            }
-
-            let row = row.unwrap();
-
-            if row.try_get::<String, _>("description").is_err() {
-                return Ok(None);
+            match row {
+                Some(row) => Ok(Some(row.try_get("description")?)),
+                None => Ok(None),
             }
-
-            Ok(Some(row.get("description")))
         }
 
-        /// Получить популярных пользователей
+        /// Получить популярных пользователей, по убыванию количества подписчиков
         /// ### Принимает:
         ///
-        /// ID пользователя
+        /// ID пользователя, запрос страницы с курсором по количеству подписчиков и `id`
         ///
         /// ### Возвращает:
-        /// Если [`Ok`], то `Vec<User>`. При ошибки [`sqlx::Error`]
-        pub async fn get_popular_users(&self, user_id: i32) -> Result<Vec<PopularUser>, sqlx::Error> {
-            let row = sqlx::query_as::<_, PopularUser>("
+        /// Если [`Ok`], то `Page<PopularUser>`. При ошибки [`sqlx::Error`]
+        pub async fn get_popular_users(&self, user_id: i32, page: &PageRequest<FollowersCursor>) -> Result<Page<PopularUser>, sqlx::Error> {
+            let rows = sqlx::query_as::<_, PopularUser>("
                 SELECT COUNT(u.id) AS followers, u.id AS user_id, first_name, last_name, about,
-                password, login, full_avatar, crop_avatar, date_registration
+                login, full_avatar_id AS full_avatar, crop_avatar_id AS crop_avatar, date_registration
                 FROM users AS u, users_followers AS uf
                 WHERE uf.users_author_id = u.id AND u.id != $1
                 GROUP BY u.id
-                ORDER BY followers DESC
+                HAVING ($2::int8 IS NULL OR (COUNT(u.id), u.id) < ($2, $3))
+                ORDER BY followers DESC, u.id DESC
+                LIMIT $4;
             ")
                 .bind(user_id)
-                .fetch_all(&self.pool).await;
+                .bind(page.cursor.map(|o| o.followers))
+                .bind(page.cursor.map(|o| o.id))
+                .bind(page.limit + 1)
+                .fetch_all(&self.pool).await?;
 
-            if let Err(sqlx::Error::RowNotFound) = row {
+            Ok(Page::from_rows(rows, page.limit, |o| FollowersCursor { followers: o.followers, id: o.id }))
+        }
+
+        /// Нечеткий поиск пользователей по ключевым словам.
+        ///
+        /// Каждое слово должно быть похоже (триграммы `pg_trgm`) на имя, фамилию или логин пользователя,
+        /// либо встречаться в них или в описании. Слова ищутся и в транслитерации (`ivanov` / `иванов`).
+        /// ### Принимает:
+        ///
+        /// ID пользователя (исключается из результата), ключевые слова через пробел - `&str`
+        ///
+        /// ### Возвращает:
+        /// Если [`Ok`], то `Vec<FoundUser>`, отсортированный по релевантности, затем по количеству подписчиков.
+        /// При ошибки [`sqlx::Error`]
+        pub async fn find_user_by_key_words(&self, user_id: i32, words: &str) -> Result<Vec<FoundUser>, sqlx::Error> {
+            let terms = search_terms(words);
+
+            if terms.is_empty() {
                 return Ok(Vec::with_capacity(0));
             }
 
-            Ok(row.unwrap())
+            let search = user_search(&terms);
+
+            let mut tx = self.pool.begin().await?;
+
+            // Порог для оператора `<%`, чтобы поиск мог использовать GIN индексы
+            sqlx::query("SELECT set_config('pg_trgm.word_similarity_threshold', $1, true);")
+                .bind(SEARCH_SIMILARITY_THRESHOLD.to_string())
+                .execute(&mut tx).await?;
+
+            let rows = sqlx::query_as::<_, FoundUser>(FIND_USERS_QUERY)
+                .bind(user_id)
+                .bind(&search.spellings)
+                .bind(&search.patterns)
+                .bind(&search.terms)
+                .bind(terms.len() as i32)
+                .bind(MAX_SEARCH_RESULTS)
+                .fetch_all(&mut tx).await?;
+
+            tx.commit().await?;
+
+            Ok(rows)
         }
 
-        /// Получить популярных пользователей
+        /// Установка роли пользователя
         /// ### Принимает:
         ///
-        /// ID пользователя, ключевое слово - `&str`
+        /// ID пользователя, роль
         ///
         /// ### Возвращает:
-        /// Если [`Ok`], то `Vec<PopularUser>`. При ошибки [`sqlx::Error`]
-        pub async fn find_user_by_key_words(&self, user_id: i32, word: &str) -> Result<Vec<PopularUser>, sqlx::Error> {
-            let words = word.split(' ').collect::<Vec<&str>>();
+        /// Если [`Ok`], то `true` - роль изменена, `false` - пользователь не найден. При ошибки [`sqlx::Error`]
+        pub async fn set_user_role(&self, user_id: i32, role: Role) -> Result<bool, sqlx::Error> {
+            let result = sqlx::query("
+                UPDATE users
+                SET role = $2
+                WHERE id = $1;
+            ")
+                .bind(user_id)
+                .bind(role)
+                .execute(&self.pool).await?;
 
-            if words.is_empty() {
-                return Ok(Vec::with_capacity(0));
+            Ok(result.rows_affected() > 0)
+        }
+
+        /// Запись привилегированного действия в журнал модерации
+        /// ### Принимает:
+        ///
+        /// ID пользователя (кто выполнил), название действия, тип и ID объекта, подробности
+        ///
+        /// ### Возвращает:
+        /// Если [`Ok`], то `()`. При ошибки [`sqlx::Error`]
+        pub async fn insert_moderation_record(&self, actor_id: i32, action: &str, target_type: &str, target_id: i32, details: Option<&str>) -> Result<(), sqlx::Error> {
+            let _ = sqlx::query("
+                INSERT INTO moderation_log
+                (actor_id, action, target_type, target_id, details)
+                VALUES($1, $2, $3, $4, $5);
+            ")
+                .bind(actor_id)
+                .bind(action)
+                .bind(target_type)
+                .bind(target_id)
+                .bind(details)
+                .execute(&self.pool).await?;
+
+            Ok(())
+        }
+
+        /// Получение журнала модерации, новые записи первыми
+        /// ### Принимает:
+        ///
+        /// Максимальное количество записей
+        ///
+        /// ### Возвращает:
+        /// Если [`Ok`], то `Vec<ModerationRecord>`. При ошибки [`sqlx::Error`]
+        pub async fn get_moderation_log(&self, limit: i64) -> Result<Vec<ModerationRecord>, sqlx::Error> {
+            let rows = sqlx::query_as::<_, ModerationRecord>("
+                SELECT id, actor_id, action, target_type, target_id, details, date
+                FROM moderation_log
+                ORDER BY id DESC
+                LIMIT $1;
+            ")
+                .bind(limit)
+                .fetch_all(&self.pool).await?;
+
+            Ok(rows)
+        }
+    }
+
+    /// Сохранение текущей версии записи в `article_revisions`. Строка записи блокируется до конца транзакции
+    async fn save_article_revision(tx: &mut Transaction<'_, Postgres>, editor_id: i32, article_id: i32) -> Result<(), sqlx::Error> {
+        sqlx::query("
+            INSERT INTO article_revisions (articles_id, editor_id, image_id, title, description, description_html, description_text, crop_description, summary, reading_time)
+            SELECT id, $2, image_id, title, description, description_html, description_text, crop_description, summary, reading_time
+            FROM articles
+            WHERE id = $1
+            FOR UPDATE;
+        ")
+            .bind(article_id)
+            .bind(editor_id)
+            .execute(&mut *tx).await?;
+
+        Ok(())
+    }
+
+    /// Отметка, что запись изменена
+    async fn touch_article(tx: &mut Transaction<'_, Postgres>, article_id: i32) -> Result<(), sqlx::Error> {
+        sqlx::query("
+            UPDATE articles
+            SET edited_date = now()
+            WHERE id = $1;
+        ")
+            .bind(article_id)
+            .execute(&mut *tx).await?;
+
+        Ok(())
+    }
+
+    /// Запись картинки из хранилища файлов в `images`. Если такая картинка уже есть, то вернется ее ID
+    async fn insert_image(tx: &mut Transaction<'_, Postgres>, image: &StoredImage) -> Result<i32, sqlx::Error> {
+        let row = sqlx::query("
+            INSERT INTO images (hash, content_type)
+            VALUES ($1, $2)
+            ON CONFLICT (hash) DO UPDATE SET hash = EXCLUDED.hash
+            RETURNING id;
+        ")
+            .bind(&image.hash)
+            .bind(&image.content_type)
+            .fetch_one(&mut *tx).await?;
+
+        row.try_get("id")
+    }
+
+    /// Почему часть нельзя добавить к загрузке. [`None`], если смещение совпадает с полученным
+    /// и часть помещается в загрузку
+    fn reject_upload_chunk(upload: Option<&Upload>, offset: i64, length: i64) -> Option<AppendChunk> {
+        let upload = match upload {
+            Some(o) => o,
+            None => return Some(AppendChunk::NotFound),
+        };
+
+        if upload.media_id.is_some() {
+            return Some(AppendChunk::Completed);
+        }
+
+        if offset != upload.received {
+            return Some(AppendChunk::OffsetMismatch(upload.received));
+        }
+
+        if upload.received + length > upload.size {
+            return Some(AppendChunk::Overflow);
+        }
+
+        None
+    }
+
+    /// Замена тегов записи. Новые теги создаются в `tags`
+    async fn set_article_tags(tx: &mut Transaction<'_, Postgres>, article_id: i32, tags: &[String]) -> Result<(), sqlx::Error> {
+        let tags = normalize_tags(tags);
+
+        sqlx::query("
+            DELETE FROM articles_tags
+            WHERE articles_id = $1 AND tags_id NOT IN (SELECT id FROM tags WHERE name = ANY($2));
+        ")
+            .bind(article_id)
+            .bind(&tags)
+            .execute(&mut *tx).await?;
+
+        if tags.is_empty() {
+            return Ok(());
+        }
+
+        sqlx::query("
+            INSERT INTO tags (name)
+            SELECT unnest($1::varchar[])
+            ON CONFLICT (name) DO NOTHING;
+        ")
+            .bind(&tags)
+            .execute(&mut *tx).await?;
+
+        sqlx::query("
+            INSERT INTO articles_tags (articles_id, tags_id)
+            SELECT $1, id FROM tags WHERE name = ANY($2)
+            ON CONFLICT DO NOTHING;
+        ")
+            .bind(article_id)
+            .bind(&tags)
+            .execute(&mut *tx).await?;
+
+        Ok(())
+    }
+
+    /// Колонки записи для `Article`. Количество реакций и комментариев берется из `rs` и `cs` в [`article_select`]
+    const ARTICLE_COLUMNS: &str = "
+        a.id AS article_id, a.image_id AS image, title, COALESCE(a.summary, a.crop_description) AS crop_description, a.summary, a.reading_time,
+        publish_date, edited_date, status, publish_at,
+        ARRAY(SELECT t.name FROM articles_tags AS ats JOIN tags AS t ON t.id = ats.tags_id WHERE ats.articles_id = a.id ORDER BY t.name) AS tags,
+        ARRAY(SELECT ROW(m.id, m.image_id, m.position, m.caption, m.alt)::article_media_item FROM articles_media AS m WHERE m.articles_id = a.id ORDER BY m.position) AS gallery,
+        u.id AS user_id, first_name, last_name, about, login, full_avatar_id AS full_avatar, crop_avatar_id AS crop_avatar, date_registration,
+        rs.likes, rs.dislikes, rs.reaction, cs.comments
+    ";
+
+    /// Колонки текста записи для `FullArticle`
+    const ARTICLE_BODY_COLUMNS: &str = "description_text AS full_description, description AS description_markdown, description_html";
+
+    /// Начало запроса записей до `WHERE`: колонки [`ARTICLE_COLUMNS`] и `columns`, автор записи,
+    /// количество реакций (`rs`) и комментариев (`cs`) одним проходом по каждой записи, затем соединения `joins`.
+    ///
+    /// `viewer` - номер параметра с ID текущего пользователя, для которого выбирается его реакция
+    fn article_select(viewer: u8, columns: &str, joins: &str) -> String {
+        let columns = if columns.is_empty() { String::new() } else { format!(", {}", columns) };
+
+        format!("
+            SELECT {ARTICLE_COLUMNS}{columns}
+            FROM articles AS a
+            JOIN users AS u ON u.id = a.author_id
+            CROSS JOIN LATERAL (
+                SELECT COUNT(*) FILTER (WHERE r.description = 'Нравится') AS likes,
+                       COUNT(*) FILTER (WHERE r.description = 'Не нравится') AS dislikes,
+                       MAX(r.description) FILTER (WHERE ar.users_id = ${viewer}) AS reaction
+                FROM articles_reactions AS ar
+                JOIN reactions AS r ON r.id = ar.reactions_id
+                WHERE ar.articles_id = a.id
+            ) AS rs
+            CROSS JOIN LATERAL (
+                SELECT COUNT(*) AS comments
+                FROM articles_comments AS ac
+                WHERE ac.articles_id = a.id
+            ) AS cs
+            {joins}
+        ")
+    }
+
+    /// Во сколько раз комментарий весит больше реакции в оценке популярных записей
+    const TRENDING_COMMENT_WEIGHT: i64 = 2;
+
+    /// Как быстро оценка популярной записи уменьшается с возрастом
+    const TRENDING_GRAVITY: f64 = 1.5;
+
+    /// Максимальное количество ключевых слов в поисковом запросе
+    const MAX_SEARCH_TERMS: usize = 8;
+
+    /// Максимальная длина одного ключевого слова
+    const MAX_SEARCH_TERM_LENGTH: usize = 64;
+
+    /// Максимальное количество пользователей в результате поиска
+    const MAX_SEARCH_RESULTS: i64 = 50;
+
+    /// Порог похожести слова (`word_similarity`), ниже которого пользователь не попадает в результат
+    const SEARCH_SIMILARITY_THRESHOLD: f32 = 0.4;
+
+    /// Сколько первых символов описания передается в `ts_headline` для фрагмента найденной записи.
+    /// `ts_headline` разбирает весь переданный текст заново, а описание может занимать сотни тысяч символов
+    const SEARCH_SNIPPET_SOURCE_LENGTH: usize = 4096;
+
+    /// Поиск пользователей по словам. Все значения из запроса пользователя передаются параметрами:
+    /// `$2` - варианты написания слов, `$3` - шаблоны `LIKE` для них, `$4` - номер слова для каждого варианта
+    const FIND_USERS_QUERY: &str = r#"
+        WITH spellings AS (
+            SELECT * FROM unnest($2::text[], $3::text[], $4::int4[]) AS s(spelling, pattern, term)
+        ), scores AS (
+            SELECT u.id, s.term, MAX(GREATEST(
+                CASE WHEN lower(u.first_name) LIKE s.pattern
+                    OR lower(u.last_name) LIKE s.pattern
+                    OR lower(u.login) LIKE s.pattern THEN 1.0 ELSE 0.0 END,
+                CASE WHEN lower(u.about) LIKE s.pattern THEN 0.5 ELSE 0.0 END,
+                word_similarity(s.spelling, lower(u.first_name)),
+                word_similarity(s.spelling, lower(u.last_name)),
+                word_similarity(s.spelling, lower(u.login))
+            )) AS score
+            FROM users AS u
+            JOIN spellings AS s ON
+                s.spelling <% lower(u.first_name)
+                OR s.spelling <% lower(u.last_name)
+                OR s.spelling <% lower(u.login)
+                OR lower(u.first_name) LIKE s.pattern
+                OR lower(u.last_name) LIKE s.pattern
+                OR lower(u.login) LIKE s.pattern
+                OR lower(u.about) LIKE s.pattern
+            WHERE u.id != $1
+            GROUP BY u.id, s.term
+        ), relevance AS (
+            SELECT id, CAST(SUM(score) / $5 AS float4) AS relevance
+            FROM scores
+            GROUP BY id
+            HAVING COUNT(*) = $5
+        )
+        SELECT r.relevance, (
+            SELECT COUNT(*) FROM users_followers AS uf WHERE uf.users_author_id = u.id
+        ) AS followers,
+        u.id AS user_id, u.first_name, u.last_name, u.about,
+        u.login, u.full_avatar_id AS full_avatar, u.crop_avatar_id AS crop_avatar, u.date_registration
+        FROM relevance AS r
+        JOIN users AS u ON u.id = r.id
+        ORDER BY r.relevance DESC, followers DESC, u.id
+        LIMIT $6;
+    "#;
+
+    /// Параметры поиска пользователей: варианты написания каждого слова, экранированные шаблоны `LIKE`
+    /// и номер слова, к которому относится вариант
+    struct UserSearch {
+        spellings: Vec<String>,
+        patterns: Vec<String>,
+        terms: Vec<i32>,
+    }
+
+    fn user_search(terms: &[String]) -> UserSearch {
+        let mut search = UserSearch {
+            spellings: Vec::new(),
+            patterns: Vec::new(),
+            terms: Vec::new(),
+        };
+
+        for (index, term) in terms.iter().enumerate() {
+            for spelling in spellings_of(term) {
+                search.patterns.push(format!("%{}%", escape_like(&spelling)));
+                search.spellings.push(spelling);
+                search.terms.push(index as i32);
             }
+        }
 
-            let query = match words.len() {
-                1 => {
-                    format!(r#"
-                        SELECT CAST(0 AS int8) AS followers, u.id AS user_id, u.first_name, u.last_name, u.about,
-                        u.password, u.login, u.full_avatar, u.crop_avatar, u.date_registration
-                        FROM users AS u
-                        WHERE u.first_name LIKE '%{0}%' AND u.last_name LIKE '%{1}%' AND u.id != {2}
-                        OR u.first_name LIKE '%{1}%' AND u.last_name LIKE '%{0}%' AND u.id != {2};
-                    "#, words[0], String::new(), user_id)
-                },
-                _ => {
-                    format!(r#"
-                        SELECT CAST(0 AS int8) AS followers, u.id AS user_id, u.first_name, u.last_name, u.about,
-                        u.password, u.login, u.full_avatar, u.crop_avatar, u.date_registration
-                        FROM users AS u
-                        WHERE u.first_name LIKE '%{0}%' AND u.last_name LIKE '%{1}%' AND u.id != {2}
-                        OR u.first_name LIKE '%{1}%' AND u.last_name LIKE '%{0}%' AND u.id != {2};
-                    "#, words[0], words[1], user_id)
-                },
-            };
+        search
+    }
 
-            let row = sqlx::query_as::<_, PopularUser>(&query)
-                .fetch_all(&self.pool)
-                .await;
+    /// Разбивает строку поиска на слова в нижнем регистре без повторов.
+    /// Управляющие символы отбрасываются: PostgreSQL не принимает `\0` в тексте
+    fn search_terms(words: &str) -> Vec<String> {
+        let mut terms: Vec<String> = Vec::new();
 
-            if let Err(sqlx::Error::RowNotFound) = row {
-                return Ok(Vec::with_capacity(0));
+        for word in words.split_whitespace() {
+            let term = word.chars()
+                .filter(|o| !o.is_control())
+                .take(MAX_SEARCH_TERM_LENGTH)
+                .collect::<String>()
+                .to_lowercase();
+
+            if !term.is_empty() && !terms.contains(&term) {
+                terms.push(term);
+            }
+
+            if terms.len() == MAX_SEARCH_TERMS {
+                break;
             }
+        }
+
+        terms
+    }
 
-            let mut row = row.unwrap();
+    /// Символы `%`, `_` и `\` экранируются для `LIKE`, поэтому ищутся как обычные символы
+    fn escape_like(word: &str) -> String {
+        let mut escaped = String::with_capacity(word.len());
 
-            for user in &mut row {
-                let followers = self.get_user_count_followers(user.id).await;
+        for c in word.chars() {
+            if matches!(c, '%' | '_' | '\\') {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+
+        escaped
+    }
 
-                if let Err(e) = &followers {
-                    log(Level::Error, "[PostgresSQL][find_user_by_key_words] >>> get_user_count_followers",
-                        &format!("Handle: {}", e)
-                    );
+    #[cfg(test)]
+    mod tests {
+        use sqlx::Row;
+        use super::*;
+        use crate::markdown::render::render_markdown;
+        use crate::postgresql::models::model_article::article::ArticleStatus;
+        use crate::postgresql::models::model_image::image::{image_hash, StoredImage};
+        use crate::postgresql::models::model_user::user::Role;
+        use crate::testing::fixtures::{connect, insert_article, insert_user};
+
+        const HOSTILE_INPUTS: [&str; 7] = [
+            "%",
+            "_",
+            "\\",
+            "o'brien \"quoted\"",
+            "'; DROP TABLE users; --",
+            "a\0b \u{1}\u{7f}",
+            "%_\\' \0",
+        ];
+
+        #[test]
+        fn escape_like_escapes_wildcards() {
+            assert_eq!(escape_like("%"), "\\%");
+            assert_eq!(escape_like("_"), "\\_");
+            assert_eq!(escape_like("\\"), "\\\\");
+            assert_eq!(escape_like("100%_a\\b"), "100\\%\\_a\\\\b");
+        }
+
+        #[test]
+        fn escape_like_keeps_quotes() {
+            assert_eq!(escape_like("o'brien \"quoted\""), "o'brien \"quoted\"");
+            assert_eq!(escape_like("'; DROP TABLE users; --"), "'; DROP TABLE users; --");
+        }
+
+        #[test]
+        fn search_terms_of_injection() {
+            assert_eq!(search_terms("'; DROP TABLE users; --"), vec!["';", "drop", "table", "users;", "--"]);
+        }
+
+        #[test]
+        fn search_terms_strip_control_chars() {
+            assert_eq!(search_terms("a\0b"), vec!["ab"]);
+            assert_eq!(search_terms("\0 \u{1}\u{7f} \u{1b}"), Vec::<String>::new());
+            assert_eq!(search_terms("a\u{9f}b"), vec!["ab"]);
+        }
+
+        #[test]
+        fn search_terms_of_whitespace() {
+            assert!(search_terms("").is_empty());
+            assert!(search_terms(" \t\r\n\u{3000}").is_empty());
+        }
+
+        #[test]
+        fn search_terms_of_long_input() {
+            let word = "Я".repeat(MAX_SEARCH_TERM_LENGTH * 100);
+            assert_eq!(search_terms(&word), vec!["я".repeat(MAX_SEARCH_TERM_LENGTH)]);
+
+            let words = (0..1000).map(|o| o.to_string()).collect::<Vec<_>>().join(" ");
+            assert_eq!(search_terms(&words), (0..MAX_SEARCH_TERMS).map(|o| o.to_string()).collect::<Vec<_>>());
+        }
+
+        #[test]
+        fn search_terms_lowercase_without_repeats() {
+            assert_eq!(search_terms("Ivan IVAN ivan Petrov"), vec!["ivan", "petrov"]);
+        }
+
+        #[test]
+        fn user_search_patterns_are_escaped() {
+            for input in HOSTILE_INPUTS {
+                let terms = search_terms(input);
+                let search = user_search(&terms);
+
+                assert_eq!(search.spellings.len(), search.patterns.len());
+                assert_eq!(search.spellings.len(), search.terms.len());
+
+                for (spelling, pattern) in search.spellings.iter().zip(&search.patterns) {
+                    assert_eq!(pattern, &format!("%{}%", escape_like(spelling)));
+                    assert!(!spelling.chars().any(char::is_control));
                 }
+            }
+        }
 
-                user.followers = followers.unwrap();
+        #[test]
+        fn find_users_query_is_static() {
+            for param in ["$1", "$2::text[]", "$3::text[]", "$4::int4[]", "$5", "$6"] {
+                assert!(FIND_USERS_QUERY.contains(param), "{} is not bound", param);
             }
+            assert!(!FIND_USERS_QUERY.contains('{'));
+        }
 
-            Ok(row)
+        #[test]
+        fn listing_queries_do_not_select_password() {
+            assert!(!ARTICLE_COLUMNS.contains("password"));
+            assert!(!article_select(1, ARTICLE_BODY_COLUMNS, "").contains("password"));
+            assert!(!FIND_USERS_QUERY.contains("password"));
+        }
+
+        #[actix_web::test]
+        async fn find_user_by_hostile_key_words() {
+            let Some(conn) = connect().await else { return };
+            let viewer = insert_user(&conn, Role::User).await;
+            let found = insert_user(&conn, Role::User).await;
+
+            for input in HOSTILE_INPUTS {
+                conn.find_user_by_key_words(viewer.auth.id, input).await
+                    .unwrap_or_else(|e| panic!("{:?}: {}", input, e));
+            }
+
+            conn.find_user_by_key_words(viewer.auth.id, &"x".repeat(1 << 16)).await.unwrap();
+
+            let login: String = sqlx::query("SELECT login FROM users WHERE id = $1;")
+                .bind(found.auth.id)
+                .fetch_one(&conn.pool).await.unwrap()
+                .get("login");
+
+            // `_` в логине совпадает только с `_`, а не с любым символом
+            let users = conn.find_user_by_key_words(viewer.auth.id, &login).await.unwrap();
+            assert!(users.iter().any(|o| o.user.id == found.auth.id));
+
+            let users = conn.find_user_by_key_words(viewer.auth.id, &login.replace('_', "x")).await.unwrap();
+            assert!(!users.iter().any(|o| o.user.id == found.auth.id && o.relevance >= 1.0));
+        }
+
+        #[actix_web::test]
+        async fn set_avatar_of_missing_user() {
+            let Some(conn) = connect().await else { return };
+            let avatar = StoredImage {
+                hash: image_hash(b"missing user avatar"),
+                content_type: "image/png".to_owned(),
+            };
+
+            let result = conn.set_avatar_by_id(i32::MAX, &avatar, &avatar).await;
+            assert!(matches!(result, Err(sqlx::Error::RowNotFound)));
+
+            let images: i64 = sqlx::query("SELECT COUNT(*) FROM images WHERE hash = $1;")
+                .bind(&avatar.hash)
+                .fetch_one(&conn.pool).await.unwrap()
+                .get("count");
+            assert_eq!(images, 0);
+        }
+
+        #[actix_web::test]
+        async fn upload_chunk_is_written_only_when_accepted() {
+            let Some(conn) = connect().await else { return };
+            let user = insert_user(&conn, Role::User).await;
+            let upload = conn.insert_upload(user.auth.id, 8).await.unwrap();
+
+            let written = &std::cell::Cell::new(0);
+            let write = |length: i64| async move {
+                written.set(written.get() + length);
+                Ok(())
+            };
+
+            let result = conn.append_upload_chunk(user.auth.id, upload.id, 4, 4, write(4)).await.unwrap();
+            assert_eq!(result, AppendChunk::OffsetMismatch(0));
+            let result = conn.append_upload_chunk(user.auth.id, upload.id, 0, 16, write(16)).await.unwrap();
+            assert_eq!(result, AppendChunk::Overflow);
+            assert_eq!(written.get(), 0);
+
+            let AppendChunk::Accepted(upload) = conn.append_upload_chunk(user.auth.id, upload.id, 0, 4, write(4)).await.unwrap() else {
+                panic!("chunk at offset 0 must be accepted")
+            };
+            assert_eq!(upload.received, 4);
+            assert_eq!(written.get(), 4);
+
+            let failed = async { Err(std::io::Error::other("disk is full")) };
+            let result = conn.append_upload_chunk(user.auth.id, upload.id, 4, 4, failed).await;
+            assert!(matches!(result, Err(sqlx::Error::Io(_))));
+            assert_eq!(conn.get_upload(user.auth.id, upload.id).await.unwrap().unwrap().received, 4);
+        }
+
+        #[actix_web::test]
+        async fn upload_chunk_is_not_counted_if_upload_changed_during_write() {
+            let Some(conn) = connect().await else { return };
+            let user = insert_user(&conn, Role::User).await;
+            let upload = conn.insert_upload(user.auth.id, 8).await.unwrap();
+            let image = StoredImage {
+                hash: image_hash(b"completed during write"),
+                content_type: "image/png".to_owned(),
+            };
+
+            // другой запрос успел добавить часть, пока эта записывалась
+            let conn = &conn;
+            let moved = async move {
+                sqlx::query("UPDATE uploads SET received = 2 WHERE id = $1;")
+                    .bind(upload.id)
+                    .execute(&conn.pool).await.unwrap();
+                Ok(())
+            };
+            let result = conn.append_upload_chunk(user.auth.id, upload.id, 0, 4, moved).await.unwrap();
+            assert_eq!(result, AppendChunk::OffsetMismatch(2));
+            assert_eq!(conn.get_upload(user.auth.id, upload.id).await.unwrap().unwrap().received, 2);
+
+            // загрузку завершили, пока часть записывалась
+            let completed = async move {
+                conn.complete_upload(user.auth.id, upload.id, &image).await.unwrap().unwrap();
+                Ok(())
+            };
+            let result = conn.append_upload_chunk(user.auth.id, upload.id, 2, 4, completed).await.unwrap();
+            assert_eq!(result, AppendChunk::Completed);
+            assert_eq!(conn.get_upload(user.auth.id, upload.id).await.unwrap().unwrap().received, 2);
+        }
+
+        #[actix_web::test]
+        async fn expired_uploads_keep_completed_media() {
+            let Some(conn) = connect().await else { return };
+            let user = insert_user(&conn, Role::User).await;
+            let image = StoredImage {
+                hash: image_hash(b"completed upload"),
+                content_type: "image/png".to_owned(),
+            };
+
+            let abandoned = conn.insert_upload(user.auth.id, 4).await.unwrap();
+            let completed = conn.insert_upload(user.auth.id, 4).await.unwrap();
+            let media_id = conn.complete_upload(user.auth.id, completed.id, &image).await.unwrap().unwrap();
+
+            sqlx::query("
+                UPDATE uploads
+                SET updated_at = now() - interval '2 days', completed_at = now() - interval '2 days'
+                WHERE users_id = $1;
+            ")
+                .bind(user.auth.id)
+                .execute(&conn.pool).await.unwrap();
+
+            let now = Utc::now();
+
+            conn.remove_expired_uploads(now - chrono::Duration::days(1), now - chrono::Duration::days(30)).await.unwrap();
+            assert!(conn.get_upload(user.auth.id, abandoned.id).await.unwrap().is_none());
+            assert!(conn.get_upload_image(user.auth.id, media_id).await.unwrap().is_some());
+
+            conn.remove_expired_uploads(now - chrono::Duration::days(1), now - chrono::Duration::days(1)).await.unwrap();
+            assert!(conn.get_upload_image(user.auth.id, media_id).await.unwrap().is_none());
+        }
+
+        #[actix_web::test]
+        async fn scheduled_article_is_published_after_loaded_pages() {
+            let Some(conn) = connect().await else { return };
+            let user = insert_user(&conn, Role::User).await;
+            let loaded = insert_article(&conn, user.auth.id).await;
+            let scheduled = insert_article(&conn, user.auth.id).await;
+
+            sqlx::query("UPDATE articles SET status = 'scheduled', publish_at = now() - interval '1 hour' WHERE id = $1;")
+                .bind(scheduled)
+                .execute(&conn.pool).await.unwrap();
+
+            let cursor: DateTime<Utc> = sqlx::query("SELECT publish_date FROM articles WHERE id = $1;")
+                .bind(loaded)
+                .fetch_one(&conn.pool).await.unwrap()
+                .get("publish_date");
+
+            assert!(conn.publish_scheduled_articles().await.unwrap() >= 1);
+
+            let row = sqlx::query("SELECT status::text, publish_date FROM articles WHERE id = $1;")
+                .bind(scheduled)
+                .fetch_one(&conn.pool).await.unwrap();
+            assert_eq!(row.get::<String, _>("status"), "published");
+            assert!(row.get::<DateTime<Utc>, _>("publish_date") > cursor);
+        }
+
+        #[actix_web::test]
+        async fn search_snippet_of_long_article_is_bounded() {
+            let Some(conn) = connect().await else { return };
+            let user = insert_user(&conn, Role::User).await;
+            let first = format!("first{}", user.auth.id);
+            let last = format!("last{}", user.auth.id);
+            let article = InsertArticle {
+                image: None,
+                media_id: None,
+                title: "Long".to_owned(),
+                description: format!("{first} {} {last}", "filler ".repeat(SEARCH_SNIPPET_SOURCE_LENGTH)),
+                summary: None,
+                status: ArticleStatus::Published,
+                publish_at: None,
+                tags: Vec::new(),
+            };
+            let image = StoredImage {
+                hash: image_hash(b"long article"),
+                content_type: "image/png".to_owned(),
+            };
+            conn.insert_article(user.auth.id, &article, &image, &render_markdown(&article.description)).await.unwrap();
+
+            let search = |query: &str| ArticleSearch { query: query.to_owned(), author_id: Some(user.auth.id), from: None, to: None, limit: 10 };
+
+            let found = conn.search_articles(None, &search(&first)).await.unwrap();
+            assert_eq!(found.len(), 1);
+            assert!(found[0].snippet.contains(&format!("<b>{first}</b>")), "{}", found[0].snippet);
+
+            // Слово за пределами фрагмента находится по `search_vector`, но не подсвечивается
+            let found = conn.search_articles(None, &search(&last)).await.unwrap();
+            assert_eq!(found.len(), 1);
+            assert!(!found[0].snippet.contains("<b>"), "{}", found[0].snippet);
+            assert!(found[0].snippet.len() < SEARCH_SNIPPET_SOURCE_LENGTH);
         }
     }
 }
 
-pub mod models;
\ No newline at end of file
+pub mod models;
+pub mod migrations;
\ No newline at end of file
@@ -1,2 +1,9 @@
 pub(crate) mod model_user;
-pub(crate) mod model_article;
\ No newline at end of file
+pub(crate) mod model_article;
+pub(crate) mod model_session;
+pub(crate) mod model_moderation;
+pub(crate) mod model_validation;
+pub(crate) mod model_page;
+pub(crate) mod model_tag;
+pub(crate) mod model_image;
+pub(crate) mod model_upload;
@@ -1,39 +1,274 @@
 pub(crate) mod article {
     use chrono::{DateTime, Utc};
     use serde::{Serialize, Deserialize};
+    use validator::{Validate, ValidationErrors};
+    use sqlx::postgres::{PgHasArrayType, PgTypeInfo};
     use crate::postgresql::models::model_user::user::User;
+    use crate::postgresql::models::model_validation::validation::{
+        deserialize_form_number,
+        deserialize_optional_form_number,
+        error,
+        validate_base64,
+        validate_not_blank,
+    };
+    use crate::postgresql::models::model_tag::tag::validate_tags;
+    use crate::postgresql::models::model_image::image::ImageUrl;
+    use crate::markdown::render::RenderedMarkdown;
 
+    /// Максимальное количество картинок в галерее записи
+    pub const MAX_ARTICLE_MEDIA: usize = 20;
+
+    /// Максимальная длина текста записи в Markdown, в символах
+    pub const MAX_DESCRIPTION_LENGTH: u64 = 262_144;
+
+    /// Максимальная длина краткого описания, в символах
+    pub const MAX_SUMMARY_LENGTH: u64 = 512;
+
+    /// Статус записи, хранится в `articles.status`
+    #[derive(Clone, Copy, Eq, Hash, PartialEq, Debug, Default, Serialize, Deserialize, sqlx::Type)]
+    #[sqlx(type_name = "varchar", rename_all = "lowercase")]
+    #[serde(rename_all = "lowercase")]
+    pub enum ArticleStatus {
+        /// Черновик - виден только автору
+        Draft,
+        /// Будет опубликована в `publish_at`
+        Scheduled,
+        /// Опубликована
+        #[default]
+        Published,
+    }
+
+    /// Проверка времени публикации: запланированной записи нужно `publish_at` в будущем,
+    /// а для остальных статусов его передавать не нужно
+    pub fn validate_schedule(status: ArticleStatus, publish_at: Option<DateTime<Utc>>) -> Result<(), ValidationErrors> {
+        let message = match (status, publish_at) {
+            (ArticleStatus::Scheduled, None) => "Required for scheduled article",
+            (ArticleStatus::Scheduled, Some(o)) if o <= Utc::now() => "Must be in the future",
+            (ArticleStatus::Draft | ArticleStatus::Published, Some(_)) => "Allowed only for scheduled article",
+            _ => return Ok(()),
+        };
+
+        let mut errors = ValidationErrors::new();
+        errors.add("publish_at", error("schedule", message));
+
+        Err(errors)
+    }
+
+    /// Описание должно содержать текст и после отрисовки: Markdown только из HTML тегов или картинок
+    /// дает пустое превью
+    pub fn validate_rendered_description(description: &RenderedMarkdown) -> Result<(), ValidationErrors> {
+        if !description.text.trim().is_empty() {
+            return Ok(());
+        }
+
+        let mut errors = ValidationErrors::new();
+        errors.add("description", error("blank", "Must contain text"));
+
+        Err(errors)
+    }
+
+    /// Запись в лентах и поиске, без текста. Полностью запись отдается в [`FullArticle`]
     #[derive(Clone, Eq, Hash, PartialEq, Debug, Serialize, Deserialize, sqlx::FromRow)]
     pub struct Article {
         #[sqlx(rename = "article_id")]
         pub id: i32,
         #[sqlx(flatten)]
         pub author: User,
-        pub image: Vec<u8>,
+        pub image: ImageUrl,
         pub title: String,
+        /// Превью: `summary`, если автор его задал, иначе начало текста, обрезанное по границе слова
         pub crop_description: String,
-        pub full_description: String,
+        /// Краткое описание от автора
+        pub summary: Option<String>,
+        /// Время чтения в минутах
+        pub reading_time: i32,
         pub publish_date: DateTime<Utc>,
+        pub edited_date: Option<DateTime<Utc>>,
+        pub status: ArticleStatus,
+        pub publish_at: Option<DateTime<Utc>>,
+        pub tags: Vec<String>,
         #[sqlx(default)]
         pub likes: i64,
         #[sqlx(default)]
         pub dislikes: i64,
+        #[sqlx(default)]
+        pub comments: i64,
+        /// Реакция текущего пользователя на запись
+        #[sqlx(default)]
+        pub reaction: Option<String>,
+        /// Галерея записи по порядку. `image` - обложка для карточки в ленте
+        pub gallery: Vec<ArticleMedia>,
     }
 
+    /// Запись вместе с текстом во всех формах
     #[derive(Clone, Eq, Hash, PartialEq, Debug, Serialize, Deserialize, sqlx::FromRow)]
-    pub struct CropArticle {
-        pub author_id: i32,
-        pub image: Vec<u8>,
+    pub struct FullArticle {
+        #[sqlx(flatten)]
+        #[serde(flatten)]
+        pub article: Article,
+        /// Текст без разметки
+        pub full_description: String,
+        /// Исходный текст в Markdown, для редактирования
+        pub description_markdown: String,
+        /// Отрисованный и очищенный HTML
+        pub description_html: String,
+    }
+
+    /// Картинка из галереи записи. В выборках записей приходит как тип `article_media_item`
+    #[derive(Clone, Eq, Hash, PartialEq, Debug, Serialize, Deserialize, sqlx::FromRow, sqlx::Type)]
+    #[sqlx(type_name = "article_media_item")]
+    pub struct ArticleMedia {
+        pub id: i32,
+        pub image: ImageUrl,
+        pub position: i32,
+        pub caption: Option<String>,
+        pub alt: Option<String>,
+    }
+
+    /// Запись из результата полнотекстового поиска.
+    ///
+    /// `title_highlight` и `snippet` содержат найденные слова, выделенные тегом `<b>`
+    #[derive(Clone, PartialEq, Debug, Serialize, Deserialize, sqlx::FromRow)]
+    pub struct FoundArticle {
+        #[sqlx(flatten)]
+        #[serde(flatten)]
+        pub article: Article,
+        pub rank: f32,
+        pub title_highlight: String,
+        pub snippet: String,
+    }
+
+    /// Запись из популярных: `score` - оценка по реакциям и комментариям, которая уменьшается с возрастом записи
+    #[derive(Clone, PartialEq, Debug, Serialize, Deserialize, sqlx::FromRow)]
+    pub struct TrendingArticle {
+        #[sqlx(flatten)]
+        #[serde(flatten)]
+        pub article: Article,
+        pub score: f64,
+    }
+
+    /// Параметры полнотекстового поиска записей
+    #[derive(Clone, Eq, Hash, PartialEq, Debug)]
+    pub struct ArticleSearch {
+        pub query: String,
+        pub author_id: Option<i32>,
+        pub from: Option<DateTime<Utc>>,
+        pub to: Option<DateTime<Utc>>,
+        pub limit: i64,
+    }
+
+    #[derive(Clone, Eq, Hash, PartialEq, Debug, Serialize, Deserialize, Validate)]
+    pub struct InsertArticle {
+        /// Картинка в base64. В multipart/form-data вместо нее передается файл `image`
+        #[serde(default)]
+        #[validate(length(min = 1), custom = "validate_base64")]
+        pub image: Option<String>,
+        /// ID картинки из завершенной докачиваемой загрузки (`finalize-upload`), передается вместо `image`
+        #[serde(default, deserialize_with = "deserialize_optional_form_number")]
+        #[validate(range(min = 1))]
+        pub media_id: Option<i32>,
+        #[validate(length(min = 1, max = 64), custom = "validate_not_blank")]
         pub title: String,
+        #[validate(length(min = 1, max = "MAX_DESCRIPTION_LENGTH"), custom = "validate_not_blank")]
         pub description: String,
+        /// Краткое описание для лент. Если не передано, превью считается из текста
+        #[serde(default)]
+        #[validate(length(min = 1, max = "MAX_SUMMARY_LENGTH"), custom = "validate_not_blank")]
+        pub summary: Option<String>,
+        #[serde(default)]
+        pub status: ArticleStatus,
+        #[serde(default)]
+        pub publish_at: Option<DateTime<Utc>>,
+        #[serde(default)]
+        #[validate(custom = "validate_tags")]
+        pub tags: Vec<String>,
+    }
+
+    impl PgHasArrayType for ArticleMedia {
+        fn array_type_info() -> PgTypeInfo {
+            PgTypeInfo::with_name("_article_media_item")
+        }
+    }
+
+    /// Добавление картинки в конец галереи записи. Картинка передается одним из способов:
+    /// файлом `image` в multipart/form-data, в base64 в `image` или через `media_id`
+    #[derive(Clone, Eq, Hash, PartialEq, Debug, Serialize, Deserialize, Validate)]
+    pub struct InsertArticleMedia {
+        #[serde(deserialize_with = "deserialize_form_number")]
+        #[validate(range(min = 1))]
+        pub article_id: i32,
+        #[serde(default)]
+        #[validate(length(min = 1), custom = "validate_base64")]
+        pub image: Option<String>,
+        #[serde(default, deserialize_with = "deserialize_optional_form_number")]
+        #[validate(range(min = 1))]
+        pub media_id: Option<i32>,
+        #[serde(default)]
+        #[validate(length(max = 512))]
+        pub caption: Option<String>,
+        #[serde(default)]
+        #[validate(length(max = 256))]
+        pub alt: Option<String>,
+    }
+
+    /// Новый порядок галереи: `items` - ID всех картинок галереи в нужном порядке
+    #[derive(Clone, Eq, Hash, PartialEq, Debug, Serialize, Deserialize, Validate)]
+    pub struct ReorderArticleMedia {
+        #[validate(range(min = 1))]
+        pub article_id: i32,
+        #[validate(length(min = 1, max = "MAX_ARTICLE_MEDIA"))]
+        pub items: Vec<i32>,
     }
 
     #[derive(Clone, Eq, Hash, PartialEq, Debug, Serialize, Deserialize)]
-    pub struct InsertArticle {
-        pub author_id: i32,
-        pub image: String,
+    pub struct RemoveArticleMedia {
+        pub article_id: i32,
+        pub item_id: i32,
+    }
+
+    /// Публикация черновика сейчас или в `publish_at`
+    #[derive(Clone, Eq, Hash, PartialEq, Debug, Serialize, Deserialize)]
+    pub struct PublishArticle {
+        pub article_id: i32,
+        #[serde(default)]
+        pub publish_at: Option<DateTime<Utc>>,
+    }
+
+    /// Изменение записи. Поля, которые не переданы, остаются без изменений.
+    /// Переданный список `tags` полностью заменяет теги записи, пустой `summary` удаляет краткое описание
+    #[derive(Clone, Eq, Hash, PartialEq, Debug, Serialize, Deserialize, Validate)]
+    pub struct UpdateArticle {
+        #[validate(range(min = 1))]
+        pub article_id: i32,
+        #[validate(length(min = 1), custom = "validate_base64")]
+        pub image: Option<String>,
+        #[validate(length(min = 1, max = 64), custom = "validate_not_blank")]
+        pub title: Option<String>,
+        #[validate(length(min = 1, max = "MAX_DESCRIPTION_LENGTH"), custom = "validate_not_blank")]
+        pub description: Option<String>,
+        #[validate(length(max = "MAX_SUMMARY_LENGTH"))]
+        pub summary: Option<String>,
+        #[validate(custom = "validate_tags")]
+        pub tags: Option<Vec<String>>,
+    }
+
+    /// Предыдущая версия записи
+    #[derive(Clone, Eq, Hash, PartialEq, Debug, Serialize, Deserialize, sqlx::FromRow)]
+    pub struct ArticleRevision {
+        pub id: i32,
+        pub article_id: i32,
+        pub editor_id: Option<i32>,
+        pub image: ImageUrl,
         pub title: String,
         pub description: String,
+        pub summary: Option<String>,
+        pub date: DateTime<Utc>,
+    }
+
+    #[derive(Clone, Eq, Hash, PartialEq, Debug, Serialize, Deserialize)]
+    pub struct RestoreArticleRevision {
+        pub article_id: i32,
+        pub revision_id: i32,
     }
 
     #[derive(Clone, Eq, Hash, PartialEq, Debug, Serialize, Deserialize, sqlx::FromRow)]
@@ -45,17 +280,20 @@ pub(crate) mod article {
         pub publish_date: DateTime<Utc>,
     }
 
-    #[derive(Clone, Eq, Hash, PartialEq, Debug, Serialize, Deserialize)]
+    #[derive(Clone, Eq, Hash, PartialEq, Debug, Serialize, Deserialize, Validate)]
     pub struct InsertComment {
-        pub user_id: i32,
+        #[validate(range(min = 1))]
         pub article_id: i32,
+        #[validate(length(min = 1, max = 1024), custom = "validate_not_blank")]
         pub message: String,
     }
 
-    #[derive(Clone, Eq, Hash, PartialEq, Debug, Serialize, Deserialize)]
+    /// Название реакции дополнительно проверяется по таблице `reactions` в обработчике
+    #[derive(Clone, Eq, Hash, PartialEq, Debug, Serialize, Deserialize, Validate)]
     pub struct InsertReaction {
-        pub user_id: i32,
+        #[validate(range(min = 1))]
         pub article_id: i32,
+        #[validate(length(min = 1, max = 16))]
         pub reaction: String,
     }
 }
\ No newline at end of file
@@ -0,0 +1,92 @@
+pub(crate) mod image {
+    use std::fmt;
+    use serde::{Serialize, Serializer, Deserialize, Deserializer};
+    use serde::de::Error;
+    use sha2::{Digest, Sha256};
+    use sqlx::Postgres;
+    use sqlx::error::BoxDynError;
+    use sqlx::postgres::{PgTypeInfo, PgValueRef};
+
+    /// Путь, по которому отдаются картинки (`GET /images/{id}`)
+    pub const IMAGES_PATH: &str = "/images/";
+
+    /// Картинка из таблицы `images`. Если `data` - `None`, то содержимое лежит в хранилище файлов под ключом `hash`
+    #[derive(Clone, Eq, Hash, PartialEq, Debug, sqlx::FromRow)]
+    pub struct Image {
+        pub id: i32,
+        pub hash: String,
+        pub content_type: String,
+        pub data: Option<Vec<u8>>,
+    }
+
+    /// Картинка, доступная пользователю. `public` - картинка аватара или опубликованной записи, ее можно кешировать
+    /// где угодно. Остальные картинки (черновиков, запланированных записей, загрузок) видит только их владелец
+    #[derive(Clone, Eq, Hash, PartialEq, Debug, sqlx::FromRow)]
+    pub struct VisibleImage {
+        #[sqlx(flatten)]
+        pub image: Image,
+        pub public: bool,
+    }
+
+    /// Картинка, сохраненная в хранилище файлов, которую нужно записать в `images`
+    #[derive(Clone, Eq, Hash, PartialEq, Debug, sqlx::FromRow)]
+    pub struct StoredImage {
+        pub hash: String,
+        pub content_type: String,
+    }
+
+    /// Ссылка на картинку. В базе данных хранится ID из `images`, а в JSON - путь `/images/{id}`
+    #[derive(Clone, Copy, Eq, Hash, PartialEq, Debug)]
+    pub struct ImageUrl(pub i32);
+
+    impl sqlx::Type<Postgres> for ImageUrl {
+        fn type_info() -> PgTypeInfo {
+            <i32 as sqlx::Type<Postgres>>::type_info()
+        }
+    }
+
+    impl<'r> sqlx::Decode<'r, Postgres> for ImageUrl {
+        fn decode(value: PgValueRef<'r>) -> Result<Self, BoxDynError> {
+            Ok(ImageUrl(<i32 as sqlx::Decode<Postgres>>::decode(value)?))
+        }
+    }
+
+    impl fmt::Display for ImageUrl {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{}{}", IMAGES_PATH, self.0)
+        }
+    }
+
+    impl Serialize for ImageUrl {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_str(self)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for ImageUrl {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let url = String::deserialize(deserializer)?;
+
+            url.strip_prefix(IMAGES_PATH)
+                .and_then(|o| o.parse::<i32>().ok())
+                .map(ImageUrl)
+                .ok_or_else(|| D::Error::custom(format!("invalid image url: {}", url)))
+        }
+    }
+
+    /// sha256 содержимого картинки в hex. По нему картинки не дублируются и он же отдается как ETag
+    pub fn image_hash(data: &[u8]) -> String {
+        format!("{:x}", Sha256::digest(data))
+    }
+
+    /// Определение `Content-Type` по первым байтам картинки
+    pub fn sniff_content_type(data: &[u8]) -> &'static str {
+        match data {
+            [0xFF, 0xD8, 0xFF, ..] => "image/jpeg",
+            [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, ..] => "image/png",
+            [b'G', b'I', b'F', b'8', ..] => "image/gif",
+            [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => "image/webp",
+            _ => "application/octet-stream",
+        }
+    }
+}
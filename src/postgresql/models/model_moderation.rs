@@ -0,0 +1,15 @@
+pub(crate) mod moderation {
+    use chrono::{DateTime, Utc};
+    use serde::{Serialize, Deserialize};
+
+    #[derive(Clone, Eq, Hash, PartialEq, Debug, Serialize, Deserialize, sqlx::FromRow)]
+    pub struct ModerationRecord {
+        pub id: i32,
+        pub actor_id: Option<i32>,
+        pub action: String,
+        pub target_type: String,
+        pub target_id: i32,
+        pub details: Option<String>,
+        pub date: DateTime<Utc>,
+    }
+}
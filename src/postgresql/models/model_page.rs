@@ -0,0 +1,73 @@
+pub(crate) mod page {
+    use base64::Engine;
+    use base64::engine::general_purpose;
+    use chrono::{DateTime, Utc};
+    use serde::{Serialize, Deserialize};
+    use serde::de::DeserializeOwned;
+
+    /// Размер страницы по умолчанию
+    pub const DEFAULT_PAGE_SIZE: i64 = 20;
+
+    /// Максимальный размер страницы
+    pub const MAX_PAGE_SIZE: i64 = 100;
+
+    /// Страница списка. `next_cursor` передается в следующий запрос, [`None`] - страниц больше нет
+    #[derive(Clone, Eq, Hash, PartialEq, Debug, Serialize, Deserialize)]
+    pub struct Page<T> {
+        pub items: Vec<T>,
+        pub next_cursor: Option<String>,
+    }
+
+    impl<T> Page<T> {
+        /// Создание страницы из строк, выбранных с `LIMIT limit + 1`.
+        /// Лишняя строка отбрасывается и означает, что есть следующая страница.
+        pub fn from_rows<C, F>(mut rows: Vec<T>, limit: i64, cursor: F) -> Self
+        where C: Serialize, F: Fn(&T) -> C {
+            let has_next = rows.len() as i64 > limit;
+            rows.truncate(limit as usize);
+
+            let next_cursor = match has_next {
+                true => rows.last().map(|o| encode_cursor(&cursor(o))),
+                false => None,
+            };
+
+            Page { items: rows, next_cursor }
+        }
+    }
+
+    /// Запрос страницы: курсор предыдущей страницы и размер
+    #[derive(Clone, Eq, Hash, PartialEq, Debug)]
+    pub struct PageRequest<C> {
+        pub cursor: Option<C>,
+        pub limit: i64,
+    }
+
+    /// Курсор для списков, упорядоченных по `publish_date, id`
+    #[derive(Clone, Copy, Eq, Hash, PartialEq, Debug, Serialize, Deserialize)]
+    pub struct DateCursor {
+        pub publish_date: DateTime<Utc>,
+        pub id: i32,
+    }
+
+    /// Курсор для списков, упорядоченных по количеству подписчиков и `id`
+    #[derive(Clone, Copy, Eq, Hash, PartialEq, Debug, Serialize, Deserialize)]
+    pub struct FollowersCursor {
+        pub followers: i64,
+        pub id: i32,
+    }
+
+    /// Непрозрачный курсор для клиента - JSON в base64 (url-safe, без паддинга)
+    pub fn encode_cursor<C>(cursor: &C) -> String
+    where C: Serialize {
+        general_purpose::URL_SAFE_NO_PAD.encode(serde_json::to_vec(cursor).unwrap_or_default())
+    }
+
+    /// ### Возвращает:
+    /// [`None`], если курсор поврежден или от другого списка
+    pub fn decode_cursor<C>(cursor: &str) -> Option<C>
+    where C: DeserializeOwned {
+        let bytes = general_purpose::URL_SAFE_NO_PAD.decode(cursor).ok()?;
+
+        serde_json::from_slice::<C>(&bytes).ok()
+    }
+}
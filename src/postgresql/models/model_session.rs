@@ -0,0 +1,62 @@
+pub(crate) mod session {
+    use chrono::{DateTime, Utc};
+    use serde::{Serialize, Deserialize};
+    use crate::postgresql::models::model_user::user::Role;
+
+    #[derive(Clone, Eq, Hash, PartialEq, Debug, Serialize, Deserialize, sqlx::FromRow)]
+    pub struct Session {
+        #[sqlx(rename = "session_id")]
+        pub id: i32,
+        pub user_id: i32,
+        pub device: Option<String>,
+        pub created_at: DateTime<Utc>,
+        pub last_used_at: DateTime<Utc>,
+        pub expires_at: DateTime<Utc>,
+        #[sqlx(default)]
+        pub current: bool,
+    }
+
+    /// Действующая сессия вместе с ролью ее владельца
+    #[derive(Clone, Copy, Eq, Hash, PartialEq, Debug, sqlx::FromRow)]
+    pub struct SessionUser {
+        pub session_id: i32,
+        pub user_id: i32,
+        pub role: Role,
+    }
+
+    /// Хеши выданных токенов, которые сохраняются в базе данных
+    #[derive(Clone, Eq, Hash, PartialEq, Debug)]
+    pub struct SessionTokens {
+        pub access_hash: String,
+        pub access_expires_at: DateTime<Utc>,
+        pub refresh_hash: String,
+        pub refresh_expires_at: DateTime<Utc>,
+    }
+
+    /// Результат обновления токенов по refresh токену
+    #[derive(Clone, Copy, Eq, Hash, PartialEq, Debug)]
+    pub enum RefreshOutcome {
+        /// Токены выданы заново, старый refresh токен погашен
+        Rotated { session_id: i32, user_id: i32 },
+        /// Повторное использование уже погашенного refresh токена - сессия отозвана
+        Reused { session_id: i32, user_id: i32 },
+        /// Токен не найден, истек или сессия уже отозвана
+        Invalid,
+    }
+
+    #[derive(Clone, Eq, Hash, PartialEq, Debug, Serialize, Deserialize)]
+    pub struct RefreshSession {
+        pub refresh_token: String,
+    }
+
+    #[derive(Clone, Eq, Hash, PartialEq, Debug, Serialize, Deserialize)]
+    pub struct RevokeSession {
+        pub session_id: i32,
+    }
+
+    #[derive(Clone, Eq, Hash, PartialEq, Debug, Serialize, Deserialize)]
+    pub struct RevokeAllSessions {
+        #[serde(default)]
+        pub keep_current: bool,
+    }
+}
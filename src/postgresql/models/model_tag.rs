@@ -0,0 +1,67 @@
+pub(crate) mod tag {
+    use once_cell::sync::Lazy;
+    use regex::Regex;
+    use serde::{Serialize, Deserialize};
+    use validator::ValidationError;
+    use crate::postgresql::models::model_validation::validation::error;
+
+    /// Максимальная длина тега (`tags.name`)
+    pub const MAX_TAG_LENGTH: usize = 32;
+
+    /// Максимальное количество тегов у записи
+    pub const MAX_ARTICLE_TAGS: usize = 10;
+
+    /// Тег: буквы, цифры, `_`, `-`, `+`, `.` и `#` (например `c++`, `c#`, `.net`)
+    static TAG_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[\p{L}\p{N}_+#.\-]+$").unwrap());
+
+    /// Тег и количество опубликованных записей с ним
+    #[derive(Clone, Eq, Hash, PartialEq, Debug, Serialize, Deserialize, sqlx::FromRow)]
+    pub struct Tag {
+        pub id: i32,
+        pub name: String,
+        pub articles: i64,
+    }
+
+    #[derive(Clone, Eq, Hash, PartialEq, Debug, Serialize, Deserialize)]
+    pub struct FollowTag {
+        pub tag: String,
+    }
+
+    /// Приведение тега к виду, в котором он хранится: без `#` в начале, без пробелов по краям, в нижнем регистре
+    pub fn normalize_tag(tag: &str) -> String {
+        tag.trim().trim_start_matches('#').trim().to_lowercase()
+    }
+
+    /// Приведение списка тегов к виду, в котором он хранится, без повторов
+    pub fn normalize_tags(tags: &[String]) -> Vec<String> {
+        let mut normalized: Vec<String> = Vec::with_capacity(tags.len());
+
+        for tag in tags.iter().map(|o| normalize_tag(o)) {
+            if !normalized.contains(&tag) {
+                normalized.push(tag);
+            }
+        }
+
+        normalized
+    }
+
+    /// Проверка тегов записи: не больше [`MAX_ARTICLE_TAGS`], каждый тег после [`normalize_tag`]
+    /// не пустой, не длиннее [`MAX_TAG_LENGTH`] и без пробелов
+    pub fn validate_tags(tags: &[String]) -> Result<(), ValidationError> {
+        if tags.len() > MAX_ARTICLE_TAGS {
+            return Err(error("tags_count", "Too many tags"));
+        }
+
+        for tag in tags.iter().map(|o| normalize_tag(o)) {
+            if tag.is_empty() || tag.chars().count() > MAX_TAG_LENGTH {
+                return Err(error("tag_length", "Tag must be from 1 to 32 characters"));
+            }
+
+            if !TAG_REGEX.is_match(&tag) {
+                return Err(error("tag_format", "Tag may contain only letters, digits and _ + # . -"));
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,49 @@
+pub(crate) mod upload {
+    use chrono::{DateTime, Utc};
+    use serde::{Serialize, Deserialize};
+    use validator::Validate;
+
+    /// Максимальный итоговый размер загрузки - 64мб
+    pub const MAX_UPLOAD_SIZE: i64 = 67_108_864;
+
+    /// Максимальный размер одной части загрузки - 8мб
+    pub const MAX_UPLOAD_CHUNK_SIZE: usize = 8_388_608;
+
+    /// Сессия докачиваемой загрузки. `media_id` появляется после завершения загрузки
+    /// и передается в `insert_article` вместо картинки
+    #[derive(Clone, Eq, Hash, PartialEq, Debug, Serialize, Deserialize, sqlx::FromRow)]
+    pub struct Upload {
+        pub id: i32,
+        pub size: i64,
+        pub received: i64,
+        pub media_id: Option<i32>,
+        pub created_at: DateTime<Utc>,
+        pub updated_at: DateTime<Utc>,
+    }
+
+    #[derive(Clone, Eq, Hash, PartialEq, Debug, Serialize, Deserialize, Validate)]
+    pub struct CreateUpload {
+        #[validate(range(min = 1, max = "MAX_UPLOAD_SIZE"))]
+        pub size: i64,
+    }
+
+    #[derive(Clone, Eq, Hash, PartialEq, Debug, Serialize, Deserialize)]
+    pub struct FinalizeUpload {
+        pub upload_id: i32,
+    }
+
+    /// Результат добавления части к загрузке
+    #[derive(Clone, Eq, PartialEq, Debug)]
+    pub enum AppendChunk {
+        /// Часть принята, загрузка в новом состоянии
+        Accepted(Upload),
+        /// Загрузки нет или она принадлежит другому пользователю
+        NotFound,
+        /// Загрузка уже завершена
+        Completed,
+        /// Смещение части не совпадает с количеством уже полученных байт
+        OffsetMismatch(i64),
+        /// Часть выходит за объявленный размер загрузки
+        Overflow,
+    }
+}
@@ -1,6 +1,35 @@
 pub(crate) mod user {
     use chrono::{DateTime, Utc};
     use serde::{Serialize, Deserialize};
+    use validator::Validate;
+    use crate::postgresql::models::model_image::image::ImageUrl;
+    use crate::postgresql::models::model_validation::validation::{
+        validate_not_blank,
+        LOGIN_REGEX,
+    };
+
+    /// Роль пользователя, хранится в `users.role`
+    #[derive(Clone, Copy, Eq, Hash, PartialEq, Debug, Serialize, Deserialize, sqlx::Type)]
+    #[sqlx(type_name = "varchar", rename_all = "lowercase")]
+    #[serde(rename_all = "lowercase")]
+    pub enum Role {
+        User,
+        Moderator,
+        Admin,
+    }
+
+    impl Role {
+        /// Может ли роль модерировать чужой контент
+        pub fn can_moderate(&self) -> bool {
+            matches!(self, Role::Moderator | Role::Admin)
+        }
+    }
+
+    #[derive(Clone, Eq, Hash, PartialEq, Debug, Serialize, Deserialize)]
+    pub struct SetUserRole {
+        pub user_id: i32,
+        pub role: Role,
+    }
 
     #[derive(Clone, Eq, Hash, PartialEq, Debug, Serialize, Deserialize, sqlx::FromRow)]
     pub struct FullUser {
@@ -11,20 +40,35 @@ pub(crate) mod user {
         pub about: Option<String>,
         pub password: String,
         pub login: String,
-        pub crop_avatar: Option<Vec<u8>>,
-        pub full_avatar: Option<Vec<u8>>,
+        pub crop_avatar: Option<ImageUrl>,
+        pub full_avatar: Option<ImageUrl>,
         pub date_registration: DateTime<Utc>,
     }
 
-    #[derive(Clone, Eq, Hash, PartialEq, Debug, Serialize, Deserialize, sqlx::FromRow)]
+    #[derive(Clone, Eq, Hash, PartialEq, Debug, Serialize, Deserialize, sqlx::FromRow, Validate)]
     pub struct RegisterUser {
+        #[validate(length(min = 1, max = 64), custom = "validate_not_blank")]
         pub first_name: String,
+        #[validate(length(min = 1, max = 64), custom = "validate_not_blank")]
         pub last_name: String,
+        #[validate(length(max = 256))]
         pub about: Option<String>,
+        #[validate(length(min = 6, max = 128))]
         pub password: String,
+        #[validate(length(min = 3, max = 64), regex = "LOGIN_REGEX")]
         pub login: String,
     }
 
+    #[derive(Clone, Eq, Hash, PartialEq, Debug, Serialize, Deserialize, Validate)]
+    pub struct LoginUser {
+        #[validate(length(min = 1, max = 64))]
+        pub login: String,
+        #[validate(length(min = 1, max = 128))]
+        pub password: String,
+        #[serde(default)]
+        pub device: Option<String>,
+    }
+
     #[derive(Clone, Eq, Hash, PartialEq, Debug, Serialize, Deserialize, sqlx::FromRow)]
     pub struct User {
         #[sqlx(rename = "user_id")]
@@ -32,11 +76,25 @@ pub(crate) mod user {
         pub first_name: String,
         pub last_name: String,
         pub about: Option<String>,
-        pub crop_avatar: Option<Vec<u8>>,
-        pub full_avatar: Option<Vec<u8>>,
+        pub crop_avatar: Option<ImageUrl>,
+        pub full_avatar: Option<ImageUrl>,
         pub date_registration: DateTime<Utc>,
     }
 
+    impl From<FullUser> for User {
+        fn from(user: FullUser) -> Self {
+            User {
+                id: user.id,
+                first_name: user.first_name,
+                last_name: user.last_name,
+                about: user.about,
+                crop_avatar: user.crop_avatar,
+                full_avatar: user.full_avatar,
+                date_registration: user.date_registration,
+            }
+        }
+    }
+
     #[derive(Clone, Eq, Hash, PartialEq, Debug, Serialize, Deserialize, sqlx::FromRow)]
     pub struct PopularUser {
         #[sqlx(rename = "user_id")]
@@ -44,8 +102,17 @@ pub(crate) mod user {
         pub first_name: String,
         pub last_name: String,
         pub about: Option<String>,
-        pub crop_avatar: Option<Vec<u8>>,
+        pub crop_avatar: Option<ImageUrl>,
         pub date_registration: DateTime<Utc>,
         pub followers: i64,
     }
-}
\ No newline at end of file
+
+    /// Пользователь из результата поиска с оценкой релевантности от 0 до 1
+    #[derive(Clone, PartialEq, Debug, Serialize, Deserialize, sqlx::FromRow)]
+    pub struct FoundUser {
+        #[sqlx(flatten)]
+        #[serde(flatten)]
+        pub user: PopularUser,
+        pub relevance: f32,
+    }
+}
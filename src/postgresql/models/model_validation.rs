@@ -0,0 +1,65 @@
+pub(crate) mod validation {
+    use std::borrow::Cow;
+    use std::str::FromStr;
+    use base64::Engine;
+    use base64::engine::general_purpose;
+    use once_cell::sync::Lazy;
+    use regex::Regex;
+    use serde::{Deserialize, Deserializer};
+    use serde::de::Error;
+    use validator::ValidationError;
+
+    /// Логин: латинские буквы, цифры, `_`, `.` и `-`
+    pub static LOGIN_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[A-Za-z0-9_.\-]+$").unwrap());
+
+    /// Строка не должна состоять только из пробелов
+    pub fn validate_not_blank(value: &str) -> Result<(), ValidationError> {
+        match value.trim().is_empty() {
+            true => Err(error("blank", "Must not be blank")),
+            false => Ok(()),
+        }
+    }
+
+    /// Строка должна быть корректным base64
+    pub fn validate_base64(value: &str) -> Result<(), ValidationError> {
+        match general_purpose::STANDARD.decode(value) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(error("base64", "Must be a valid base64 string")),
+        }
+    }
+
+    pub fn error(code: &'static str, message: &'static str) -> ValidationError {
+        let mut error = ValidationError::new(code);
+        error.message = Some(Cow::Borrowed(message));
+        error
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum NumberOrString<T> {
+        Number(T),
+        String(String),
+    }
+
+    /// Число из JSON или из строки: в multipart/form-data все поля приходят строками
+    pub fn deserialize_form_number<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+    where D: Deserializer<'de>, T: Deserialize<'de> + FromStr {
+        match NumberOrString::<T>::deserialize(deserializer)? {
+            NumberOrString::Number(o) => Ok(o),
+            NumberOrString::String(o) => o.trim().parse::<T>()
+                .map_err(|_| D::Error::custom(format!("invalid number: {}", o))),
+        }
+    }
+
+    /// Необязательное число из JSON или из строки, см. [`deserialize_form_number`]
+    pub fn deserialize_optional_form_number<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+    where D: Deserializer<'de>, T: Deserialize<'de> + FromStr {
+        match Option::<NumberOrString<T>>::deserialize(deserializer)? {
+            None => Ok(None),
+            Some(NumberOrString::Number(o)) => Ok(Some(o)),
+            Some(NumberOrString::String(o)) => o.trim().parse::<T>()
+                .map(Some)
+                .map_err(|_| D::Error::custom(format!("invalid number: {}", o))),
+        }
+    }
+}
@@ -0,0 +1,105 @@
+pub mod transliteration {
+    /// Сочетания латинских букв, которые дают одну кириллическую. Проверяются раньше одиночных букв.
+    const LATIN_GROUPS: &[(&str, char)] = &[
+        ("shch", 'щ'),
+        ("zh", 'ж'),
+        ("kh", 'х'),
+        ("ts", 'ц'),
+        ("ch", 'ч'),
+        ("sh", 'ш'),
+        ("yu", 'ю'),
+        ("ya", 'я'),
+        ("yo", 'ё'),
+    ];
+
+    fn cyrillic_to_latin(c: char) -> Option<&'static str> {
+        let latin = match c {
+            'а' => "a", 'б' => "b", 'в' => "v", 'г' => "g", 'д' => "d",
+            'е' => "e", 'ё' => "e", 'ж' => "zh", 'з' => "z", 'и' => "i",
+            'й' => "y", 'к' => "k", 'л' => "l", 'м' => "m", 'н' => "n",
+            'о' => "o", 'п' => "p", 'р' => "r", 'с' => "s", 'т' => "t",
+            'у' => "u", 'ф' => "f", 'х' => "kh", 'ц' => "ts", 'ч' => "ch",
+            'ш' => "sh", 'щ' => "shch", 'ъ' => "", 'ы' => "y", 'ь' => "",
+            'э' => "e", 'ю' => "yu", 'я' => "ya",
+            _ => return None,
+        };
+
+        Some(latin)
+    }
+
+    fn latin_to_cyrillic(c: char, after_vowel: bool) -> Option<&'static str> {
+        let cyrillic = match c {
+            'a' => "а", 'b' => "б", 'c' => "к", 'd' => "д", 'e' => "е",
+            'f' => "ф", 'g' => "г", 'h' => "х", 'i' => "и", 'j' => "дж",
+            'k' => "к", 'l' => "л", 'm' => "м", 'n' => "н", 'o' => "о",
+            'p' => "п", 'q' => "к", 'r' => "р", 's' => "с", 't' => "т",
+            'u' => "у", 'v' => "в", 'w' => "в", 'x' => "кс", 'z' => "з",
+            'y' if after_vowel => "й",
+            'y' => "ы",
+            _ => return None,
+        };
+
+        Some(cyrillic)
+    }
+
+    fn is_vowel(c: char) -> bool {
+        matches!(c, 'a' | 'e' | 'i' | 'o' | 'u' | 'y' | 'а' | 'е' | 'ё' | 'и' | 'о' | 'у' | 'ы' | 'э' | 'ю' | 'я')
+    }
+
+    /// Транслитерация кириллицы в латиницу (`Иванов` -> `ivanov`).
+    /// Остальные символы остаются без изменений. Результат в нижнем регистре.
+    pub fn to_latin(text: &str) -> String {
+        let mut result = String::with_capacity(text.len());
+
+        for c in text.to_lowercase().chars() {
+            match cyrillic_to_latin(c) {
+                Some(o) => result.push_str(o),
+                None => result.push(c),
+            }
+        }
+
+        result
+    }
+
+    /// Транслитерация латиницы в кириллицу (`ivanov` -> `иванов`).
+    /// Остальные символы остаются без изменений. Результат в нижнем регистре.
+    pub fn to_cyrillic(text: &str) -> String {
+        let text = text.to_lowercase();
+        let mut result = String::with_capacity(text.len() * 2);
+        let mut rest = text.as_str();
+        let mut previous: Option<char> = None;
+
+        while let Some(c) = rest.chars().next() {
+            if let Some((latin, cyrillic)) = LATIN_GROUPS.iter().find(|(o, _)| rest.starts_with(o)) {
+                result.push(*cyrillic);
+                rest = &rest[latin.len()..];
+                previous = Some(*cyrillic);
+                continue;
+            }
+
+            let after_vowel = previous.is_some_and(is_vowel);
+            match latin_to_cyrillic(c, after_vowel) {
+                Some(o) => result.push_str(o),
+                None => result.push(c),
+            }
+
+            rest = &rest[c.len_utf8()..];
+            previous = Some(c);
+        }
+
+        result
+    }
+
+    /// Варианты написания слова для поиска: само слово, латиница и кириллица без повторов
+    pub fn spellings(word: &str) -> Vec<String> {
+        let mut spellings = vec![word.to_lowercase()];
+
+        for spelling in [to_latin(word), to_cyrillic(word)] {
+            if !spellings.contains(&spelling) {
+                spellings.push(spelling);
+            }
+        }
+
+        spellings
+    }
+}
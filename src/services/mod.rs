@@ -2,20 +2,34 @@ use std::str::FromStr;
 use actix_web::web::Payload;
 use actix_web::{HttpRequest, web};
 use actix_web::web::BytesMut;
+use std::any::type_name;
+use std::collections::HashMap;
+use actix_multipart::Multipart;
+use actix_web::http::header::CONTENT_TYPE;
 use serde::Serialize;
-use serde_json::{Value, json};
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value, json};
 use futures::StreamExt;
 use qstring::QString;
+use validator::Validate;
+use crate::error::api_error::{ApiError, ErrorCode};
+use crate::logger::log::{Level, log};
+use crate::postgresql::models::model_image::image::{sniff_content_type, StoredImage};
+use crate::storage::storage_media::media::{store_image, MediaStorage};
+use crate::storage::storage_staging::staging::{StagedFile, Staging};
+use base64::Engine;
+use base64::engine::general_purpose;
+use crate::postgresql::models::model_page::page::{
+    decode_cursor,
+    PageRequest,
+    DEFAULT_PAGE_SIZE,
+    MAX_PAGE_SIZE,
+};
 
 const MAX_SIZE_BUFFER_REQUEST: usize = 16_777_216; // максимальный размер буфера - 256кб
-
-pub(crate) fn json_error<T>(message: T) -> Value
-where T: Serialize {
-    json!({
-        "status": "error",
-        "message": message
-    })
-}
+const MAX_SIZE_FORM_FILE: usize = 10_485_760; // максимальный размер файла в multipart/form-data - 10мб
+const MAX_SIZE_FORM_FIELD: usize = 1_048_576; // максимальный размер текстового поля в multipart/form-data - 1мб
+const MAX_FORM_PARTS: usize = 32;
 
 pub(crate) fn json_success<T>(message: T) -> Value
 where T: Serialize {
@@ -25,12 +39,20 @@ where T: Serialize {
     })
 }
 
-pub(crate) async fn read_body_bytes(payload: &mut Payload) -> Result<BytesMut, ()> {
+pub(crate) async fn read_body_bytes(payload: &mut Payload) -> Result<BytesMut, ApiError> {
+    read_body_bytes_limited(payload, MAX_SIZE_BUFFER_REQUEST).await
+}
+
+/// Чтение тела запроса целиком, но не больше `limit` байт
+///
+/// ### Возвращает:
+/// Если тело больше `limit`, то [`ApiError`] 413
+pub(crate) async fn read_body_bytes_limited(payload: &mut Payload, limit: usize) -> Result<BytesMut, ApiError> {
     let mut body = web::BytesMut::new();
     while let Some(chunk) = payload.next().await {
-        let chunk = chunk.unwrap();
-        if (body.len() + chunk.len()) > MAX_SIZE_BUFFER_REQUEST {
-            return Err(());
+        let chunk = chunk.map_err(|e| ApiError::bad_request(e.to_string()))?;
+        if (body.len() + chunk.len()) > limit {
+            return Err(ApiError::payload_too_large());
         }
         body.extend_from_slice(&chunk);
     }
@@ -38,20 +60,276 @@ pub(crate) async fn read_body_bytes(payload: &mut Payload) -> Result<BytesMut, (
     Ok(body)
 }
 
-pub(crate) async fn get_query_param<T: FromStr>(req: &HttpRequest, query_key: &str) -> Result<T, String> {
-    let query_str = req.query_string();
-    let qs = QString::from(query_str);
+/// Разбор тела запроса из JSON
+/// ### Принимает:
+/// Тело запроса, источник для лога (например `[POST][insert-user]`)
+pub(crate) fn parse_json<T: DeserializeOwned>(body: &[u8], source: &str) -> Result<T, ApiError> {
+    serde_json::from_slice::<T>(body).map_err(|e| {
+        log(Level::Error, &format!("{} >>> serde_json::from_slice::<{}>", source, type_name::<T>().rsplit("::").next().unwrap_or_default()),
+            &format!("Handle: {}", e)
+        );
+
+        ApiError::bad_request("Error request!")
+    })
+}
+
+/// Чтение и разбор тела запроса из JSON
+pub(crate) async fn read_json<T: DeserializeOwned>(payload: &mut Payload, source: &str) -> Result<T, ApiError> {
+    let body = read_body_bytes(payload).await?;
+
+    parse_json::<T>(&body, source)
+}
+
+/// Чтение, разбор и валидация тела запроса из JSON
+///
+/// ### Возвращает:
+/// Если тело не прошло валидацию, то [`ApiError`] со списком ошибок по каждому полю
+pub(crate) async fn read_valid_json<T: DeserializeOwned + Validate>(payload: &mut Payload, source: &str) -> Result<T, ApiError> {
+    let value = read_json::<T>(payload, source).await?;
+    value.validate()?;
+
+    Ok(value)
+}
+
+/// Разбор и валидация уже прочитанных полей (например текстовых полей multipart/form-data)
+///
+/// ### Возвращает:
+/// Если поля не прошли валидацию, то [`ApiError`] со списком ошибок по каждому полю
+pub(crate) fn parse_valid_value<T: DeserializeOwned + Validate>(value: Value, source: &str) -> Result<T, ApiError> {
+    let value = serde_json::from_value::<T>(value).map_err(|e| {
+        log(Level::Error, &format!("{} >>> serde_json::from_value::<{}>", source, type_name::<T>().rsplit("::").next().unwrap_or_default()),
+            &format!("Handle: {}", e)
+        );
+
+        ApiError::bad_request("Error request!")
+    })?;
+    value.validate()?;
+
+    Ok(value)
+}
+
+/// Тело запроса в multipart/form-data
+pub(crate) struct FormData {
+    /// Текстовые поля как JSON объект. Поля вида `name[]` собираются в массив `name`
+    pub fields: Value,
+    /// Файлы по названию поля, записанные во временный каталог
+    pub files: HashMap<String, StagedFile>,
+}
+
+/// Пришло ли тело запроса в multipart/form-data
+pub(crate) fn is_multipart(req: &HttpRequest) -> bool {
+    req.headers().get(CONTENT_TYPE)
+        .and_then(|o| o.to_str().ok())
+        .is_some_and(|o| o.trim_start().to_ascii_lowercase().starts_with("multipart/form-data"))
+}
+
+/// Чтение тела запроса в multipart/form-data. Части читаются по мере поступления: текстовые поля в память,
+/// файлы сразу во временный каталог. Размер каждого файла ограничен 10мб, текстового поля - 1мб, всего тела - как у JSON
+///
+/// ### Принимает:
+/// Запрос (для заголовка с boundary), тело запроса, каталог временных файлов
+///
+/// ### Возвращает:
+/// Если [`Ok`], то [`FormData`]. Если часть больше лимита - [`ApiError`] 413
+pub(crate) async fn read_multipart(req: &HttpRequest, payload: Payload, staging: &Staging) -> Result<FormData, ApiError> {
+    let mut multipart = Multipart::new(req.headers(), payload);
+    let mut fields = Map::new();
+    let mut files = HashMap::new();
+    let mut parts = 0;
+    let mut total = 0;
+
+    while let Some(field) = multipart.next().await {
+        let mut field = field.map_err(|e| ApiError::bad_request(e.to_string()))?;
+
+        parts += 1;
+        if parts > MAX_FORM_PARTS {
+            return Err(ApiError::bad_request("Too many form parts!"));
+        }
+
+        let name = match field.name() {
+            Some(o) if !o.is_empty() => o.to_owned(),
+            _ => return Err(ApiError::bad_request("Form part without name!")),
+        };
+
+        let is_file = field.content_disposition()
+            .and_then(|o| o.get_filename())
+            .is_some();
+
+        if is_file {
+            let mut file = staging.create().await
+                .map_err(|e| ApiError::internal("read_multipart >>> staging.create", e))?;
+            let mut size = 0;
+
+            while let Some(chunk) = field.next().await {
+                let chunk = chunk.map_err(|e| ApiError::bad_request(e.to_string()))?;
+
+                total += chunk.len();
+                if total > MAX_SIZE_BUFFER_REQUEST {
+                    return Err(ApiError::payload_too_large());
+                }
+
+                size += chunk.len();
+                if size > MAX_SIZE_FORM_FILE {
+                    return Err(ApiError::new(ErrorCode::PayloadTooLarge, format!("Form part {} is too large!", name)));
+                }
+
+                file.write(&chunk).await
+                    .map_err(|e| ApiError::internal("read_multipart >>> file.write", e))?;
+            }
+
+            let file = file.finish().await
+                .map_err(|e| ApiError::internal("read_multipart >>> file.finish", e))?;
+
+            files.insert(name, file);
+            continue;
+        }
+
+        let mut data = BytesMut::new();
+        while let Some(chunk) = field.next().await {
+            let chunk = chunk.map_err(|e| ApiError::bad_request(e.to_string()))?;
+
+            total += chunk.len();
+            if total > MAX_SIZE_BUFFER_REQUEST {
+                return Err(ApiError::payload_too_large());
+            }
+
+            if (data.len() + chunk.len()) > MAX_SIZE_FORM_FIELD {
+                return Err(ApiError::new(ErrorCode::PayloadTooLarge, format!("Form part {} is too large!", name)));
+            }
+
+            data.extend_from_slice(&chunk);
+        }
+
+        let text = String::from_utf8(data.to_vec())
+            .map_err(|_| ApiError::bad_request(format!("Invalid UTF-8 for {}!", name)))?;
+
+        match name.strip_suffix("[]") {
+            Some(o) => match fields.entry(o).or_insert_with(|| Value::Array(Vec::new())) {
+                Value::Array(array) => array.push(Value::String(text)),
+                _ => return Err(ApiError::bad_request(format!("Duplicate field {}!", o))),
+            },
+            None => {
+                if fields.insert(name.clone(), Value::String(text)).is_some() {
+                    return Err(ApiError::bad_request(format!("Duplicate field {}!", name)));
+                }
+            },
+        }
+    }
+
+    Ok(FormData { fields: Value::Object(fields), files })
+}
+
+pub(crate) fn get_body_param<T: DeserializeOwned>(value: &Value, key: &str) -> Result<T, ApiError> {
+    match value.get(key) {
+        Some(o) => serde_json::from_value::<T>(o.clone())
+            .map_err(|_| ApiError::bad_request(format!("Invalid type for {}!", &key))),
+        None => Err(ApiError::bad_request(format!("Not found {}!", &key))),
+    }
+}
+
+pub(crate) async fn get_query_param<T: FromStr>(req: &HttpRequest, query_key: &str) -> Result<T, ApiError> {
+    // `+` в query string означает пробел (application/x-www-form-urlencoded), а QString его не декодирует
+    let query_str = req.query_string().replace('+', "%20");
+    let qs = QString::from(query_str.as_str());
 
     let result = match qs.get(query_key) {
         Some(o) => match o.parse::<T>() {
             Ok(o) => o,
-            Err(_) => return Err(format!("Invalid query type for {}!", &query_key)),
+            Err(_) => return Err(ApiError::new(ErrorCode::InvalidQuery, format!("Invalid query type for {}!", &query_key))),
         },
-        None => return Err(format!("Not found {}!", &query_key)),
+        None => return Err(ApiError::new(ErrorCode::InvalidQuery, format!("Not found {}!", &query_key))),
     };
 
     Ok(result)
 }
 
+/// Необязательный параметр из query string
+///
+/// ### Возвращает:
+/// [`None`], если параметра нет. [`ApiError`], если параметр есть, но имеет неверный тип
+pub(crate) async fn get_optional_query_param<T: FromStr>(req: &HttpRequest, query_key: &str) -> Result<Option<T>, ApiError> {
+    match get_query_param::<String>(req, query_key).await {
+        Ok(o) => match o.parse::<T>() {
+            Ok(o) => Ok(Some(o)),
+            Err(_) => Err(ApiError::new(ErrorCode::InvalidQuery, format!("Invalid query type for {}!", &query_key))),
+        },
+        Err(_) => Ok(None),
+    }
+}
+
+/// Запрос страницы из параметров `cursor` и `limit`
+///
+/// ### Возвращает:
+/// [`ApiError`], если курсор поврежден или параметры имеют неверный тип
+pub(crate) async fn get_page_request<C: DeserializeOwned>(req: &HttpRequest) -> Result<PageRequest<C>, ApiError> {
+    let cursor = match get_optional_query_param::<String>(req, "cursor").await?.filter(|o| !o.is_empty()) {
+        Some(o) => match decode_cursor::<C>(&o) {
+            Some(o) => Some(o),
+            None => return Err(ApiError::new(ErrorCode::InvalidQuery, "Invalid cursor!")),
+        },
+        None => None,
+    };
+
+    let limit = get_optional_query_param::<i64>(req, "limit").await?
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+
+    Ok(PageRequest { cursor, limit })
+}
+
+/// Декодирование поля из base64
+///
+/// ### Принимает:
+/// Значение в base64, название поля (для текста ошибки)
+///
+/// ### Возвращает:
+/// Если [`Ok`], то байты. Если base64 некорректный - [`ApiError`] 400
+pub(crate) fn decode_base64(value: &str, key: &str) -> Result<Vec<u8>, ApiError> {
+    general_purpose::STANDARD.decode(value)
+        .map_err(|_| ApiError::bad_request(format!("Invalid base64 for {}!", key)))
+}
+
+/// Декодирование картинки из base64
+///
+/// ### Принимает:
+/// Картинку в base64, название поля (для текста ошибки)
+///
+/// ### Возвращает:
+/// Если [`Ok`], то байты картинки. Если base64 некорректный - [`ApiError`] 400,
+/// если это не JPEG, PNG, GIF или WebP - [`ApiError`] с [`ErrorCode::InvalidImage`]
+pub(crate) fn decode_image(value: &str, key: &str) -> Result<Vec<u8>, ApiError> {
+    let data = decode_base64(value, key)?;
+
+    if sniff_content_type(&data) == "application/octet-stream" {
+        return Err(invalid_image(key));
+    }
+
+    Ok(data)
+}
+
+/// Декодирование картинки из base64 и сохранение ее в хранилище файлов
+///
+/// ### Принимает:
+/// Хранилище, картинку в base64, название поля (для текста ошибки), источник для лога
+///
+/// ### Возвращает:
+/// Если [`Ok`], то [`StoredImage`]. Ошибки декодирования - как у [`decode_image`]
+pub(crate) async fn save_image(storage: &dyn MediaStorage, value: &str, key: &str, source: &str) -> Result<StoredImage, ApiError> {
+    let data = decode_image(value, key)?;
+
+    store_image(storage, &data).await
+        .map_err(|e| ApiError::internal(&format!("{} >>> store_image", source), e))
+}
+
+/// Ошибка для файла, который не является картинкой поддерживаемого формата
+pub(crate) fn invalid_image(key: &str) -> ApiError {
+    ApiError::new(ErrorCode::InvalidImage, format!("{} is not a JPEG, PNG, GIF or WebP image!", key))
+}
+
 pub mod service_user;
-pub mod service_article;
\ No newline at end of file
+pub mod service_article;
+pub mod service_session;
+pub mod service_admin;
+pub mod service_tag;
+pub mod service_image;
+pub mod service_upload;
@@ -0,0 +1,64 @@
+pub mod admin {
+    use crate::services::{
+        get_query_param,
+        json_success,
+        read_json,
+    };
+    use actix_web::{
+        get,
+        post,
+        web,
+        HttpRequest,
+        HttpResponse,
+    };
+    use crate::error::api_error::{ApiError, ErrorCode};
+    use crate::postgresql::postgresql_manager::Connect;
+    use crate::postgresql::models::model_user::user::SetUserRole;
+    use crate::auth::auth_session::session::AuthUser;
+    use crate::auth::auth_policy::policy::{
+        audit,
+        authorize,
+        Action,
+    };
+
+    const DEFAULT_MODERATION_LOG_LIMIT: i64 = 100;
+    const MAX_MODERATION_LOG_LIMIT: i64 = 1000;
+
+    #[post("/set-user-role")]
+    pub async fn set_user_role(conn: web::Data<Connect>, auth: AuthUser, mut payload: web::Payload) -> Result<HttpResponse, ApiError> {
+        let set_role = read_json::<SetUserRole>(&mut payload, "[POST][admin/set-user-role]").await?;
+
+        let action = Action::SetUserRole { user_id: set_role.user_id };
+        let grant = authorize(&conn, &auth, action).await?;
+
+        let updated = conn.set_user_role(set_role.user_id, set_role.role).await
+            .map_err(|e| ApiError::internal("[POST][admin/set-user-role] >>> conn.set_user_role", e))?;
+
+        if !updated {
+            return Err(ApiError::new(ErrorCode::UserNotFound, "User not found!"));
+        }
+
+        let details = format!("role: {:?}", set_role.role).to_lowercase();
+        audit(&conn, &auth, action, grant, Some(&details)).await;
+
+        Ok(HttpResponse::Ok().json(
+            json_success("Success")
+        ))
+    }
+
+    #[get("/get-moderation-log")]
+    pub async fn get_moderation_log(conn: web::Data<Connect>, auth: AuthUser, req: HttpRequest) -> Result<HttpResponse, ApiError> {
+        authorize(&conn, &auth, Action::ViewModerationLog).await?;
+
+        let limit = get_query_param::<i64>(&req, "limit").await
+            .unwrap_or(DEFAULT_MODERATION_LOG_LIMIT)
+            .clamp(1, MAX_MODERATION_LOG_LIMIT);
+
+        let records = conn.get_moderation_log(limit).await
+            .map_err(|e| ApiError::internal("[GET][admin/get-moderation-log] >>> conn.get_moderation_log", e))?;
+
+        Ok(HttpResponse::Ok().json(
+            json_success(records)
+        ))
+    }
+}
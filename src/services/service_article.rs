@@ -1,386 +1,632 @@
 pub mod article {
     use crate::services::{
+        get_body_param,
+        get_optional_query_param,
+        get_page_request,
         get_query_param,
-        json_error,
         json_success,
-        read_body_bytes,
+        read_json,
+        read_valid_json,
+        read_multipart,
+        is_multipart,
+        parse_valid_value,
+        invalid_image,
+        decode_image,
+        save_image,
     };
     use actix_web::{
         get,
         post,
         web,
         HttpRequest,
-        Responder,
         HttpResponse,
     };
+    use crate::error::api_error::{ApiError, ErrorCode};
     use crate::postgresql::postgresql_manager::Connect;
-    use crate::logger::log::{Level, log};
+    use crate::storage::storage_media::media::{store_image, store_staged_image, MediaStorage};
+    use crate::storage::storage_staging::staging::{StagedFile, Staging};
+    use crate::auth::auth_session::session::AuthUser;
+    use crate::auth::auth_policy::policy::{
+        audit,
+        authorize,
+        Action,
+    };
     use serde_json::Value;
+    use validator::ValidationErrors;
+    use crate::postgresql::models::model_validation::validation::error;
+    use chrono::{DateTime, Duration, Utc};
+    use crate::postgresql::models::model_page::page::DateCursor;
+    use crate::markdown::render::render_markdown;
     use crate::postgresql::models::model_article::article::{
+        validate_rendered_description,
+        validate_schedule,
+        ArticleSearch,
+        ArticleStatus,
         InsertArticle,
+        InsertArticleMedia,
         InsertComment,
         InsertReaction,
+        PublishArticle,
+        RemoveArticleMedia,
+        ReorderArticleMedia,
+        RestoreArticleRevision,
+        UpdateArticle,
+        MAX_ARTICLE_MEDIA,
     };
+    use crate::postgresql::models::model_image::image::StoredImage;
+
+    const DEFAULT_SEARCH_LIMIT: i64 = 20;
+    const MAX_SEARCH_LIMIT: i64 = 100;
+    const MAX_SEARCH_QUERY_LENGTH: usize = 256;
+    const DEFAULT_TRENDING_LIMIT: i64 = 20;
+    const MAX_TRENDING_LIMIT: i64 = 100;
+    const MAX_TRENDING_WINDOW_HOURS: i64 = 720;
+
+    /// Настройки лент, задаются при запуске сервера
+    #[derive(Clone, Copy, Debug)]
+    pub struct FeedSettings {
+        /// За сколько последних часов выбираются популярные записи, если не передан `window_hours`
+        pub trending_window_hours: i64,
+    }
 
     #[post("/insert-article")]
-    pub async fn insert_article(conn: web::Data<Connect>, mut payload: web::Payload) -> impl Responder {
-        let body = match read_body_bytes(&mut payload).await {
-            Ok(o) => o,
-            Err(_) => return HttpResponse::Ok().json(
-                json_error("Request overflow!")
-            )
+    pub async fn insert_article(conn: web::Data<Connect>, storage: web::Data<dyn MediaStorage>, staging: web::Data<Staging>, auth: AuthUser, req: HttpRequest, mut payload: web::Payload) -> Result<HttpResponse, ApiError> {
+        let (article, file) = if is_multipart(&req) {
+            let mut form = read_multipart(&req, payload, &staging).await?;
+            let file = form.files.remove("image");
+
+            (parse_valid_value::<InsertArticle>(form.fields, "[POST][insert-article]")?, file)
+        } else {
+            (read_valid_json::<InsertArticle>(&mut payload, "[POST][insert-article]").await?, None)
         };
+        validate_schedule(article.status, article.publish_at)?;
 
-        let article = match serde_json::from_slice::<InsertArticle>(&body) {
-            Ok(o) => o,
-            Err(e) => {
-                log(Level::Error, "[POST][insert-article] >>> serde_json::from_slice::<CropArticle>",
-                    &format!("Handle: {}", e)
-                );
+        let description = render_markdown(&article.description);
+        validate_rendered_description(&description)?;
 
-                return HttpResponse::Ok().json(
-                    json_error("Error request!")
-                )
-            },
+        authorize(&conn, &auth, Action::InsertArticle).await?;
+
+        let image = resolve_image(&conn, storage.get_ref(), auth.id, file, article.image.as_deref(), article.media_id, "[POST][insert-article]").await?;
+
+        conn.insert_article(auth.id, &article, &image, &description).await
+            .map_err(|e| ApiError::internal("[POST][insert-article] >>> conn.insert_article(&article)", e))?;
+
+        Ok(HttpResponse::Ok().json(
+            json_success("Success")
+        ))
+    }
+
+    #[post("/publish-article")]
+    pub async fn publish_article(conn: web::Data<Connect>, auth: AuthUser, mut payload: web::Payload) -> Result<HttpResponse, ApiError> {
+        let publish = read_json::<PublishArticle>(&mut payload, "[POST][publish-article]").await?;
+
+        let status = match publish.publish_at {
+            Some(_) => ArticleStatus::Scheduled,
+            None => ArticleStatus::Published,
         };
+        validate_schedule(status, publish.publish_at)?;
 
-        if let Err(e) = conn.insert_article(&article).await {
-            log(Level::Error, "[POST][insert-article] >>> conn.insert_article(&article)",
-                &format!("Handle: {}", e)
-            );
+        authorize(&conn, &auth, Action::EditArticle { article_id: publish.article_id }).await?;
 
-            return HttpResponse::Ok().json(
-                json_error("Error")
-            );
+        let published = conn.publish_article(publish.article_id, publish.publish_at).await
+            .map_err(|e| ApiError::internal("[POST][publish-article] >>> conn.publish_article", e))?;
+
+        if !published {
+            return Err(ApiError::new(ErrorCode::AlreadyPublished, "Article already published!"));
         }
 
-        HttpResponse::Ok().json(
+        Ok(HttpResponse::Ok().json(
             json_success("Success")
-        )
+        ))
+    }
+
+    #[get("/get-article")]
+    pub async fn get_article(conn: web::Data<Connect>, auth: Option<AuthUser>, req: HttpRequest) -> Result<HttpResponse, ApiError> {
+        let article_id = get_query_param::<i32>(&req, "article_id").await?;
+
+        let article = conn.get_article(article_id, auth.map(|o| o.id)).await
+            .map_err(|e| ApiError::internal("[GET][get-article] >>> conn.get_article", e))?
+            .ok_or_else(|| ApiError::new(ErrorCode::ArticleNotFound, "Article not found!"))?;
+
+        Ok(HttpResponse::Ok().json(
+            json_success(article)
+        ))
+    }
+
+    #[get("/get-drafts")]
+    pub async fn get_drafts(conn: web::Data<Connect>, auth: AuthUser, req: HttpRequest) -> Result<HttpResponse, ApiError> {
+        let page = get_page_request::<DateCursor>(&req).await?;
+
+        let articles = conn.get_drafts(auth.id, &page).await
+            .map_err(|e| ApiError::internal("[GET][get-drafts] >>> conn.get_drafts", e))?;
+
+        Ok(HttpResponse::Ok().json(
+            json_success(articles)
+        ))
     }
 
     #[get("/get-articles")]
-    pub async fn get_articles(conn: web::Data<Connect>, req: HttpRequest) -> impl Responder {
-        let user_id = match get_query_param::<i32>(&req, "user_id").await {
-            Ok(o) => o,
-            Err(e) => return HttpResponse::BadRequest().json(
-                json_error(e)
-            )
-        };
+    pub async fn get_articles(conn: web::Data<Connect>, auth: Option<AuthUser>, req: HttpRequest) -> Result<HttpResponse, ApiError> {
+        let user_id = get_query_param::<i32>(&req, "user_id").await?;
 
-        let articles = match conn.get_articles(user_id).await {
-            Ok(o) => o,
-            Err(e) => {
-                log(Level::Error, "[GET][get-articles] >>> conn.get_articles",
-                    &format!("Handle: {}", e)
-                );
+        let page = get_page_request::<DateCursor>(&req).await?;
 
-                return HttpResponse::Ok().json(
-                    json_error("Error!")
-                );
-            },
+        let articles = conn.get_articles(user_id, auth.map(|o| o.id), &page).await
+            .map_err(|e| ApiError::internal("[GET][get-articles] >>> conn.get_articles", e))?;
+
+        Ok(HttpResponse::Ok().json(
+            json_success(articles)
+        ))
+    }
+
+    #[get("/get-latest-articles")]
+    pub async fn get_latest_articles(conn: web::Data<Connect>, auth: Option<AuthUser>, req: HttpRequest) -> Result<HttpResponse, ApiError> {
+        let page = get_page_request::<DateCursor>(&req).await?;
+
+        let articles = conn.get_latest_articles(auth.map(|o| o.id), &page).await
+            .map_err(|e| ApiError::internal("[GET][get-latest-articles] >>> conn.get_latest_articles", e))?;
+
+        Ok(HttpResponse::Ok().json(
+            json_success(articles)
+        ))
+    }
+
+    #[get("/get-trending-articles")]
+    pub async fn get_trending_articles(conn: web::Data<Connect>, auth: Option<AuthUser>, settings: web::Data<FeedSettings>, req: HttpRequest) -> Result<HttpResponse, ApiError> {
+        let window_hours = get_optional_query_param::<i64>(&req, "window_hours").await?
+            .unwrap_or(settings.trending_window_hours)
+            .clamp(1, MAX_TRENDING_WINDOW_HOURS);
+        let limit = get_optional_query_param::<i64>(&req, "limit").await?
+            .unwrap_or(DEFAULT_TRENDING_LIMIT)
+            .clamp(1, MAX_TRENDING_LIMIT);
+
+        let articles = conn.get_trending_articles(auth.map(|o| o.id), Utc::now() - Duration::hours(window_hours), limit).await
+            .map_err(|e| ApiError::internal("[GET][get-trending-articles] >>> conn.get_trending_articles", e))?;
+
+        Ok(HttpResponse::Ok().json(
+            json_success(articles)
+        ))
+    }
+
+    #[get("/search-articles")]
+    pub async fn search_articles(conn: web::Data<Connect>, auth: Option<AuthUser>, req: HttpRequest) -> Result<HttpResponse, ApiError> {
+        let query = get_query_param::<String>(&req, "query").await?;
+
+        if query.trim().is_empty() {
+            return Err(ApiError::new(ErrorCode::InvalidQuery, "Empty query!"));
+        }
+
+        let search = ArticleSearch {
+            query: query.chars().take(MAX_SEARCH_QUERY_LENGTH).collect(),
+            author_id: get_optional_query_param::<i32>(&req, "author_id").await?,
+            from: get_optional_query_param::<DateTime<Utc>>(&req, "from").await?,
+            to: get_optional_query_param::<DateTime<Utc>>(&req, "to").await?,
+            limit: get_optional_query_param::<i64>(&req, "limit").await?
+                .unwrap_or(DEFAULT_SEARCH_LIMIT)
+                .clamp(1, MAX_SEARCH_LIMIT),
         };
 
-        HttpResponse::Ok().json(
+        let articles = conn.search_articles(auth.map(|o| o.id), &search).await
+            .map_err(|e| ApiError::internal("[GET][search-articles] >>> conn.search_articles", e))?;
+
+        Ok(HttpResponse::Ok().json(
             json_success(articles)
-        )
+        ))
     }
 
     #[get("/get-articles-from-user")]
-    pub async fn get_articles_from_user(conn: web::Data<Connect>, req: HttpRequest) -> impl Responder {
-        let user_id = match get_query_param::<i32>(&req, "user_id").await {
-            Ok(o) => o,
-            Err(e) => return HttpResponse::BadRequest().json(
-                json_error(e)
-            )
-        };
+    pub async fn get_articles_from_user(conn: web::Data<Connect>, auth: Option<AuthUser>, req: HttpRequest) -> Result<HttpResponse, ApiError> {
+        let user_id = get_query_param::<i32>(&req, "user_id").await?;
 
-        let articles = match conn.get_articles_from_user(user_id).await {
-            Ok(o) => o,
-            Err(e) => {
-                log(Level::Error, "[GET][get-articles-from-user] >>> conn.get_articles_from_user",
-                    &format!("Handle: {}", e)
-                );
+        let page = get_page_request::<DateCursor>(&req).await?;
 
-                return HttpResponse::Ok().json(
-                    json_error("Error!")
-                );
-            },
-        };
+        let articles = conn.get_articles_from_user(user_id, auth.map(|o| o.id), &page).await
+            .map_err(|e| ApiError::internal("[GET][get-articles-from-user] >>> conn.get_articles_from_user", e))?;
 
-        HttpResponse::Ok().json(
+        Ok(HttpResponse::Ok().json(
             json_success(articles)
-        )
+        ))
     }
 
     #[post("/remove-article")]
-    pub async fn remove_article(conn: web::Data<Connect>, mut payload: web::Payload) -> impl Responder {
-        let body = match read_body_bytes(&mut payload).await {
-            Ok(o) => o,
-            Err(_) => return HttpResponse::Ok().json(
-                json_error("Request overflow!")
-            )
+    pub async fn remove_article(conn: web::Data<Connect>, auth: AuthUser, mut payload: web::Payload) -> Result<HttpResponse, ApiError> {
+        let value = read_json::<Value>(&mut payload, "[POST][remove-article]").await?;
+
+        let article_id = get_body_param::<i32>(&value, "article_id")?;
+
+        let action = Action::RemoveArticle { article_id };
+        let grant = authorize(&conn, &auth, action).await?;
+
+        conn.remove_article(article_id).await
+            .map_err(|e| ApiError::internal("[POST][remove-article] >>> conn.remove_article(&article)", e))?;
+
+        audit(&conn, &auth, action, grant, None).await;
+
+        Ok(HttpResponse::Ok().json(
+            json_success("Success")
+        ))
+    }
+
+    #[post("/update-article")]
+    pub async fn update_article(conn: web::Data<Connect>, storage: web::Data<dyn MediaStorage>, auth: AuthUser, mut payload: web::Payload) -> Result<HttpResponse, ApiError> {
+        let article = read_valid_json::<UpdateArticle>(&mut payload, "[POST][update-article]").await?;
+
+        if article.image.is_none() && article.title.is_none() && article.description.is_none() && article.summary.is_none() && article.tags.is_none() {
+            return Err(ApiError::bad_request("Nothing to update!"));
+        }
+
+        let description = article.description.as_deref().map(render_markdown);
+        if let Some(o) = &description {
+            validate_rendered_description(o)?;
+        }
+
+        authorize(&conn, &auth, Action::EditArticle { article_id: article.article_id }).await?;
+
+        let image = match &article.image {
+            Some(o) => Some(save_image(storage.get_ref(), o, "image", "[POST][update-article]").await?),
+            None => None,
         };
 
-        let value = match serde_json::from_slice::<Value>(&body) {
-            Ok(o) => o,
-            Err(e) => {
-                log(Level::Error, "[POST][remove-article] >>> serde_json::from_slice::<Value>",
-                    &format!("Handle: {}", e)
-                );
+        conn.update_article(auth.id, &article, image.as_ref(), description.as_ref()).await
+            .map_err(|e| ApiError::internal("[POST][update-article] >>> conn.update_article(&article)", e))?;
 
-                return HttpResponse::Ok().json(
-                    json_error("Error request!")
-                )
-            },
+        Ok(HttpResponse::Ok().json(
+            json_success("Success")
+        ))
+    }
+
+    #[post("/add-article-media")]
+    pub async fn add_article_media(conn: web::Data<Connect>, storage: web::Data<dyn MediaStorage>, staging: web::Data<Staging>, auth: AuthUser, req: HttpRequest, mut payload: web::Payload) -> Result<HttpResponse, ApiError> {
+        let (media, file) = if is_multipart(&req) {
+            let mut form = read_multipart(&req, payload, &staging).await?;
+            let file = form.files.remove("image");
+
+            (parse_valid_value::<InsertArticleMedia>(form.fields, "[POST][add-article-media]")?, file)
+        } else {
+            (read_valid_json::<InsertArticleMedia>(&mut payload, "[POST][add-article-media]").await?, None)
         };
 
-        let article_id = value["article_id"].as_i64().unwrap() as i32;
-        let user_id = value["user_id"].as_i64().unwrap() as i32;
+        authorize(&conn, &auth, Action::EditArticle { article_id: media.article_id }).await?;
+
+        let image = resolve_image(&conn, storage.get_ref(), auth.id, file, media.image.as_deref(), media.media_id, "[POST][add-article-media]").await?;
+
+        let item = conn.insert_article_media(&media, &image).await
+            .map_err(|e| ApiError::internal("[POST][add-article-media] >>> conn.insert_article_media", e))?
+            .ok_or_else(|| ApiError::new(ErrorCode::GalleryFull, format!("Gallery can not contain more than {} images!", MAX_ARTICLE_MEDIA)))?;
+
+        Ok(HttpResponse::Ok().json(
+            json_success(item)
+        ))
+    }
+
+    #[post("/reorder-article-media")]
+    pub async fn reorder_article_media(conn: web::Data<Connect>, auth: AuthUser, mut payload: web::Payload) -> Result<HttpResponse, ApiError> {
+        let reorder = read_valid_json::<ReorderArticleMedia>(&mut payload, "[POST][reorder-article-media]").await?;
+
+        authorize(&conn, &auth, Action::EditArticle { article_id: reorder.article_id }).await?;
 
-        if let Err(sqlx::Error::RowNotFound) = conn.get_article_info(article_id).await {
-            return HttpResponse::Ok().json(
-                json_error("Article not found!")
-            );
+        let reordered = conn.reorder_article_media(reorder.article_id, &reorder.items).await
+            .map_err(|e| ApiError::internal("[POST][reorder-article-media] >>> conn.reorder_article_media", e))?;
+
+        if !reordered {
+            return Err(ApiError::bad_request("Items must contain every gallery image exactly once!"));
         }
 
-        let is_author = match conn.is_user_author_article(user_id, article_id).await {
-            Ok(o) => o,
-            Err(e) => {
-                log(Level::Error, "[POST][remove-article] >>> conn.is_user_author_article(user_id, article_id)",
-                    &format!("Handle: {}", e)
-                );
+        Ok(HttpResponse::Ok().json(
+            json_success("Success")
+        ))
+    }
 
-                return HttpResponse::Ok().json(
-                    json_error("Error")
-                );
-            },
-        };
+    #[post("/remove-article-media")]
+    pub async fn remove_article_media(conn: web::Data<Connect>, auth: AuthUser, mut payload: web::Payload) -> Result<HttpResponse, ApiError> {
+        let remove = read_json::<RemoveArticleMedia>(&mut payload, "[POST][remove-article-media]").await?;
+
+        authorize(&conn, &auth, Action::EditArticle { article_id: remove.article_id }).await?;
+
+        let removed = conn.remove_article_media(remove.article_id, remove.item_id).await
+            .map_err(|e| ApiError::internal("[POST][remove-article-media] >>> conn.remove_article_media", e))?;
 
-        if !is_author {
-            return HttpResponse::Ok().json(
-                json_error("You are not author this article!")
-            );
+        if !removed {
+            return Err(ApiError::new(ErrorCode::MediaNotFound, "Media not found!"));
         }
 
-        if let Err(e) = conn.remove_article(article_id).await {
-            log(Level::Error, "[POST][remove-article] >>> conn.remove_article(&article)",
-                &format!("Handle: {}", e)
-            );
+        Ok(HttpResponse::Ok().json(
+            json_success("Success")
+        ))
+    }
+
+    #[get("/get-article-revisions")]
+    pub async fn get_article_revisions(conn: web::Data<Connect>, auth: AuthUser, req: HttpRequest) -> Result<HttpResponse, ApiError> {
+        let article_id = get_query_param::<i32>(&req, "article_id").await?;
+
+        authorize(&conn, &auth, Action::EditArticle { article_id }).await?;
 
-            return HttpResponse::Ok().json(
-                json_error("Error")
-            );
+        let revisions = conn.get_article_revisions(article_id).await
+            .map_err(|e| ApiError::internal("[GET][get-article-revisions] >>> conn.get_article_revisions", e))?;
+
+        Ok(HttpResponse::Ok().json(
+            json_success(revisions)
+        ))
+    }
+
+    #[post("/restore-article-revision")]
+    pub async fn restore_article_revision(conn: web::Data<Connect>, auth: AuthUser, mut payload: web::Payload) -> Result<HttpResponse, ApiError> {
+        let restore = read_json::<RestoreArticleRevision>(&mut payload, "[POST][restore-article-revision]").await?;
+
+        authorize(&conn, &auth, Action::EditArticle { article_id: restore.article_id }).await?;
+
+        let restored = conn.restore_article_revision(auth.id, restore.article_id, restore.revision_id).await
+            .map_err(|e| ApiError::internal("[POST][restore-article-revision] >>> conn.restore_article_revision", e))?;
+
+        if !restored {
+            return Err(ApiError::new(ErrorCode::RevisionNotFound, "Revision not found!"));
         }
 
-        HttpResponse::Ok().json(
+        Ok(HttpResponse::Ok().json(
             json_success("Success")
-        )
+        ))
     }
 
     #[post("/insert-article-comment")]
-    pub async fn insert_article_comment(conn: web::Data<Connect>, mut payload: web::Payload) -> impl Responder {
-        let body = match read_body_bytes(&mut payload).await {
-            Ok(o) => o,
-            Err(_) => return HttpResponse::Ok().json(
-                json_error("Request overflow!")
-            )
-        };
+    pub async fn insert_article_comment(conn: web::Data<Connect>, auth: AuthUser, mut payload: web::Payload) -> Result<HttpResponse, ApiError> {
+        let insert_comment = read_valid_json::<InsertComment>(&mut payload, "[POST][insert-article-comment]").await?;
 
-        let insert_comment = match serde_json::from_slice::<InsertComment>(&body) {
-            Ok(o) => o,
-            Err(e) => {
-                log(Level::Error, "[POST][insert-article-comment] >>> serde_json::from_slice::<InsertComment>",
-                    &format!("Handle: {}", e)
-                );
+        let article_id = insert_comment.article_id;
+        authorize(&conn, &auth, Action::InsertComment { article_id }).await?;
 
-                return HttpResponse::Ok().json(
-                    json_error("Error request!")
-                )
-            },
-        };
+        conn.insert_comment_to_article(auth.id, &insert_comment).await
+            .map_err(|e| ApiError::internal("[POST][insert-article-comment] >>> conn.insert_comment_to_article", e))?;
 
-        if let Err(sqlx::Error::RowNotFound) = conn.insert_comment_to_article(&insert_comment).await {
-            return HttpResponse::Ok().json(
-                json_error("Article not found!")
-            );
-        }
+        Ok(HttpResponse::Ok().json(
+            json_success("Success")
+        ))
+    }
+
+    #[post("/remove-article-comment")]
+    pub async fn remove_article_comment(conn: web::Data<Connect>, auth: AuthUser, mut payload: web::Payload) -> Result<HttpResponse, ApiError> {
+        let value = read_json::<Value>(&mut payload, "[POST][remove-article-comment]").await?;
+
+        let comment_id = get_body_param::<i32>(&value, "comment_id")?;
+
+        let action = Action::RemoveComment { comment_id };
+        let grant = authorize(&conn, &auth, action).await?;
 
-        HttpResponse::Ok().json(
+        conn.remove_comment(comment_id).await
+            .map_err(|e| ApiError::internal("[POST][remove-article-comment] >>> conn.remove_comment(comment_id)", e))?;
+
+        audit(&conn, &auth, action, grant, None).await;
+
+        Ok(HttpResponse::Ok().json(
             json_success("Success")
-        )
+        ))
     }
 
     #[get("/get-article-comments")]
-    pub async fn get_article_comments(conn: web::Data<Connect>, req: HttpRequest) -> impl Responder {
-        let article_id = match get_query_param::<i32>(&req, "article_id").await {
-            Ok(o) => o,
-            Err(e) => return HttpResponse::BadRequest().json(
-                json_error(e)
-            )
-        };
+    pub async fn get_article_comments(conn: web::Data<Connect>, req: HttpRequest) -> Result<HttpResponse, ApiError> {
+        let article_id = get_query_param::<i32>(&req, "article_id").await?;
 
-        let articles = match conn.get_comments_from_article(article_id).await {
-            Ok(o) => o,
-            Err(e) => {
-                log(Level::Error, "[GET][get-article-comments] >>> conn.get_comments_from_article",
-                    &format!("Handle: {}", e)
-                );
+        let page = get_page_request::<DateCursor>(&req).await?;
 
-                return HttpResponse::Ok().json(
-                    json_error("Error!")
-                );
-            },
-        };
+        let comments = conn.get_comments_from_article(article_id, &page).await
+            .map_err(|e| ApiError::internal("[GET][get-article-comments] >>> conn.get_comments_from_article", e))?;
 
-        HttpResponse::Ok().json(
-            json_success(articles)
-        )
+        Ok(HttpResponse::Ok().json(
+            json_success(comments)
+        ))
     }
 
     #[post("/insert-reaction-for-article")]
-    pub async fn insert_reaction_for_article(conn: web::Data<Connect>, mut payload: web::Payload) -> impl Responder {
-        let body = match read_body_bytes(&mut payload).await {
-            Ok(o) => o,
-            Err(_) => return HttpResponse::Ok().json(
-                json_error("Request overflow!")
-            )
-        };
+    pub async fn insert_reaction_for_article(conn: web::Data<Connect>, auth: AuthUser, mut payload: web::Payload) -> Result<HttpResponse, ApiError> {
+        let reaction = read_valid_json::<InsertReaction>(&mut payload, "[POST][insert-reaction-for-article]").await?;
 
-        let reaction = match serde_json::from_slice::<InsertReaction>(&body) {
-            Ok(o) => o,
-            Err(e) => {
-                log(Level::Error, "[POST][insert-reaction-for-article] >>> serde_json::from_slice::<InsertReaction>",
-                    &format!("Handle: {}", e)
-                );
+        let article_id = reaction.article_id;
+        authorize(&conn, &auth, Action::ReactArticle { article_id }).await?;
 
-                return HttpResponse::Ok().json(
-                    json_error("Error request!")
-                )
-            },
-        };
+        let known = conn.exist_reaction(&reaction.reaction).await
+            .map_err(|e| ApiError::internal("[POST][insert-reaction-for-article] >>> conn.exist_reaction", e))?;
 
-        let exists = match conn.exists_reaction_for_article(&reaction).await {
-            Ok(o) => o,
-            Err(e) => {
-                log(Level::Error, "[POST][insert-reaction-for-article] >>> conn.exists_reaction_for_article(&reaction)",
-                    &format!("Handle: {}", e)
-                );
+        if !known {
+            return Err(unknown_reaction());
+        }
 
-                return HttpResponse::Ok().json(
-                    json_error("Error")
-                );
-            }
-        };
+        let exists = conn.exists_reaction_for_article(auth.id, &reaction).await
+            .map_err(|e| ApiError::internal("[POST][insert-reaction-for-article] >>> conn.exists_reaction_for_article(&reaction)", e))?;
 
         if exists {
-            return HttpResponse::Ok().json(
-                json_error("Reaction already inserted!")
-            );
+            return Err(ApiError::new(ErrorCode::ReactionExists, "Reaction already inserted!"));
         }
 
-        if let Err(e) = conn.insert_reaction_for_article(&reaction).await {
-            log(Level::Error, "[POST][insert-reaction-for-article] >>> conn.insert_reaction_for_article(&reaction)",
-                &format!("Handle: {}", e)
-            );
+        conn.insert_reaction_for_article(auth.id, &reaction).await
+            .map_err(|e| ApiError::internal("[POST][insert-reaction-for-article] >>> conn.insert_reaction_for_article(&reaction)", e))?;
 
-            return HttpResponse::Ok().json(
-                json_error("Error")
-            );
-        }
-
-        HttpResponse::Ok().json(
+        Ok(HttpResponse::Ok().json(
             json_success("Success")
-        )
+        ))
     }
 
-    #[get("/get-reaction-for-article-by-user")]
-    pub async fn get_reaction_for_article_by_user(conn: web::Data<Connect>, req: HttpRequest) -> impl Responder {
-        let user_id = match get_query_param::<i32>(&req, "user_id").await {
-            Ok(o) => o,
-            Err(e) => return HttpResponse::BadRequest().json(
-                json_error(e)
-            )
-        };
+    fn unknown_reaction() -> ApiError {
+        let mut errors = ValidationErrors::new();
+        errors.add("reaction", error("exists", "Unknown reaction"));
 
-        let article_id = match get_query_param::<i32>(&req, "article_id").await {
-            Ok(o) => o,
-            Err(e) => return HttpResponse::BadRequest().json(
-                json_error(e)
-            )
-        };
+        ApiError::from(errors)
+    }
 
-        let name = match conn.get_reaction_for_article_by_user(user_id, article_id).await {
-            Ok(o) => o,
-            Err(e) => {
-                log(Level::Error, "[GET][get-reaction-for-article-by-user] >>> conn.get_reaction_for_article_by_user",
-                    &format!("Handle: {}", e)
-                );
+    #[get("/get-reaction-for-article-by-user")]
+    pub async fn get_reaction_for_article_by_user(conn: web::Data<Connect>, req: HttpRequest) -> Result<HttpResponse, ApiError> {
+        let user_id = get_query_param::<i32>(&req, "user_id").await?;
+        let article_id = get_query_param::<i32>(&req, "article_id").await?;
 
-                return HttpResponse::Ok().json(
-                    json_error("Error!")
-                );
-            },
-        };
+        let name = conn.get_reaction_for_article_by_user(user_id, article_id).await
+            .map_err(|e| ApiError::internal("[GET][get-reaction-for-article-by-user] >>> conn.get_reaction_for_article_by_user", e))?;
 
-        HttpResponse::Ok().json(
+        Ok(HttpResponse::Ok().json(
             json_success(name)
-        )
+        ))
     }
 
     #[post("/remove-reaction-for-article")]
-    pub async fn remove_reaction_for_article(conn: web::Data<Connect>, mut payload: web::Payload) -> impl Responder {
-        let body = match read_body_bytes(&mut payload).await {
-            Ok(o) => o,
-            Err(_) => return HttpResponse::Ok().json(
-                json_error("Request overflow!")
-            )
-        };
+    pub async fn remove_reaction_for_article(conn: web::Data<Connect>, auth: AuthUser, mut payload: web::Payload) -> Result<HttpResponse, ApiError> {
+        let reaction = read_valid_json::<InsertReaction>(&mut payload, "[POST][remove-reaction-for-article]").await?;
+
+        let article_id = reaction.article_id;
+        authorize(&conn, &auth, Action::ReactArticle { article_id }).await?;
+
+        let exists = conn.exists_reaction_for_article(auth.id, &reaction).await
+            .map_err(|e| ApiError::internal("[POST][remove-reaction-for-article] >>> conn.exists_reaction_for_article(&reaction)", e))?;
 
-        let reaction = match serde_json::from_slice::<InsertReaction>(&body) {
-            Ok(o) => o,
-            Err(e) => {
-                log(Level::Error, "[POST][remove-reaction-for-article] >>> serde_json::from_slice::<InsertReaction>",
-                    &format!("Handle: {}", e)
-                );
+        if !exists {
+            return Err(ApiError::new(ErrorCode::ReactionNotFound, "Reaction not found!"));
+        }
+
+        conn.remove_reaction_for_article(auth.id, &reaction).await
+            .map_err(|e| ApiError::internal("[POST][remove-reaction-for-article] >>> conn.remove_reaction_for_article(&reaction)", e))?;
+
+        Ok(HttpResponse::Ok().json(
+            json_success("Success")
+        ))
+    }
+
+    /// Источник картинки записи, уже проверенный [`image_source`]
+    enum ImageSource {
+        /// Файл из multipart/form-data
+        File(StagedFile),
+        /// Картинка из base64
+        Data(Vec<u8>),
+        /// `media_id` завершенной загрузки пользователя
+        Media(i32),
+    }
 
-                return HttpResponse::Ok().json(
-                    json_error("Error request!")
-                )
+    /// Проверка, что у картинки записи ровно один источник: файл из multipart/form-data, base64 или `media_id`,
+    /// и что файл или base64 - картинка. База данных и хранилище не нужны
+    ///
+    /// ### Возвращает:
+    /// Если [`Ok`], то [`ImageSource`]. Если источников нет или их несколько - [`ApiError`] 400,
+    /// если файл не является картинкой - [`ApiError`] с [`ErrorCode::InvalidImage`]
+    fn image_source(file: Option<StagedFile>, image: Option<&str>, media_id: Option<i32>) -> Result<ImageSource, ApiError> {
+        match (file, image, media_id) {
+            (Some(file), None, None) => match file.content_type() {
+                "application/octet-stream" => Err(invalid_image("image")),
+                _ => Ok(ImageSource::File(file)),
             },
-        };
+            (None, Some(image), None) => Ok(ImageSource::Data(decode_image(image, "image")?)),
+            (None, None, Some(media_id)) => Ok(ImageSource::Media(media_id)),
+            (None, None, None) => Err(ApiError::bad_request("Not found image!")),
+            _ => Err(ApiError::bad_request("Expected either image or media_id!")),
+        }
+    }
+
+    /// Картинка для записи ровно из одного источника (см. [`image_source`]), сохраненная в хранилище
+    ///
+    /// ### Возвращает:
+    /// Если [`Ok`], то [`StoredImage`]. Ошибки проверки источника - как у [`image_source`],
+    /// если загрузки `media_id` нет - [`ApiError`] с [`ErrorCode::MediaNotFound`]
+    async fn resolve_image(conn: &Connect, storage: &dyn MediaStorage, user_id: i32, file: Option<StagedFile>, image: Option<&str>, media_id: Option<i32>, source: &str) -> Result<StoredImage, ApiError> {
+        match image_source(file, image, media_id)? {
+            ImageSource::File(file) => store_staged_image(storage, &file).await
+                .map_err(|e| ApiError::internal(&format!("{} >>> store_staged_image", source), e)),
+            ImageSource::Data(data) => store_image(storage, &data).await
+                .map_err(|e| ApiError::internal(&format!("{} >>> store_image", source), e)),
+            ImageSource::Media(media_id) => conn.get_upload_image(user_id, media_id).await
+                .map_err(|e| ApiError::internal(&format!("{} >>> conn.get_upload_image", source), e))?
+                .ok_or_else(|| ApiError::new(ErrorCode::MediaNotFound, "Media not found!")),
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use base64::Engine;
+        use base64::engine::general_purpose;
+        use super::{image_source, resolve_image, ImageSource};
+        use crate::error::api_error::{ApiError, ErrorCode};
+        use crate::storage::storage_local::local::LocalStorage;
+        use crate::storage::storage_media::media::MediaStorage;
+        use crate::storage::storage_staging::staging::{StagedFile, Staging};
+        use crate::testing::fixtures::connect;
+
+        const PNG: [u8; 12] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, 0, 0, 0, 0];
+        const SOURCE: &str = "[Test][resolve-image]";
+
+        fn storage() -> LocalStorage {
+            LocalStorage::new(std::env::temp_dir().join("xamarin-news-api-test-media"))
+        }
 
-        let exists = match conn.exists_reaction_for_article(&reaction).await {
-            Ok(o) => o,
-            Err(e) => {
-                log(Level::Error, "[POST][remove-reaction-for-article] >>> conn.exists_reaction_for_article(&reaction)",
-                    &format!("Handle: {}", e)
-                );
+        async fn stage(data: &[u8]) -> StagedFile {
+            let staging = Staging::new(std::env::temp_dir().join("xamarin-news-api-test-media").join(".staging"));
+            let mut writer = staging.create().await.unwrap();
+            writer.write(data).await.unwrap();
+            writer.finish().await.unwrap()
+        }
 
-                return HttpResponse::Ok().json(
-                    json_error("Error")
-                );
+        fn assert_code(result: Result<ImageSource, ApiError>, code: ErrorCode) {
+            match result {
+                Err(e) => assert_eq!(e.code, code, "{}", e),
+                Ok(_) => panic!("expected {:?}", code),
             }
-        };
+        }
 
-        if !exists {
-            return HttpResponse::Ok().json(
-                json_error("Reaction not found!")
-            );
+        #[actix_web::test]
+        async fn file_and_base64_are_rejected() {
+            let image = general_purpose::STANDARD.encode(PNG);
+
+            let result = image_source(Some(stage(&PNG).await), Some(&image), None);
+
+            let Err(error) = result else { panic!("file and base64 must be rejected") };
+            assert_eq!(error.code, ErrorCode::InvalidBody);
+            assert_eq!(error.message, "Expected either image or media_id!");
         }
 
-        if let Err(e) = conn.remove_reaction_for_article(&reaction).await {
-            log(Level::Error, "[POST][remove-reaction-for-article] >>> conn.remove_reaction_for_article(&reaction)",
-                &format!("Handle: {}", e)
-            );
+        #[actix_web::test]
+        async fn file_and_media_id_are_rejected() {
+            assert_code(image_source(Some(stage(&PNG).await), None, Some(1)), ErrorCode::InvalidBody);
+        }
 
-            return HttpResponse::Ok().json(
-                json_error("Error")
-            );
+        #[test]
+        fn base64_and_media_id_are_rejected() {
+            let image = general_purpose::STANDARD.encode(PNG);
+
+            assert_code(image_source(None, Some(&image), Some(1)), ErrorCode::InvalidBody);
         }
 
-        HttpResponse::Ok().json(
-            json_success("Success")
-        )
+        #[test]
+        fn missing_image_is_rejected() {
+            assert_code(image_source(None, None, None), ErrorCode::InvalidBody);
+        }
+
+        #[actix_web::test]
+        async fn file_that_is_not_image_is_rejected() {
+            let file = stage(b"<html><script>alert(1)</script></html>").await;
+
+            assert_code(image_source(Some(file), None, None), ErrorCode::InvalidImage);
+        }
+
+        #[test]
+        fn base64_that_is_not_image_is_rejected() {
+            let image = general_purpose::STANDARD.encode(b"%PDF-1.7");
+
+            assert_code(image_source(None, Some(&image), None), ErrorCode::InvalidImage);
+            assert_code(image_source(None, Some("not base64!"), None), ErrorCode::InvalidBody);
+        }
+
+        #[test]
+        fn base64_image_is_decoded() {
+            let image = general_purpose::STANDARD.encode(PNG);
+
+            let Ok(ImageSource::Data(data)) = image_source(None, Some(&image), None) else { panic!("base64 image must be accepted") };
+            assert_eq!(data, PNG);
+        }
+
+        #[actix_web::test]
+        async fn staged_file_is_stored() {
+            let Some(conn) = connect().await else { return };
+            let storage = storage();
+            let file = stage(&PNG).await;
+            let hash = file.hash().to_owned();
+
+            let image = resolve_image(&conn, &storage, 0, Some(file), None, None, SOURCE).await.unwrap();
+
+            assert_eq!(image.hash, hash);
+            assert_eq!(image.content_type, "image/png");
+            assert_eq!(storage.get(&hash).await.unwrap(), Some(PNG.to_vec()));
+        }
     }
 }
@@ -0,0 +1,165 @@
+pub mod image {
+    use actix_web::{
+        get,
+        web,
+        HttpRequest,
+        HttpResponse,
+    };
+    use actix_web::http::header::{
+        CacheControl,
+        CacheDirective,
+        ETag,
+        EntityTag,
+        IfNoneMatch,
+        Header,
+        X_CONTENT_TYPE_OPTIONS,
+    };
+    use crate::auth::auth_session::session::AuthUser;
+    use crate::error::api_error::{ApiError, ErrorCode};
+    use crate::postgresql::postgresql_manager::Connect;
+    use crate::postgresql::models::model_image::image::VisibleImage;
+    use crate::storage::storage_media::media::MediaStorage;
+
+    /// Картинка по ID никогда не меняется, поэтому клиент может хранить ее в кеше сколько угодно
+    const IMAGE_MAX_AGE_SECONDS: u32 = 31_536_000;
+
+    #[get("/images/{image_id}")]
+    pub async fn get_image(conn: web::Data<Connect>, storage: web::Data<dyn MediaStorage>, auth: Option<AuthUser>, req: HttpRequest, path: web::Path<i32>) -> Result<HttpResponse, ApiError> {
+        let image_id = path.into_inner();
+
+        // Недоступная картинка отвечает так же, как несуществующая, чтобы по ID нельзя было узнать о черновиках
+        let VisibleImage { image, public } = match conn.get_image(image_id, auth.map(|o| o.id)).await {
+            Ok(Some(o)) => o,
+            Ok(None) => return Err(ApiError::new(ErrorCode::ImageNotFound, "Image not found!")),
+            Err(e) => return Err(ApiError::internal("[GET][images] >>> conn.get_image", e)),
+        };
+
+        let etag = EntityTag::new_strong(image.hash.clone());
+        // Картинку черновика хранит только кеш клиента и каждый раз сверяет с сервером: доступ к ней может пропасть
+        let cache_control = match public {
+            true => CacheControl(vec![
+                CacheDirective::Public,
+                CacheDirective::MaxAge(IMAGE_MAX_AGE_SECONDS),
+                CacheDirective::Extension("immutable".to_owned(), None),
+            ]),
+            false => CacheControl(vec![
+                CacheDirective::Private,
+                CacheDirective::NoCache,
+            ]),
+        };
+
+        let not_modified = match IfNoneMatch::parse(&req) {
+            Ok(IfNoneMatch::Any) => true,
+            Ok(IfNoneMatch::Items(items)) => items.iter().any(|o| o.weak_eq(&etag)),
+            Err(_) => false,
+        };
+
+        if not_modified {
+            return Ok(HttpResponse::NotModified()
+                .insert_header(ETag(etag))
+                .insert_header(cache_control)
+                .finish());
+        }
+
+        let data = match image.data {
+            Some(o) => o,
+            None => match storage.get(&image.hash).await {
+                Ok(Some(o)) => o,
+                Ok(None) => return Err(ApiError::internal("[GET][images] >>> storage.get", format!("Missing media {}", image.hash))),
+                Err(e) => return Err(ApiError::internal("[GET][images] >>> storage.get", e)),
+            },
+        };
+
+        Ok(HttpResponse::Ok()
+            .content_type(image.content_type)
+            .insert_header(ETag(etag))
+            .insert_header(cache_control)
+            .insert_header((X_CONTENT_TYPE_OPTIONS, "nosniff"))
+            .body(data))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use std::sync::Arc;
+        use actix_web::{test, web, App};
+        use actix_web::http::StatusCode;
+        use actix_web::http::header::CACHE_CONTROL;
+        use rand::Rng;
+        use sqlx::Row;
+        use super::get_image;
+        use crate::markdown::render::render_markdown;
+        use crate::postgresql::postgresql_manager::Connect;
+        use crate::postgresql::models::model_article::article::{ArticleStatus, InsertArticle};
+        use crate::postgresql::models::model_user::user::Role;
+        use crate::storage::storage_local::local::LocalStorage;
+        use crate::storage::storage_media::media::{store_image, MediaStorage};
+        use crate::testing::fixtures::{connect, insert_user};
+
+        /// Запрос картинки
+        ///
+        /// ### Возвращает:
+        /// Статус ответа и `Cache-Control`
+        async fn fetch(conn: &Connect, storage: Arc<dyn MediaStorage>, image_id: i32, token: Option<&str>) -> (StatusCode, String) {
+            let app = test::init_service(App::new()
+                .app_data(web::Data::new(conn.clone()))
+                .app_data(web::Data::from(storage))
+                .service(get_image)
+            ).await;
+
+            let mut request = test::TestRequest::get().uri(&format!("/images/{}", image_id));
+            if let Some(token) = token {
+                request = request.insert_header(("Authorization", format!("Bearer {}", token)));
+            }
+
+            let response = test::call_service(&app, request.to_request()).await;
+            let cache_control = response.headers().get(CACHE_CONTROL)
+                .map(|o| o.to_str().unwrap().to_owned())
+                .unwrap_or_default();
+
+            (response.status(), cache_control)
+        }
+
+        #[actix_web::test]
+        async fn draft_image_is_visible_only_to_author() {
+            let Some(conn) = connect().await else { return };
+            let storage: Arc<dyn MediaStorage> = Arc::new(LocalStorage::new(std::env::temp_dir().join("xamarin-news-api-test-media")));
+            let author = insert_user(&conn, Role::User).await;
+            let other = insert_user(&conn, Role::User).await;
+
+            // Картинка уникальная, иначе она может оказаться общей с опубликованной записью
+            let mut data = vec![0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+            data.extend(rand::thread_rng().gen::<[u8; 16]>());
+            let image = store_image(storage.as_ref(), &data).await.unwrap();
+
+            let article = InsertArticle {
+                image: None,
+                media_id: None,
+                title: "Draft".to_owned(),
+                description: "Draft article".to_owned(),
+                summary: None,
+                status: ArticleStatus::Draft,
+                publish_at: None,
+                tags: Vec::new(),
+            };
+            conn.insert_article(author.auth.id, &article, &image, &render_markdown(&article.description)).await.unwrap();
+
+            let row = sqlx::query("SELECT a.id, a.image_id FROM articles AS a JOIN images AS i ON i.id = a.image_id WHERE i.hash = $1;")
+                .bind(&image.hash)
+                .fetch_one(&conn.pool).await.unwrap();
+            let (article_id, image_id): (i32, i32) = (row.get("id"), row.get("image_id"));
+
+            assert_eq!(fetch(&conn, storage.clone(), image_id, None).await.0, StatusCode::NOT_FOUND);
+            assert_eq!(fetch(&conn, storage.clone(), image_id, Some(&other.token)).await.0, StatusCode::NOT_FOUND);
+
+            let (status, cache_control) = fetch(&conn, storage.clone(), image_id, Some(&author.token)).await;
+            assert_eq!(status, StatusCode::OK);
+            assert!(cache_control.contains("private") && !cache_control.contains("public"), "{}", cache_control);
+
+            assert!(conn.publish_article(article_id, None).await.unwrap());
+
+            let (status, cache_control) = fetch(&conn, storage.clone(), image_id, None).await;
+            assert_eq!(status, StatusCode::OK);
+            assert!(cache_control.contains("public") && cache_control.contains("immutable"), "{}", cache_control);
+        }
+    }
+}
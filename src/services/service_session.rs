@@ -0,0 +1,114 @@
+pub mod session {
+    use crate::services::{
+        json_success,
+        parse_json,
+        read_body_bytes,
+        read_json,
+    };
+    use actix_web::{
+        get,
+        post,
+        web,
+        HttpResponse,
+    };
+    use crate::error::api_error::{ApiError, ErrorCode};
+    use crate::postgresql::postgresql_manager::Connect;
+    use crate::postgresql::models::model_session::session::{
+        RefreshOutcome,
+        RefreshSession,
+        RevokeAllSessions,
+        RevokeSession,
+    };
+    use crate::auth::auth_session::session::{
+        hash_token,
+        issue_tokens,
+        AuthUser,
+    };
+    use crate::logger::log::{Level, log};
+
+    #[post("/refresh-token")]
+    pub async fn refresh_token(conn: web::Data<Connect>, mut payload: web::Payload) -> Result<HttpResponse, ApiError> {
+        let refresh = read_json::<RefreshSession>(&mut payload, "[POST][refresh-token]").await?;
+
+        let (pair, tokens) = issue_tokens();
+
+        let outcome = conn.rotate_refresh_token(&hash_token(&refresh.refresh_token), &tokens).await
+            .map_err(|e| ApiError::internal("[POST][refresh-token] >>> conn.rotate_refresh_token", e))?;
+
+        match outcome {
+            RefreshOutcome::Rotated { .. } => Ok(HttpResponse::Ok().json(
+                json_success(pair)
+            )),
+            RefreshOutcome::Reused { session_id, user_id } => {
+                log(Level::Warning, "[POST][refresh-token] >>> refresh token reuse detected, session revoked",
+                    &format!("Session: {}, user: {}", session_id, user_id)
+                );
+
+                Err(ApiError::new(ErrorCode::InvalidRefreshToken, "Unauthorized!"))
+            },
+            RefreshOutcome::Invalid => Err(ApiError::new(ErrorCode::InvalidRefreshToken, "Unauthorized!")),
+        }
+    }
+
+    #[post("/logout")]
+    pub async fn logout(conn: web::Data<Connect>, auth: AuthUser) -> Result<HttpResponse, ApiError> {
+        conn.revoke_session(auth.id, auth.session_id).await
+            .map_err(|e| ApiError::internal("[POST][logout] >>> conn.revoke_session", e))?;
+
+        Ok(HttpResponse::Ok().json(
+            json_success("Success")
+        ))
+    }
+
+    #[get("/get-sessions")]
+    pub async fn get_sessions(conn: web::Data<Connect>, auth: AuthUser) -> Result<HttpResponse, ApiError> {
+        let mut sessions = conn.get_active_sessions(auth.id).await
+            .map_err(|e| ApiError::internal("[GET][get-sessions] >>> conn.get_active_sessions", e))?;
+
+        for session in &mut sessions {
+            session.current = session.id == auth.session_id;
+        }
+
+        Ok(HttpResponse::Ok().json(
+            json_success(sessions)
+        ))
+    }
+
+    #[post("/revoke-session")]
+    pub async fn revoke_session(conn: web::Data<Connect>, auth: AuthUser, mut payload: web::Payload) -> Result<HttpResponse, ApiError> {
+        let revoke = read_json::<RevokeSession>(&mut payload, "[POST][revoke-session]").await?;
+
+        let revoked = conn.revoke_session(auth.id, revoke.session_id).await
+            .map_err(|e| ApiError::internal("[POST][revoke-session] >>> conn.revoke_session", e))?;
+
+        if !revoked {
+            return Err(ApiError::new(ErrorCode::SessionNotFound, "Session not found!"));
+        }
+
+        Ok(HttpResponse::Ok().json(
+            json_success("Success")
+        ))
+    }
+
+    #[post("/revoke-all-sessions")]
+    pub async fn revoke_all_sessions(conn: web::Data<Connect>, auth: AuthUser, mut payload: web::Payload) -> Result<HttpResponse, ApiError> {
+        let body = read_body_bytes(&mut payload).await?;
+
+        let revoke = match body.is_empty() {
+            true => RevokeAllSessions { keep_current: false },
+            false => parse_json::<RevokeAllSessions>(&body, "[POST][revoke-all-sessions]")?,
+        };
+
+        let keep_session_id = match revoke.keep_current {
+            true => Some(auth.session_id),
+            false => None,
+        };
+
+        let count = conn.revoke_all_sessions(auth.id, keep_session_id).await
+            .map_err(|e| ApiError::internal("[POST][revoke-all-sessions] >>> conn.revoke_all_sessions", e))?;
+
+        Ok(HttpResponse::Ok().json(
+            json_success(count)
+        ))
+    }
+}
@@ -0,0 +1,137 @@
+pub mod tag {
+    use crate::services::{
+        get_optional_query_param,
+        get_page_request,
+        get_query_param,
+        json_success,
+        read_json,
+    };
+    use actix_web::{
+        get,
+        post,
+        web,
+        HttpRequest,
+        HttpResponse,
+    };
+    use chrono::{Duration, Utc};
+    use crate::error::api_error::{ApiError, ErrorCode};
+    use crate::postgresql::postgresql_manager::Connect;
+    use crate::auth::auth_session::session::AuthUser;
+    use crate::postgresql::models::model_page::page::DateCursor;
+    use crate::postgresql::models::model_tag::tag::{
+        normalize_tag,
+        FollowTag,
+        MAX_TAG_LENGTH,
+    };
+
+    const DEFAULT_TAGS_LIMIT: i64 = 10;
+    const MAX_TAGS_LIMIT: i64 = 50;
+    const DEFAULT_TRENDING_DAYS: i64 = 7;
+    const MAX_TRENDING_DAYS: i64 = 365;
+
+    #[get("/autocomplete-tags")]
+    pub async fn autocomplete_tags(conn: web::Data<Connect>, req: HttpRequest) -> Result<HttpResponse, ApiError> {
+        let prefix = normalize_tag(&get_query_param::<String>(&req, "prefix").await?);
+
+        if prefix.is_empty() {
+            return Err(ApiError::new(ErrorCode::InvalidQuery, "Empty prefix!"));
+        }
+
+        let prefix = prefix.chars().take(MAX_TAG_LENGTH).collect::<String>();
+        let limit = get_optional_query_param::<i64>(&req, "limit").await?
+            .unwrap_or(DEFAULT_TAGS_LIMIT)
+            .clamp(1, MAX_TAGS_LIMIT);
+
+        let tags = conn.autocomplete_tags(&prefix, limit).await
+            .map_err(|e| ApiError::internal("[GET][autocomplete-tags] >>> conn.autocomplete_tags", e))?;
+
+        Ok(HttpResponse::Ok().json(
+            json_success(tags)
+        ))
+    }
+
+    #[get("/trending-tags")]
+    pub async fn get_trending_tags(conn: web::Data<Connect>, req: HttpRequest) -> Result<HttpResponse, ApiError> {
+        let days = get_optional_query_param::<i64>(&req, "days").await?
+            .unwrap_or(DEFAULT_TRENDING_DAYS)
+            .clamp(1, MAX_TRENDING_DAYS);
+        let limit = get_optional_query_param::<i64>(&req, "limit").await?
+            .unwrap_or(DEFAULT_TAGS_LIMIT)
+            .clamp(1, MAX_TAGS_LIMIT);
+
+        let tags = conn.get_trending_tags(Utc::now() - Duration::days(days), limit).await
+            .map_err(|e| ApiError::internal("[GET][trending-tags] >>> conn.get_trending_tags", e))?;
+
+        Ok(HttpResponse::Ok().json(
+            json_success(tags)
+        ))
+    }
+
+    #[get("/articles-by-tag")]
+    pub async fn get_articles_by_tag(conn: web::Data<Connect>, auth: Option<AuthUser>, req: HttpRequest) -> Result<HttpResponse, ApiError> {
+        let tag = normalize_tag(&get_query_param::<String>(&req, "tag").await?);
+
+        let page = get_page_request::<DateCursor>(&req).await?;
+
+        let articles = conn.get_articles_by_tag(&tag, auth.map(|o| o.id), &page).await
+            .map_err(|e| ApiError::internal("[GET][articles-by-tag] >>> conn.get_articles_by_tag", e))?;
+
+        Ok(HttpResponse::Ok().json(
+            json_success(articles)
+        ))
+    }
+
+    #[get("/get-followed-tags")]
+    pub async fn get_followed_tags(conn: web::Data<Connect>, auth: AuthUser) -> Result<HttpResponse, ApiError> {
+        let tags = conn.get_followed_tags(auth.id).await
+            .map_err(|e| ApiError::internal("[GET][get-followed-tags] >>> conn.get_followed_tags", e))?;
+
+        Ok(HttpResponse::Ok().json(
+            json_success(tags)
+        ))
+    }
+
+    #[post("/follow-tag")]
+    pub async fn follow_tag(conn: web::Data<Connect>, auth: AuthUser, mut payload: web::Payload) -> Result<HttpResponse, ApiError> {
+        let FollowTag { tag } = read_json::<FollowTag>(&mut payload, "[POST][follow-tag]").await?;
+
+        let tag_id = match conn.get_tag_id(&normalize_tag(&tag)).await {
+            Ok(Some(o)) => o,
+            Ok(None) => return Err(ApiError::new(ErrorCode::TagNotFound, "Tag not found!")),
+            Err(e) => return Err(ApiError::internal("[POST][follow-tag] >>> conn.get_tag_id", e)),
+        };
+
+        let followed = conn.follow_tag(auth.id, tag_id).await
+            .map_err(|e| ApiError::internal("[POST][follow-tag] >>> conn.follow_tag", e))?;
+
+        if !followed {
+            return Err(ApiError::new(ErrorCode::AlreadyFollowing, "You already subscribed this tag!"));
+        }
+
+        Ok(HttpResponse::Ok().json(
+            json_success("Success")
+        ))
+    }
+
+    #[post("/unfollow-tag")]
+    pub async fn unfollow_tag(conn: web::Data<Connect>, auth: AuthUser, mut payload: web::Payload) -> Result<HttpResponse, ApiError> {
+        let FollowTag { tag } = read_json::<FollowTag>(&mut payload, "[POST][unfollow-tag]").await?;
+
+        let tag_id = match conn.get_tag_id(&normalize_tag(&tag)).await {
+            Ok(Some(o)) => o,
+            Ok(None) => return Err(ApiError::new(ErrorCode::TagNotFound, "Tag not found!")),
+            Err(e) => return Err(ApiError::internal("[POST][unfollow-tag] >>> conn.get_tag_id", e)),
+        };
+
+        let unfollowed = conn.unfollow_tag(auth.id, tag_id).await
+            .map_err(|e| ApiError::internal("[POST][unfollow-tag] >>> conn.unfollow_tag", e))?;
+
+        if !unfollowed {
+            return Err(ApiError::new(ErrorCode::NotFollowing, "You are not subscribed this tag!"));
+        }
+
+        Ok(HttpResponse::Ok().json(
+            json_success("Success")
+        ))
+    }
+}
@@ -0,0 +1,210 @@
+pub mod upload {
+    use actix_web::{
+        get,
+        post,
+        put,
+        web,
+        HttpRequest,
+        HttpResponse,
+    };
+    use serde_json::json;
+    use crate::services::{
+        get_query_param,
+        json_success,
+        read_body_bytes_limited,
+        read_json,
+        read_valid_json,
+    };
+    use crate::error::api_error::{ApiError, ErrorCode};
+    use crate::postgresql::postgresql_manager::Connect;
+    use crate::postgresql::models::model_image::image::ImageUrl;
+    use crate::postgresql::models::model_upload::upload::{
+        AppendChunk,
+        CreateUpload,
+        FinalizeUpload,
+        MAX_UPLOAD_CHUNK_SIZE,
+    };
+    use crate::storage::storage_media::media::{store_staged_image, MediaStorage};
+    use crate::storage::storage_staging::staging::Staging;
+    use crate::auth::auth_session::session::AuthUser;
+    use crate::auth::auth_policy::policy::{authorize, Action};
+    use crate::logger::log::{Level, log};
+
+    #[post("/create-upload")]
+    pub async fn create_upload(conn: web::Data<Connect>, auth: AuthUser, mut payload: web::Payload) -> Result<HttpResponse, ApiError> {
+        let upload = read_valid_json::<CreateUpload>(&mut payload, "[POST][create-upload]").await?;
+
+        authorize(&conn, &auth, Action::InsertArticle).await?;
+
+        let upload = conn.insert_upload(auth.id, upload.size).await
+            .map_err(|e| ApiError::internal("[POST][create-upload] >>> conn.insert_upload", e))?;
+
+        Ok(HttpResponse::Ok().json(
+            json_success(upload)
+        ))
+    }
+
+    /// Часть загрузки. Тело запроса - содержимое части, `offset` - с какого байта она начинается.
+    /// Если ответ на часть потерялся, клиент узнает текущее смещение из `get-upload` и продолжает с него
+    #[put("/upload-chunk/{upload_id}")]
+    pub async fn upload_chunk(conn: web::Data<Connect>, staging: web::Data<Staging>, auth: AuthUser, req: HttpRequest, path: web::Path<i32>, mut payload: web::Payload) -> Result<HttpResponse, ApiError> {
+        let upload_id = path.into_inner();
+        let offset = get_query_param::<i64>(&req, "offset").await?;
+
+        let data = read_body_bytes_limited(&mut payload, MAX_UPLOAD_CHUNK_SIZE).await?;
+
+        if data.is_empty() {
+            return Err(ApiError::bad_request("Empty chunk!"));
+        }
+
+        // части одной загрузки пишутся по очереди, база данных во время записи не блокируется
+        let _lock = staging.lock_upload(upload_id).await;
+
+        // запись выполняется, только если смещение совпало с полученным, поэтому оно не отрицательное
+        let write = staging.write_upload_chunk(upload_id, offset as u64, &data);
+
+        let upload = match conn.append_upload_chunk(auth.id, upload_id, offset, data.len() as i64, write).await {
+            Ok(AppendChunk::Accepted(o)) => o,
+            Ok(AppendChunk::NotFound) => return Err(ApiError::new(ErrorCode::UploadNotFound, "Upload not found!")),
+            Ok(AppendChunk::Completed) => return Err(ApiError::new(ErrorCode::UploadCompleted, "Upload already completed!")),
+            Ok(AppendChunk::OffsetMismatch(received)) => return Err(ApiError::new(ErrorCode::UploadOffsetMismatch,
+                format!("Expected offset {}!", received)
+            )),
+            Ok(AppendChunk::Overflow) => return Err(ApiError::bad_request("Chunk exceeds upload size!")),
+            Err(e) => return Err(ApiError::internal("[PUT][upload-chunk] >>> conn.append_upload_chunk", e)),
+        };
+
+        Ok(HttpResponse::Ok().json(
+            json_success(upload)
+        ))
+    }
+
+    #[get("/get-upload")]
+    pub async fn get_upload(conn: web::Data<Connect>, auth: AuthUser, req: HttpRequest) -> Result<HttpResponse, ApiError> {
+        let upload_id = get_query_param::<i32>(&req, "upload_id").await?;
+
+        let upload = conn.get_upload(auth.id, upload_id).await
+            .map_err(|e| ApiError::internal("[GET][get-upload] >>> conn.get_upload", e))?
+            .ok_or_else(|| ApiError::new(ErrorCode::UploadNotFound, "Upload not found!"))?;
+
+        Ok(HttpResponse::Ok().json(
+            json_success(upload)
+        ))
+    }
+
+    /// Завершение загрузки. Повторный вызов для завершенной загрузки возвращает тот же `media_id`.
+    /// Завершение и части одной загрузки выполняются по очереди под [`Staging::lock_upload`]
+    #[post("/finalize-upload")]
+    pub async fn finalize_upload(conn: web::Data<Connect>, storage: web::Data<dyn MediaStorage>, staging: web::Data<Staging>, auth: AuthUser, mut payload: web::Payload) -> Result<HttpResponse, ApiError> {
+        let finalize = read_json::<FinalizeUpload>(&mut payload, "[POST][finalize-upload]").await?;
+
+        // параллельное завершение ждет, пока первое не удалит файл, и получает уже готовый media_id
+        let _lock = staging.lock_upload(finalize.upload_id).await;
+
+        let upload = conn.get_upload(auth.id, finalize.upload_id).await
+            .map_err(|e| ApiError::internal("[POST][finalize-upload] >>> conn.get_upload", e))?
+            .ok_or_else(|| ApiError::new(ErrorCode::UploadNotFound, "Upload not found!"))?;
+
+        let media_id = match upload.media_id {
+            Some(o) => o,
+            None => {
+                if upload.received < upload.size {
+                    return Err(ApiError::new(ErrorCode::UploadIncomplete,
+                        format!("Received {} of {} bytes!", upload.received, upload.size)
+                    ));
+                }
+
+                let file = staging.open_upload(upload.id, upload.size as u64).await
+                    .map_err(|e| ApiError::internal("[POST][finalize-upload] >>> staging.open_upload", e))?;
+
+                if file.content_type() == "application/octet-stream" {
+                    return Err(ApiError::new(ErrorCode::InvalidImage, "Upload is not an image!"));
+                }
+
+                let image = store_staged_image(storage.get_ref(), &file).await
+                    .map_err(|e| ApiError::internal("[POST][finalize-upload] >>> store_staged_image", e))?;
+
+                let completed = conn.complete_upload(auth.id, upload.id, &image).await
+                    .map_err(|e| ApiError::internal("[POST][finalize-upload] >>> conn.complete_upload", e))?;
+
+                // если файл не удалился, его уберет сборщик загрузок
+                if let Err(e) = staging.remove_upload(upload.id).await {
+                    log(Level::Error, "[POST][finalize-upload] >>> staging.remove_upload", &format!("Handle: {}", e));
+                }
+
+                match completed {
+                    Some(o) => o,
+                    // загрузку завершил параллельный запрос
+                    None => conn.get_upload(auth.id, upload.id).await
+                        .map_err(|e| ApiError::internal("[POST][finalize-upload] >>> conn.get_upload", e))?
+                        .and_then(|o| o.media_id)
+                        .ok_or_else(|| ApiError::new(ErrorCode::UploadNotFound, "Upload not found!"))?,
+                }
+            },
+        };
+
+        Ok(HttpResponse::Ok().json(
+            json_success(json!({
+                "media_id": media_id,
+                "image": ImageUrl(media_id),
+            }))
+        ))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use std::sync::Arc;
+        use actix_web::{test, web, App};
+        use actix_web::http::StatusCode;
+        use serde_json::{json, Value};
+        use super::{finalize_upload, upload_chunk};
+        use crate::postgresql::models::model_user::user::Role;
+        use crate::storage::storage_local::local::LocalStorage;
+        use crate::storage::storage_media::media::MediaStorage;
+        use crate::storage::storage_staging::staging::Staging;
+        use crate::testing::fixtures::{connect, insert_user};
+
+        const PNG_HEADER: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+
+        #[actix_web::test]
+        async fn concurrent_finalize_returns_same_media_id() {
+            let Some(conn) = connect().await else { return };
+            let user = insert_user(&conn, Role::User).await;
+            let data = [&PNG_HEADER[..], b"finalized twice"].concat();
+            let upload = conn.insert_upload(user.auth.id, data.len() as i64).await.unwrap();
+
+            let root = std::env::temp_dir().join("xamarin-news-api-test-media");
+            let storage: Arc<dyn MediaStorage> = Arc::new(LocalStorage::new(&root));
+            let app = test::init_service(App::new()
+                .app_data(web::Data::new(conn.clone()))
+                .app_data(web::Data::from(storage))
+                .app_data(web::Data::new(Staging::new(root.join(".staging"))))
+                .service(upload_chunk)
+                .service(finalize_upload)
+            ).await;
+
+            let request = test::TestRequest::put()
+                .uri(&format!("/upload-chunk/{}?offset=0", upload.id))
+                .insert_header(("Authorization", format!("Bearer {}", user.token)))
+                .set_payload(data);
+            assert_eq!(test::call_service(&app, request.to_request()).await.status(), StatusCode::OK);
+
+            let finalize = || test::TestRequest::post()
+                .uri("/finalize-upload")
+                .insert_header(("Authorization", format!("Bearer {}", user.token)))
+                .set_json(json!({ "upload_id": upload.id }))
+                .to_request();
+            let (first, second) = tokio::join!(
+                test::call_service(&app, finalize()),
+                test::call_service(&app, finalize()),
+            );
+
+            assert_eq!(first.status(), StatusCode::OK);
+            assert_eq!(second.status(), StatusCode::OK);
+            let first: Value = test::read_body_json(first).await;
+            let second: Value = test::read_body_json(second).await;
+            assert!(first["message"]["media_id"].is_i64());
+            assert_eq!(first["message"]["media_id"], second["message"]["media_id"]);
+        }
+    }
+}
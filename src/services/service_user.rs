@@ -1,474 +1,512 @@
 pub mod user {
     use crate::postgresql::models::model_user::user::{
+        FullUser,
+        LoginUser,
         RegisterUser,
+        User,
+    };
+    use crate::auth::auth_session::session::{
+        issue_tokens,
+        AuthUser,
+    };
+    use crate::auth::auth_policy::policy::{
+        audit,
+        authorize,
+        Action,
+    };
+    use crate::auth::auth_password::password::{
+        hash_password,
+        verify_password,
+        Verification,
     };
     use crate::services::{
+        get_body_param,
+        get_page_request,
         get_query_param,
-        json_error,
         json_success,
-        read_body_bytes
+        read_json,
+        read_valid_json,
+        read_multipart,
+        is_multipart,
+        decode_base64,
     };
     use actix_web::{
         get,
         post,
         web,
         HttpRequest,
-        Responder,
         HttpResponse,
     };
-    use base64::Engine;
-    use base64::engine::general_purpose;
+    use actix_web::http::header::USER_AGENT;
+    use crate::error::api_error::{ApiError, ErrorCode};
     use crate::postgresql::postgresql_manager::Connect;
+    use crate::storage::storage_media::media::{store_image, MediaStorage};
+    use crate::storage::storage_staging::staging::Staging;
+    use crate::storage::storage_avatar::avatar::{
+        process_avatar,
+        AvatarError,
+        AvatarSettings,
+    };
+    use crate::postgresql::models::model_page::page::FollowersCursor;
     use crate::logger::log::{Level, log};
     use serde_json::{json, Value};
 
-    #[post("/insert-user")]
-    pub async fn insert_user(conn: web::Data<Connect>, mut payload: web::Payload) -> impl Responder {
-        let body = match read_body_bytes(&mut payload).await {
-            Ok(o) => o,
-            Err(_) => return HttpResponse::Ok().json(
-                json_error("Request overflow!")
-            )
-        };
+    const MAX_DEVICE_NAME_LENGTH: usize = 128;
 
-        let user = match serde_json::from_slice::<RegisterUser>(&body) {
-            Ok(o) => o,
-            Err(e) => {
-                log(Level::Error, "[POST][insert-user] >>> serde_json::from_slice::<RegisterUser>",
-                    &format!("Handle: {}", e)
-                );
+    #[post("/insert-user")]
+    pub async fn insert_user(conn: web::Data<Connect>, mut payload: web::Payload) -> Result<HttpResponse, ApiError> {
+        let mut user = read_valid_json::<RegisterUser>(&mut payload, "[POST][insert-user]").await?;
 
-                return HttpResponse::Ok().json(
-                    json_error("Error request!")
-                )
-            },
-        };
+        let exists = conn.exist_user_by_login(&user.login).await
+            .map_err(|e| ApiError::internal("[POST][insert-user] >>> conn.exist_user_by_login", e))?;
 
-        if let Ok(o) = conn.exist_user_by_login(&user.login).await {
-            if o {
-                return HttpResponse::Ok().json(
-                    json_error("Login already registered!")
-                );
-            }
+        if exists {
+            return Err(ApiError::new(ErrorCode::LoginTaken, "Login already registered!"));
         }
 
-        if let Err(e) = conn.insert_user(&user).await {
-            log(Level::Error, "[POST][insert-user] >>> conn.insert_user(&user)",
-                &format!("Handle: {}", e)
-            );
+        let password = user.password.clone();
+        user.password = web::block(move || hash_password(&password)).await
+            .map_err(|e| ApiError::internal("[POST][insert-user] >>> web::block(hash_password)", e))?
+            .map_err(|e| ApiError::internal("[POST][insert-user] >>> hash_password", e))?;
 
-            return HttpResponse::Ok().json(
-                json_error("Error")
-            );
-        };
+        conn.insert_user(&user).await
+            .map_err(|e| ApiError::internal("[POST][insert-user] >>> conn.insert_user(&user)", e))?;
 
-        HttpResponse::Ok().json(
+        Ok(HttpResponse::Ok().json(
             json_success("Success")
-        )
+        ))
     }
 
-    #[get("/login-user")]
-    pub async fn login_user(conn: web::Data<Connect>, req: HttpRequest) -> impl Responder {
-        let login = match get_query_param::<String>(&req, "login").await {
-            Ok(o) => o,
-            Err(e) => return HttpResponse::BadRequest().json(
-                json_error(e)
-            )
-        };
+    #[post("/login-user")]
+    pub async fn login_user(conn: web::Data<Connect>, req: HttpRequest, mut payload: web::Payload) -> Result<HttpResponse, ApiError> {
+        let LoginUser { login, password, device } = read_valid_json::<LoginUser>(&mut payload, "[POST][login-user]").await?;
 
-        let password = match get_query_param::<String>(&req, "password").await {
+        let user = match conn.get_full_user_by_login(&login).await {
             Ok(o) => o,
-            Err(e) => return HttpResponse::BadRequest().json(
-                json_error(e)
-            )
+            Err(sqlx::Error::RowNotFound) => return Err(ApiError::new(ErrorCode::InvalidCredentials, "User not found!")),
+            Err(e) => return Err(ApiError::internal("[POST][login-user] >>> conn.get_full_user_by_login", e)),
         };
 
-        if !conn.exist_user_by_login(&login).await.unwrap() {
-            return HttpResponse::Ok().json(
-                json_error("User not found!")
-            );
+        let stored = user.password.clone();
+        let verification = web::block(move || verify_password(&password, &stored)).await
+            .map_err(|e| ApiError::internal("[POST][login-user] >>> web::block(verify_password)", e))?;
+
+        match verification {
+            Verification::Invalid => return Err(ApiError::new(ErrorCode::InvalidCredentials, "User not found!")),
+            Verification::ValidLegacy => rehash_legacy_password(&conn, &user).await,
+            Verification::Valid => (),
         }
 
-        let user = match conn.get_user_by_login_and_pass(&login, &password).await {
-            Ok(o) => o,
-            Err(e) => {
-                log(Level::Error, "[GET][login-user] >>> conn.get_user_by_login_and_pass",
+        let device = device.or_else(|| req.headers()
+            .get(USER_AGENT)
+            .and_then(|o| o.to_str().ok())
+            .map(String::from)
+        )
+            .map(|o| o.chars().take(MAX_DEVICE_NAME_LENGTH).collect::<String>());
+
+        let (pair, tokens) = issue_tokens();
+
+        conn.insert_session(user.id, device.as_deref(), &tokens).await
+            .map_err(|e| ApiError::internal("[POST][login-user] >>> conn.insert_session", e))?;
+
+        let result = json!({
+            "tokens": pair,
+            "user": User::from(user),
+        });
+
+        Ok(HttpResponse::Ok().json(
+            json_success(result)
+        ))
+    }
+
+    /// Перехеширование пароля, который хранится в базе данных в открытом виде.
+    ///
+    /// Ошибки только логируются, так-как пользователь уже успешно вошел.
+    async fn rehash_legacy_password(conn: &Connect, user: &FullUser) {
+        let password = user.password.clone();
+        let hash = match web::block(move || hash_password(&password)).await {
+            Ok(Ok(o)) => o,
+            Ok(Err(e)) => {
+                log(Level::Error, "[POST][login-user] >>> hash_password",
                     &format!("Handle: {}", e)
                 );
-
-                return HttpResponse::Ok().json(
-                    json_error("User not found!")
+                return;
+            },
+            Err(e) => {
+                log(Level::Error, "[POST][login-user] >>> web::block(hash_password)",
+                    &format!("Handle: {}", e)
                 );
+                return;
             },
         };
 
-        HttpResponse::Ok().json(
-            json_success(user)
-        )
+        if let Err(e) = conn.set_user_password(user.id, &hash).await {
+            log(Level::Error, "[POST][login-user] >>> conn.set_user_password",
+                &format!("Handle: {}", e)
+            );
+        }
     }
 
     #[get("/user-info")]
-    pub async fn user_info(conn: web::Data<Connect>, req: HttpRequest) -> impl Responder {
-        let id = match get_query_param::<i32>(&req, "user_id").await {
-            Ok(o) => o,
-            Err(e) => return HttpResponse::BadRequest().json(
-                json_error(e)
-            )
-        };
+    pub async fn user_info(conn: web::Data<Connect>, req: HttpRequest) -> Result<HttpResponse, ApiError> {
+        let id = get_query_param::<i32>(&req, "user_id").await?;
 
         let user = match conn.get_user_info_by_id(id).await {
             Ok(o) => o,
-            Err(e) => {
-                log(Level::Error, "[GET][user-info] >>> conn.get_user_info_by_id",
-                    &format!("Handle: {}", e)
-                );
-
-                return HttpResponse::Ok().json(
-                    json_error("User not found!")
-                );
-            },
+            Err(sqlx::Error::RowNotFound) => return Err(ApiError::new(ErrorCode::UserNotFound, "User not found!")),
+            Err(e) => return Err(ApiError::internal("[GET][user-info] >>> conn.get_user_info_by_id", e)),
         };
 
-        HttpResponse::Ok().json(
+        Ok(HttpResponse::Ok().json(
             json_success(user)
-        )
+        ))
     }
 
     #[get("/user-count-followers")]
-    pub async fn user_count_followers(conn: web::Data<Connect>, req: HttpRequest) -> impl Responder {
-        let id = match get_query_param::<i32>(&req, "user_id").await {
-            Ok(o) => o,
-            Err(e) => return HttpResponse::Ok().json(
-                json_error(e)
-            )
-        };
+    pub async fn user_count_followers(conn: web::Data<Connect>, req: HttpRequest) -> Result<HttpResponse, ApiError> {
+        let id = get_query_param::<i32>(&req, "user_id").await?;
 
-        let count = match conn.get_user_count_followers(id).await {
-            Ok(o) => o,
-            Err(e) => {
-                log(Level::Error, "[GET][user-count-followers] >>> conn.get_user_count_followers",
-                    &format!("Handle: {}", e)
-                );
-
-                return HttpResponse::Ok().json(
-                    json_error(0)
-                );
-            },
-        };
+        let count = conn.get_user_count_followers(id).await
+            .map_err(|e| ApiError::internal("[GET][user-count-followers] >>> conn.get_user_count_followers", e))?;
 
-        HttpResponse::Ok().json(
+        Ok(HttpResponse::Ok().json(
             json_success(count)
-        )
+        ))
     }
 
     #[get("/is-user-followed")]
-    pub async fn is_user_followed(conn: web::Data<Connect>, req: HttpRequest) -> impl Responder {
-        let author_user_id = match get_query_param::<i32>(&req, "author_user_id").await {
-            Ok(o) => o,
-            Err(e) => return HttpResponse::BadRequest().json(
-                json_error(e)
-            )
-        };
+    pub async fn is_user_followed(conn: web::Data<Connect>, req: HttpRequest) -> Result<HttpResponse, ApiError> {
+        let author_user_id = get_query_param::<i32>(&req, "author_user_id").await?;
+        let follower_user_id = get_query_param::<i32>(&req, "follower_user_id").await?;
 
-        let follower_user_id = match get_query_param::<i32>(&req, "follower_user_id").await {
-            Ok(o) => o,
-            Err(e) => return HttpResponse::BadRequest().json(
-                json_error(e)
-            )
-        };
-
-        let result = match conn.is_user_followed_to_user(author_user_id, follower_user_id).await {
-            Ok(o) => o,
-            Err(e) => {
-                log(Level::Error, "[GET][is-user-followed] >>> conn.is_user_followed_to_user",
-                    &format!("Handle: {}", e)
-                );
-
-                return HttpResponse::Ok().json(
-                    json_error(false)
-                );
-            },
-        };
+        let result = conn.is_user_followed_to_user(author_user_id, follower_user_id).await
+            .map_err(|e| ApiError::internal("[GET][is-user-followed] >>> conn.is_user_followed_to_user", e))?;
 
-        HttpResponse::Ok().json(
+        Ok(HttpResponse::Ok().json(
             json_success(result)
-        )
+        ))
     }
 
-    #[deprecated(since = "0.1.2", note = "Не используется так-как есть запрос user-info")]
-    #[get("/get-profile-avatar")]
-    pub async fn get_profile_avatar(conn: web::Data<Connect>, req: HttpRequest) -> impl Responder {
-        let login = match get_query_param::<String>(&req, "login").await {
-            Ok(o) => o,
-            Err(e) => return HttpResponse::BadRequest().json(
-                json_error(e)
-            )
-        };
-
-        let (crop_avatar, full_avatar) = match conn.get_avatar_by_login(&login).await {
-            Ok(o) => o,
-            Err(sqlx::Error::RowNotFound) => return HttpResponse::Ok().json(
-                json_success("User not found!")
-            ),
-            Err(e) => {
-                log(Level::Error, "[GET][get-profile-avatar] >>> conn.get_avatar_by_login",
-                    &format!("Handle: {}", e)
-                );
-
-                return HttpResponse::Ok().json(
-                    json_success("Error request!")
-                );
-            },
+    #[post("/set-profile-avatar")]
+    pub async fn set_profile_avatar(conn: web::Data<Connect>, storage: web::Data<dyn MediaStorage>, staging: web::Data<Staging>, settings: web::Data<AvatarSettings>, auth: AuthUser, req: HttpRequest, mut payload: web::Payload) -> Result<HttpResponse, ApiError> {
+        let (value, file) = if is_multipart(&req) {
+            let mut form = read_multipart(&req, payload, &staging).await?;
+            let file = form.files.remove("avatar");
+
+            (form.fields, file)
+        } else {
+            (read_json::<Value>(&mut payload, "[POST][set-profile-avatar]").await?, None)
         };
 
-        let result = json!({
-            "crop_avatar": crop_avatar,
-            "full_avatar": full_avatar,
-        });
-
-        HttpResponse::Ok().json(
-            json_success(result)
-        )
-    }
+        // Права проверяются до декодирования картинки. В multipart/form-data `user_id` может идти после файла,
+        // поэтому файл к этому моменту уже лежит во временном каталоге
+        let user_id = avatar_owner(&value, &auth)?;
 
-    #[post("/set-profile-avatar")]
-    pub async fn set_profile_avatar(conn: web::Data<Connect>, mut payload: web::Payload) -> impl Responder {
-        let body = match read_body_bytes(&mut payload).await {
-            Ok(o) => o,
-            Err(_) => return HttpResponse::Ok().json(
-                json_error("Request overflow!")
-            )
-        };
+        let action = Action::UpdateProfile { user_id };
+        let grant = authorize(&conn, &auth, action).await?;
 
-        let value = match serde_json::from_slice::<Value>(&body) {
-            Ok(o) => o,
-            Err(e) => {
-                log(Level::Error, "[POST][set-profile-avatar] >>> serde_json::from_slice::<Value>",
-                    &format!("Handle: {}", e)
-                );
-                
-                return HttpResponse::Ok().json(
-                    json_error("Error request!")
-                )
-            },
+        // аватар все равно декодируется в памяти, поэтому файл читается целиком
+        let data = match file {
+            Some(o) => o.read().await
+                .map_err(|e| ApiError::internal("[POST][set-profile-avatar] >>> file.read", e))?,
+            None => decode_base64(&get_body_param::<String>(&value, "avatar")?, "avatar")?,
         };
 
-        let crop_avatar = general_purpose::STANDARD.decode(value["crop_avatar"].as_str().unwrap()).unwrap();
-        let full_avatar = general_purpose::STANDARD.decode(value["full_avatar"].as_str().unwrap()).unwrap();
-        let login = value["login"].as_str().unwrap();
-
-        match conn.set_avatar_by_login(login, &crop_avatar, &full_avatar).await {
-            Err(sqlx::Error::RowNotFound) | Ok(_) => {
-                HttpResponse::Ok().json(
-                    json_success("Success")
-                );
-            },
-            Err(e) => {
-                log(Level::Error, "[POST][set-profile-avatar] >>> conn.set_avatar_by_login",
-                    &format!("Handle: {}", e)
-                );
-                
-                return HttpResponse::Ok().json(
-                    json_error("Error request!")
-                )
-            },
+        let settings = *settings.get_ref();
+
+        let avatar = web::block(move || process_avatar(&data, &settings)).await
+            .map_err(|e| ApiError::internal("[POST][set-profile-avatar] >>> web::block(process_avatar)", e))?
+            .map_err(|e| match e {
+                AvatarError::TooLarge { .. } => ApiError::new(ErrorCode::ImageTooLarge, e.to_string()),
+                AvatarError::Encode(_) => ApiError::internal("[POST][set-profile-avatar] >>> process_avatar", e),
+                _ => ApiError::new(ErrorCode::InvalidImage, e.to_string()),
+            })?;
+
+        let crop_avatar = store_image(storage.get_ref(), &avatar.crop).await
+            .map_err(|e| ApiError::internal("[POST][set-profile-avatar] >>> store_image(crop)", e))?;
+        let full_avatar = store_image(storage.get_ref(), &avatar.full).await
+            .map_err(|e| ApiError::internal("[POST][set-profile-avatar] >>> store_image(full)", e))?;
+
+        match conn.set_avatar_by_id(user_id, &crop_avatar, &full_avatar).await {
+            Ok(_) => audit(&conn, &auth, action, grant, None).await,
+            Err(sqlx::Error::RowNotFound) => return Err(ApiError::new(ErrorCode::UserNotFound, "User not found!")),
+            Err(e) => return Err(ApiError::internal("[POST][set-profile-avatar] >>> conn.set_avatar_by_id", e)),
         };
 
-        HttpResponse::Ok().json(
+        Ok(HttpResponse::Ok().json(
             json_success("Success")
-        )
+        ))
     }
 
-    #[post("/following-user")]
-    pub async fn following_user(conn: web::Data<Connect>, mut payload: web::Payload) -> impl Responder {
-        let body = match read_body_bytes(&mut payload).await {
-            Ok(o) => o,
-            Err(_) => return HttpResponse::Ok().json(
-                json_error("Request overflow!")
-            )
+    /// Пользователь, которому ставится аватар: `user_id` из тела, а без него - текущий пользователь.
+    /// В multipart/form-data все поля приходят строками
+    ///
+    /// ### Возвращает:
+    /// [`ApiError`] 400, если `user_id` не число или не помещается в `i32`
+    fn avatar_owner(value: &Value, auth: &AuthUser) -> Result<i32, ApiError> {
+        let user_id = match value.get("user_id") {
+            Some(Value::Null) | None => return Ok(auth.id),
+            Some(o) => o,
         };
 
-        let value = match serde_json::from_slice::<Value>(&body) {
-            Ok(o) => o,
-            Err(e) => {
-                log(Level::Error, "[POST][following-user] >>> serde_json::from_slice::<Value>",
-                    &format!("Handle: {}", e)
-                );
+        user_id.as_i64()
+            .or_else(|| user_id.as_str().and_then(|o| o.parse::<i64>().ok()))
+            .and_then(|o| i32::try_from(o).ok())
+            .ok_or_else(|| ApiError::bad_request("Invalid type for user_id!"))
+    }
 
-                return HttpResponse::Ok().json(
-                    json_error("Error request!")
-                )
-            },
-        };
+    #[post("/following-user")]
+    pub async fn following_user(conn: web::Data<Connect>, auth: AuthUser, mut payload: web::Payload) -> Result<HttpResponse, ApiError> {
+        let value = read_json::<Value>(&mut payload, "[POST][following-user]").await?;
 
-        let author_id = value["author_id"].as_i64().unwrap() as i32;
-        let follower_id = value["follower_id"].as_i64().unwrap() as i32;
+        let author_id = get_body_param::<i32>(&value, "author_id")?;
+        let follower_id = auth.id;
 
-        let result = match conn.is_user_followed_to_user(author_id, follower_id).await {
-            Ok(o) => o,
-            Err(e) => {
-                log(Level::Error, "[POST][following-user] >>> conn.is_user_followed_to_user",
-                    &format!("Handle: {}", e)
-                );
+        authorize(&conn, &auth, Action::FollowUser { author_id }).await?;
 
-                return HttpResponse::Ok().json(
-                    json_error(false)
-                );
-            },
-        };
+        let result = conn.is_user_followed_to_user(author_id, follower_id).await
+            .map_err(|e| ApiError::internal("[POST][following-user] >>> conn.is_user_followed_to_user", e))?;
 
         if result {
-            return HttpResponse::Ok().json(
-                json_error("You already subscribed this author!")
-            );
+            return Err(ApiError::new(ErrorCode::AlreadyFollowing, "You already subscribed this author!"));
         }
 
         match conn.set_following_user(author_id, follower_id).await {
-            Ok(_) | Err(sqlx::Error::RowNotFound) => {
-                HttpResponse::Ok().json(
-                    json_success("Success")
-                );
-            },
-            Err(e) => {
-                log(Level::Error, "[POST][following-user] >>> conn.set_following_user",
-                    &format!("Handle: {}", e)
-                );
-
-                return HttpResponse::Ok().json(
-                    json_error("Error request!")
-                )
-            },
+            Ok(_) | Err(sqlx::Error::RowNotFound) => (),
+            Err(e) => return Err(ApiError::internal("[POST][following-user] >>> conn.set_following_user", e)),
         };
 
-        HttpResponse::Ok().json(
+        Ok(HttpResponse::Ok().json(
             json_success("Success")
-        )
+        ))
     }
 
     #[post("/remove-following-user")]
-    pub async fn remove_following_user(conn: web::Data<Connect>, mut payload: web::Payload) -> impl Responder {
-        let body = match read_body_bytes(&mut payload).await {
-            Ok(o) => o,
-            Err(_) => return HttpResponse::Ok().json(
-                json_error("Request overflow!")
-            )
-        };
-
-        let value = match serde_json::from_slice::<Value>(&body) {
-            Ok(o) => o,
-            Err(e) => {
-                log(Level::Error, "[POST][remove-following-user] >>> serde_json::from_slice::<Value>",
-                    &format!("Handle: {}", e)
-                );
+    pub async fn remove_following_user(conn: web::Data<Connect>, auth: AuthUser, mut payload: web::Payload) -> Result<HttpResponse, ApiError> {
+        let value = read_json::<Value>(&mut payload, "[POST][remove-following-user]").await?;
 
-                return HttpResponse::Ok().json(
-                    json_error("Error request!")
-                )
-            },
-        };
+        let author_id = get_body_param::<i32>(&value, "author_id")?;
+        let follower_id = auth.id;
 
-        let author_id = value["author_id"].as_i64().unwrap() as i32;
-        let follower_id = value["follower_id"].as_i64().unwrap() as i32;
+        authorize(&conn, &auth, Action::FollowUser { author_id }).await?;
 
-        let result = match conn.is_user_followed_to_user(author_id, follower_id).await {
-            Ok(o) => o,
-            Err(e) => {
-                log(Level::Error, "[POST][remove-following-user] >>> conn.is_user_followed_to_user",
-                    &format!("Handle: {}", e)
-                );
-
-                return HttpResponse::Ok().json(
-                    json_error(false)
-                );
-            },
-        };
+        let result = conn.is_user_followed_to_user(author_id, follower_id).await
+            .map_err(|e| ApiError::internal("[POST][remove-following-user] >>> conn.is_user_followed_to_user", e))?;
 
         if !result {
-            return HttpResponse::Ok().json(
-                json_error("You are not subscribed this author!")
-            );
+            return Err(ApiError::new(ErrorCode::NotFollowing, "You are not subscribed this author!"));
         }
 
         match conn.remove_following_user(author_id, follower_id).await {
-            Ok(_) | Err(sqlx::Error::RowNotFound) => {
-                HttpResponse::Ok().json(
-                    json_success("Success")
-                );
-            },
-            Err(e) => {
-                log(Level::Error, "[POST][following-user] >>> conn.set_following_user",
-                    &format!("Handle: {}", e)
-                );
-
-                return HttpResponse::Ok().json(
-                    json_error("Error request!")
-                )
-            },
+            Ok(_) | Err(sqlx::Error::RowNotFound) => (),
+            Err(e) => return Err(ApiError::internal("[POST][remove-following-user] >>> conn.remove_following_user", e)),
         };
 
-        HttpResponse::Ok().json(
+        Ok(HttpResponse::Ok().json(
             json_success("Success")
-        )
+        ))
     }
 
     #[get("/get-popular-users")]
-    pub async fn get_popular_users(conn: web::Data<Connect>, req: HttpRequest) -> impl Responder {
-        let user_id = match get_query_param::<i32>(&req, "user_id").await {
-            Ok(o) => o,
-            Err(e) => return HttpResponse::BadRequest().json(
-                json_error(e)
-            )
-        };
+    pub async fn get_popular_users(conn: web::Data<Connect>, req: HttpRequest) -> Result<HttpResponse, ApiError> {
+        let user_id = get_query_param::<i32>(&req, "user_id").await?;
 
-        let result = match conn.get_popular_users(user_id).await {
-            Ok(o) => o,
-            Err(e) => {
-                log(Level::Error, "[GET][get-popular-users] >>> conn.get_popular_users",
-                    &format!("Handle: {}", e)
-                );
+        let page = get_page_request::<FollowersCursor>(&req).await?;
 
-                return HttpResponse::Ok().json(
-                    json_error(false)
-                );
-            },
-        };
+        let result = conn.get_popular_users(user_id, &page).await
+            .map_err(|e| ApiError::internal("[GET][get-popular-users] >>> conn.get_popular_users", e))?;
 
-        HttpResponse::Ok().json(
+        Ok(HttpResponse::Ok().json(
             json_success(result)
-        )
+        ))
     }
 
     #[get("/find-user-by-key-words")]
-    pub async fn find_user_by_key_words(conn: web::Data<Connect>, req: HttpRequest) -> impl Responder {
-        let user_id = match get_query_param::<i32>(&req, "user_id").await {
-            Ok(o) => o,
-            Err(e) => return HttpResponse::Ok().json(
-                json_error(e)
-            )
-        };
+    pub async fn find_user_by_key_words(conn: web::Data<Connect>, req: HttpRequest) -> Result<HttpResponse, ApiError> {
+        let user_id = get_query_param::<i32>(&req, "user_id").await?;
+        let words = get_query_param::<String>(&req, "words").await?;
 
-        let words = match get_query_param::<String>(&req, "words").await {
-            Ok(o) => o,
-            Err(e) => return HttpResponse::Ok().json(
-                json_error(e)
-            )
-        };
+        let users = conn.find_user_by_key_words(user_id, &words).await
+            .map_err(|e| ApiError::internal("[GET][find-user-by-key-words] >>> conn.find_user_by_key_words", e))?;
 
-        let count = match conn.find_user_by_key_words(user_id, &words).await {
-            Ok(o) => o,
-            Err(e) => {
-                log(Level::Error, "[GET][find-user-by-key-words] >>> conn.find_user_by_key_words",
-                    &format!("Handle: {}", e)
-                );
+        Ok(HttpResponse::Ok().json(
+            json_success(users)
+        ))
+    }
 
-                return HttpResponse::Ok().json(
-                    json_error(0)
-                );
-            },
-        };
+    #[cfg(test)]
+    mod tests {
+        use std::io::Cursor;
+        use std::sync::Arc;
+        use actix_web::{test, web, App};
+        use actix_web::dev::ServiceResponse;
+        use actix_web::http::StatusCode;
+        use base64::Engine;
+        use base64::engine::general_purpose;
+        use image::{ImageOutputFormat, RgbImage};
+        use serde_json::{json, Value};
+        use sqlx::Row;
+        use super::set_profile_avatar;
+        use crate::postgresql::postgresql_manager::Connect;
+        use crate::postgresql::models::model_user::user::Role;
+        use crate::storage::storage_avatar::avatar::AvatarSettings;
+        use crate::storage::storage_local::local::LocalStorage;
+        use crate::storage::storage_media::media::MediaStorage;
+        use crate::storage::storage_staging::staging::Staging;
+        use crate::testing::fixtures::{connect, insert_user};
+
+        const BOUNDARY: &str = "avatar-test-boundary";
+
+        async fn call(conn: &Connect, request: test::TestRequest) -> ServiceResponse {
+            let root = std::env::temp_dir().join("xamarin-news-api-test-media");
+            let storage: Arc<dyn MediaStorage> = Arc::new(LocalStorage::new(&root));
+
+            let app = test::init_service(App::new()
+                .app_data(web::Data::new(conn.clone()))
+                .app_data(web::Data::from(storage))
+                .app_data(web::Data::new(Staging::new(root.join(".staging"))))
+                .app_data(web::Data::new(AvatarSettings::from_env()))
+                .service(set_profile_avatar)
+            ).await;
+
+            test::call_service(&app, request.to_request()).await
+        }
 
-        HttpResponse::Ok().json(
-            json_success(count)
-        )
+        fn avatar() -> Vec<u8> {
+            let mut data = Cursor::new(Vec::new());
+            RgbImage::new(8, 8).write_to(&mut data, ImageOutputFormat::Png).unwrap();
+            data.into_inner()
+        }
+
+        fn multipart_body(user_id: i32, avatar: &[u8]) -> Vec<u8> {
+            let mut body = format!(
+                "--{b}\r\nContent-Disposition: form-data; name=\"user_id\"\r\n\r\n{user_id}\r\n\
+                --{b}\r\nContent-Disposition: form-data; name=\"avatar\"; filename=\"avatar.png\"\r\nContent-Type: image/png\r\n\r\n",
+                b = BOUNDARY,
+                user_id = user_id,
+            ).into_bytes();
+            body.extend_from_slice(avatar);
+            body.extend_from_slice(format!("\r\n--{}--\r\n", BOUNDARY).as_bytes());
+            body
+        }
+
+        async fn avatar_id(conn: &Connect, user_id: i32) -> Option<i32> {
+            sqlx::query("SELECT crop_avatar_id FROM users WHERE id = $1;")
+                .bind(user_id)
+                .fetch_one(&conn.pool).await
+                .unwrap()
+                .get("crop_avatar_id")
+        }
+
+        #[actix_web::test]
+        async fn user_can_not_set_another_avatar_with_json() {
+            let Some(conn) = connect().await else { return };
+            let owner = insert_user(&conn, Role::User).await;
+            let other = insert_user(&conn, Role::User).await;
+
+            let request = test::TestRequest::post()
+                .uri("/set-profile-avatar")
+                .insert_header(("Authorization", format!("Bearer {}", other.token)))
+                .set_json(json!({
+                    "user_id": owner.auth.id,
+                    "avatar": general_purpose::STANDARD.encode(avatar()),
+                }));
+            let response = call(&conn, request).await;
+
+            assert_eq!(response.status(), StatusCode::FORBIDDEN);
+            let body: Value = test::read_body_json(response).await;
+            assert_eq!(body["code"], "forbidden");
+            assert_eq!(avatar_id(&conn, owner.auth.id).await, None);
+        }
+
+        #[actix_web::test]
+        async fn user_can_not_set_another_avatar_with_multipart() {
+            let Some(conn) = connect().await else { return };
+            let owner = insert_user(&conn, Role::User).await;
+            let other = insert_user(&conn, Role::User).await;
+
+            let request = test::TestRequest::post()
+                .uri("/set-profile-avatar")
+                .insert_header(("Authorization", format!("Bearer {}", other.token)))
+                .insert_header(("Content-Type", format!("multipart/form-data; boundary={}", BOUNDARY)))
+                .set_payload(multipart_body(owner.auth.id, &avatar()));
+            let response = call(&conn, request).await;
+
+            assert_eq!(response.status(), StatusCode::FORBIDDEN);
+            assert_eq!(avatar_id(&conn, owner.auth.id).await, None);
+        }
+
+        #[actix_web::test]
+        async fn forbidden_avatar_is_not_decoded() {
+            let Some(conn) = connect().await else { return };
+            let owner = insert_user(&conn, Role::User).await;
+            let other = insert_user(&conn, Role::User).await;
+
+            let request = test::TestRequest::post()
+                .uri("/set-profile-avatar")
+                .insert_header(("Authorization", format!("Bearer {}", other.token)))
+                .set_json(json!({
+                    "user_id": owner.auth.id,
+                    "avatar": "not base64!",
+                }));
+            let response = call(&conn, request).await;
+
+            assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        }
+
+        #[actix_web::test]
+        async fn out_of_range_user_id_is_rejected() {
+            let Some(conn) = connect().await else { return };
+            let owner = insert_user(&conn, Role::User).await;
+
+            // Без проверки диапазона `1 << 32 | id` превратился бы в `id`
+            for user_id in [json!((1_i64 << 32) | owner.auth.id as i64), json!(((1_i64 << 32) | owner.auth.id as i64).to_string()), json!("me")] {
+                let request = test::TestRequest::post()
+                    .uri("/set-profile-avatar")
+                    .insert_header(("Authorization", format!("Bearer {}", owner.token)))
+                    .set_json(json!({
+                        "user_id": user_id,
+                        "avatar": general_purpose::STANDARD.encode(avatar()),
+                    }));
+                let response = call(&conn, request).await;
+
+                assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", user_id);
+            }
+            assert_eq!(avatar_id(&conn, owner.auth.id).await, None);
+        }
+
+        #[actix_web::test]
+        async fn user_sets_own_avatar() {
+            let Some(conn) = connect().await else { return };
+            let owner = insert_user(&conn, Role::User).await;
+
+            let request = test::TestRequest::post()
+                .uri("/set-profile-avatar")
+                .insert_header(("Authorization", format!("Bearer {}", owner.token)))
+                .set_json(json!({ "avatar": general_purpose::STANDARD.encode(avatar()) }));
+            let response = call(&conn, request).await;
+
+            assert_eq!(response.status(), StatusCode::OK);
+            assert!(avatar_id(&conn, owner.auth.id).await.is_some());
+        }
+
+        #[actix_web::test]
+        async fn admin_can_not_set_avatar_of_missing_user() {
+            let Some(conn) = connect().await else { return };
+            let admin = insert_user(&conn, Role::Admin).await;
+
+            let request = test::TestRequest::post()
+                .uri("/set-profile-avatar")
+                .insert_header(("Authorization", format!("Bearer {}", admin.token)))
+                .set_json(json!({
+                    "user_id": i32::MAX,
+                    "avatar": general_purpose::STANDARD.encode(avatar()),
+                }));
+            let response = call(&conn, request).await;
+
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+            let body: Value = test::read_body_json(response).await;
+            assert_eq!(body["code"], "user_not_found");
+        }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,5 @@
+pub mod storage_media;
+pub mod storage_local;
+pub mod storage_s3;
+pub mod storage_staging;
+pub mod storage_avatar;
@@ -0,0 +1,167 @@
+pub mod avatar {
+    use std::fmt;
+    use std::io::Cursor;
+    use image::{DynamicImage, ImageError, ImageFormat, ImageOutputFormat};
+    use image::imageops::FilterType;
+    use image::io::{Limits, Reader};
+    use crate::postgresql::models::model_image::image::sniff_content_type;
+
+    /// Сторона квадратной миниатюры, если не задано `AVATAR_CROP_SIZE`
+    const DEFAULT_AVATAR_CROP_SIZE: u32 = 256;
+    /// Наибольшая сторона полноразмерного аватара, если не задано `AVATAR_FULL_SIZE`
+    const DEFAULT_AVATAR_FULL_SIZE: u32 = 1024;
+    /// Наибольшая сторона загружаемого изображения, если не задано `AVATAR_MAX_DIMENSION`
+    const DEFAULT_AVATAR_MAX_DIMENSION: u32 = 8192;
+    /// Качество JPEG для изображений без прозрачности
+    const JPEG_QUALITY: u8 = 90;
+
+    /// Размеры аватаров, задаются при запуске сервера
+    #[derive(Clone, Copy, Debug)]
+    pub struct AvatarSettings {
+        /// Сторона квадратной миниатюры (`crop_avatar`)
+        pub crop_size: u32,
+        /// Наибольшая сторона полноразмерного аватара (`full_avatar`)
+        pub full_size: u32,
+        /// Наибольшая допустимая сторона загружаемого изображения
+        pub max_dimension: u32,
+    }
+
+    impl AvatarSettings {
+        /// Читает `AVATAR_CROP_SIZE`, `AVATAR_FULL_SIZE` и `AVATAR_MAX_DIMENSION`.
+        /// Некорректные и нулевые значения заменяются значениями по умолчанию
+        pub fn from_env() -> AvatarSettings {
+            AvatarSettings {
+                crop_size: env_size("AVATAR_CROP_SIZE", DEFAULT_AVATAR_CROP_SIZE),
+                full_size: env_size("AVATAR_FULL_SIZE", DEFAULT_AVATAR_FULL_SIZE),
+                max_dimension: env_size("AVATAR_MAX_DIMENSION", DEFAULT_AVATAR_MAX_DIMENSION),
+            }
+        }
+    }
+
+    fn env_size(key: &str, default: u32) -> u32 {
+        std::env::var(key)
+            .ok()
+            .and_then(|o| o.parse::<u32>().ok())
+            .filter(|o| *o > 0)
+            .unwrap_or(default)
+    }
+
+    #[derive(Debug)]
+    pub enum AvatarError {
+        /// Данные не являются изображением поддерживаемого формата
+        Unsupported,
+        /// Изображение больше допустимого размера
+        TooLarge { width: u32, height: u32, max: u32 },
+        Decode(ImageError),
+        Encode(ImageError),
+    }
+
+    impl fmt::Display for AvatarError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                AvatarError::Unsupported => write!(f, "Unsupported image format, expected JPEG, PNG, GIF or WebP"),
+                AvatarError::TooLarge { width, height, max } => {
+                    write!(f, "Image is too large ({}x{}), max side is {}", width, height, max)
+                },
+                AvatarError::Decode(e) => write!(f, "Invalid image: {}", e),
+                AvatarError::Encode(e) => write!(f, "Encode image: {}", e),
+            }
+        }
+    }
+
+    impl std::error::Error for AvatarError {}
+
+    /// Готовые варианты аватара
+    pub struct ProcessedAvatar {
+        /// Квадратная миниатюра `crop_size` x `crop_size`
+        pub crop: Vec<u8>,
+        /// Изображение, уменьшенное до `full_size` по наибольшей стороне
+        pub full: Vec<u8>,
+    }
+
+    /// Проверяет загруженное изображение и строит из него оба варианта аватара.
+    /// Изображение перекодируется, поэтому EXIF и прочие метаданные не сохраняются,
+    /// ориентация из EXIF применяется к пикселям заранее.
+    /// ### Принимает:
+    /// Исходные байты изображения, настройки размеров
+    ///
+    /// ### Возвращает:
+    /// Если [`Ok`], то [`ProcessedAvatar`] (PNG, если есть прозрачность, иначе JPEG)
+    pub fn process_avatar(data: &[u8], settings: &AvatarSettings) -> Result<ProcessedAvatar, AvatarError> {
+        let format = match sniff_content_type(data) {
+            "image/jpeg" => ImageFormat::Jpeg,
+            "image/png" => ImageFormat::Png,
+            "image/gif" => ImageFormat::Gif,
+            "image/webp" => ImageFormat::WebP,
+            _ => return Err(AvatarError::Unsupported),
+        };
+
+        let (width, height) = Reader::with_format(Cursor::new(data), format)
+            .into_dimensions()
+            .map_err(AvatarError::Decode)?;
+
+        if width > settings.max_dimension || height > settings.max_dimension {
+            return Err(AvatarError::TooLarge { width, height, max: settings.max_dimension });
+        }
+
+        let mut limits = Limits::default();
+        limits.max_image_width = Some(settings.max_dimension);
+        limits.max_image_height = Some(settings.max_dimension);
+
+        let mut reader = Reader::with_format(Cursor::new(data), format);
+        reader.limits(limits);
+
+        let image = reader.decode().map_err(AvatarError::Decode)?;
+        let image = apply_orientation(image, read_orientation(data));
+
+        let full = if image.width() > settings.full_size || image.height() > settings.full_size {
+            image.resize(settings.full_size, settings.full_size, FilterType::Lanczos3)
+        } else {
+            image.clone()
+        };
+
+        let crop = image.resize_to_fill(settings.crop_size, settings.crop_size, FilterType::Lanczos3);
+
+        Ok(ProcessedAvatar {
+            crop: encode(crop)?,
+            full: encode(full)?,
+        })
+    }
+
+    /// Значение тега EXIF Orientation (1 - без поворота)
+    fn read_orientation(data: &[u8]) -> u32 {
+        exif::Reader::new()
+            .read_from_container(&mut Cursor::new(data))
+            .ok()
+            .and_then(|o| {
+                o.get_field(exif::Tag::Orientation, exif::In::PRIMARY)
+                    .and_then(|field| field.value.get_uint(0))
+            })
+            .unwrap_or(1)
+    }
+
+    fn apply_orientation(image: DynamicImage, orientation: u32) -> DynamicImage {
+        match orientation {
+            2 => image.fliph(),
+            3 => image.rotate180(),
+            4 => image.flipv(),
+            5 => image.rotate90().fliph(),
+            6 => image.rotate90(),
+            7 => image.rotate270().fliph(),
+            8 => image.rotate270(),
+            _ => image,
+        }
+    }
+
+    fn encode(image: DynamicImage) -> Result<Vec<u8>, AvatarError> {
+        let mut buffer = Cursor::new(Vec::new());
+
+        if image.color().has_alpha() {
+            image.write_to(&mut buffer, ImageOutputFormat::Png)
+        } else {
+            DynamicImage::ImageRgb8(image.to_rgb8()).write_to(&mut buffer, ImageOutputFormat::Jpeg(JPEG_QUALITY))
+        }.map_err(AvatarError::Encode)?;
+
+        Ok(buffer.into_inner())
+    }
+}
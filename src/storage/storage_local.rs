@@ -0,0 +1,180 @@
+pub mod local {
+    use std::io::ErrorKind;
+    use std::path::{Path, PathBuf};
+    use async_trait::async_trait;
+    use rand::Rng;
+    use crate::storage::storage_media::media::{
+        is_valid_key,
+        MediaError,
+        MediaStorage,
+    };
+
+    /// Хранилище в локальном каталоге. Файл `ab12...` лежит в `{root}/ab/ab12...`
+    pub struct LocalStorage {
+        root: PathBuf,
+    }
+
+    impl LocalStorage {
+        pub fn new<P: Into<PathBuf>>(root: P) -> LocalStorage {
+            LocalStorage { root: root.into() }
+        }
+
+        fn path(&self, key: &str) -> Result<PathBuf, MediaError> {
+            if !is_valid_key(key) {
+                return Err(MediaError::Config(format!("Invalid media key: {}", key)));
+            }
+
+            Ok(self.root.join(&key[..2]).join(key))
+        }
+
+        /// Путь к файлу, если его еще нет в хранилище. Каталог для файла создается
+        async fn new_path(&self, key: &str) -> Result<Option<PathBuf>, MediaError> {
+            let path = self.path(key)?;
+
+            if tokio::fs::metadata(&path).await.is_ok() {
+                return Ok(None);
+            }
+
+            if let Some(dir) = path.parent() {
+                tokio::fs::create_dir_all(dir).await?;
+            }
+
+            Ok(Some(path))
+        }
+
+        /// Переименование готового временного файла в файл хранилища
+        async fn commit(temp: &Path, path: &Path) -> Result<(), MediaError> {
+            if let Err(e) = tokio::fs::rename(temp, path).await {
+                let _ = tokio::fs::remove_file(temp).await;
+                return Err(e.into());
+            }
+
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl MediaStorage for LocalStorage {
+        async fn put(&self, key: &str, data: &[u8]) -> Result<(), MediaError> {
+            let Some(path) = self.new_path(key).await? else { return Ok(()) };
+
+            // Сначала пишем во временный файл, чтобы никто не прочитал файл наполовину
+            let temp = path.with_extension(format!("tmp{}", rand::thread_rng().gen::<u32>()));
+            tokio::fs::write(&temp, data).await?;
+
+            LocalStorage::commit(&temp, &path).await
+        }
+
+        async fn put_file(&self, key: &str, source: &Path, _size: u64) -> Result<(), MediaError> {
+            let Some(path) = self.new_path(key).await? else { return Ok(()) };
+
+            let temp = path.with_extension(format!("tmp{}", rand::thread_rng().gen::<u32>()));
+            if let Err(e) = tokio::fs::copy(source, &temp).await {
+                let _ = tokio::fs::remove_file(&temp).await;
+                return Err(e.into());
+            }
+
+            LocalStorage::commit(&temp, &path).await
+        }
+
+        async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, MediaError> {
+            match tokio::fs::read(self.path(key)?).await {
+                Ok(o) => Ok(Some(o)),
+                Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+                Err(e) => Err(e.into()),
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use rand::Rng;
+        use super::*;
+        use crate::postgresql::models::model_image::image::image_hash;
+
+        /// Хранилище в новом временном каталоге, который удаляется после теста
+        struct TempStorage {
+            storage: LocalStorage,
+        }
+
+        impl TempStorage {
+            fn new() -> TempStorage {
+                let root = std::env::temp_dir().join(format!("xamarin-news-api-test-local-{:016x}", rand::thread_rng().gen::<u64>()));
+                TempStorage { storage: LocalStorage::new(root) }
+            }
+
+            /// Все файлы каталога хранилища, включая вложенные
+            fn files(&self) -> Vec<PathBuf> {
+                let mut files = Vec::new();
+                for dir in std::fs::read_dir(&self.storage.root).unwrap() {
+                    for file in std::fs::read_dir(dir.unwrap().path()).unwrap() {
+                        files.push(file.unwrap().path());
+                    }
+                }
+                files
+            }
+        }
+
+        impl Drop for TempStorage {
+            fn drop(&mut self) {
+                let _ = std::fs::remove_dir_all(&self.storage.root);
+            }
+        }
+
+        #[actix_web::test]
+        async fn put_and_get_round_trip() {
+            let temp = TempStorage::new();
+            let key = image_hash(b"round trip");
+
+            assert_eq!(temp.storage.get(&key).await.unwrap(), None);
+
+            temp.storage.put(&key, b"round trip").await.unwrap();
+            assert_eq!(temp.storage.get(&key).await.unwrap(), Some(b"round trip".to_vec()));
+            assert_eq!(temp.files(), vec![temp.storage.root.join(&key[..2]).join(&key)]);
+        }
+
+        #[actix_web::test]
+        async fn same_key_is_stored_once() {
+            let temp = TempStorage::new();
+            let key = image_hash(b"first");
+
+            temp.storage.put(&key, b"first").await.unwrap();
+            temp.storage.put(&key, b"second").await.unwrap();
+
+            let source = temp.storage.root.join("source");
+            std::fs::create_dir_all(&temp.storage.root).unwrap();
+            std::fs::write(&source, b"third").unwrap();
+            temp.storage.put_file(&key, &source, 5).await.unwrap();
+            std::fs::remove_file(&source).unwrap();
+
+            assert_eq!(temp.storage.get(&key).await.unwrap(), Some(b"first".to_vec()));
+            assert_eq!(temp.files().len(), 1);
+        }
+
+        #[actix_web::test]
+        async fn put_file_copies_source() {
+            let temp = TempStorage::new();
+            let key = image_hash(b"from file");
+            let source = std::env::temp_dir().join(format!("xamarin-news-api-test-source-{}", key));
+            std::fs::write(&source, b"from file").unwrap();
+
+            let result = temp.storage.put_file(&key, &source, 9).await;
+            assert!(source.exists());
+            std::fs::remove_file(&source).unwrap();
+            result.unwrap();
+
+            assert_eq!(temp.storage.get(&key).await.unwrap(), Some(b"from file".to_vec()));
+        }
+
+        #[actix_web::test]
+        async fn invalid_key_is_rejected() {
+            let temp = TempStorage::new();
+
+            for key in ["../../etc/passwd", "", &"A".repeat(64), &format!("{}/", "a".repeat(63))] {
+                assert!(matches!(temp.storage.put(key, b"data").await, Err(MediaError::Config(_))), "{}", key);
+                assert!(matches!(temp.storage.get(key).await, Err(MediaError::Config(_))), "{}", key);
+            }
+            assert!(!temp.storage.root.exists());
+        }
+    }
+}
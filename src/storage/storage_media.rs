@@ -0,0 +1,161 @@
+pub mod media {
+    use std::error::Error;
+    use std::fmt;
+    use std::path::Path;
+    use std::sync::Arc;
+    use async_trait::async_trait;
+    use crate::postgresql::postgresql_manager::Connect;
+    use crate::postgresql::models::model_image::image::{
+        image_hash,
+        sniff_content_type,
+        StoredImage,
+    };
+    use crate::storage::storage_local::local::LocalStorage;
+    use crate::storage::storage_s3::s3::S3Storage;
+    use crate::storage::storage_staging::staging::StagedFile;
+
+    /// Каталог локального хранилища, если не задано `MEDIA_LOCAL_PATH`
+    const DEFAULT_LOCAL_PATH: &str = "media";
+
+    /// Сколько картинок переносится из базы данных за один запрос
+    const MIGRATE_BATCH_SIZE: i64 = 50;
+
+    #[derive(Debug)]
+    pub enum MediaError {
+        Io(std::io::Error),
+        Http(String),
+        Database(sqlx::Error),
+        Config(String),
+    }
+
+    impl fmt::Display for MediaError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                MediaError::Io(e) => write!(f, "io: {}", e),
+                MediaError::Http(e) => write!(f, "http: {}", e),
+                MediaError::Database(e) => write!(f, "database: {}", e),
+                MediaError::Config(e) => write!(f, "config: {}", e),
+            }
+        }
+    }
+
+    impl Error for MediaError {}
+
+    impl From<std::io::Error> for MediaError {
+        fn from(e: std::io::Error) -> Self {
+            MediaError::Io(e)
+        }
+    }
+
+    impl From<sqlx::Error> for MediaError {
+        fn from(e: sqlx::Error) -> Self {
+            MediaError::Database(e)
+        }
+    }
+
+    /// Хранилище файлов.
+    ///
+    /// Ключ файла - sha256 содержимого в hex (см. [`image_hash`]), поэтому одинаковые файлы хранятся один раз
+    #[async_trait]
+    pub trait MediaStorage: Send + Sync {
+        /// Сохранение файла. Если файл с таким ключом уже есть, то он не перезаписывается
+        async fn put(&self, key: &str, data: &[u8]) -> Result<(), MediaError>;
+
+        /// Сохранение файла с диска без чтения его в память. `size` - размер файла в байтах
+        async fn put_file(&self, key: &str, path: &Path, size: u64) -> Result<(), MediaError>;
+
+        /// Получение файла. Если файла нет, то вернется `None`
+        async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, MediaError>;
+    }
+
+    /// Ключ должен быть sha256 в hex, иначе его нельзя безопасно использовать как имя файла
+    pub fn is_valid_key(key: &str) -> bool {
+        key.len() == 64 && key.bytes().all(|o| matches!(o, b'0'..=b'9' | b'a'..=b'f'))
+    }
+
+    /// Каталог локального хранилища из `MEDIA_LOCAL_PATH`
+    pub fn local_path_from_env() -> String {
+        std::env::var("MEDIA_LOCAL_PATH").unwrap_or_else(|_| DEFAULT_LOCAL_PATH.to_owned())
+    }
+
+    /// Создание хранилища из переменных окружения.
+    ///
+    /// `MEDIA_STORAGE` - `local` (по умолчанию) или `s3`.
+    /// Для `local` - `MEDIA_LOCAL_PATH`, для `s3` - `S3_ENDPOINT`, `S3_BUCKET`, `S3_REGION`, `S3_ACCESS_KEY`, `S3_SECRET_KEY`
+    pub fn storage_from_env() -> Result<Arc<dyn MediaStorage>, MediaError> {
+        let kind = std::env::var("MEDIA_STORAGE").unwrap_or_else(|_| "local".to_owned());
+
+        match kind.as_str() {
+            "local" => Ok(Arc::new(LocalStorage::new(local_path_from_env()))),
+            "s3" => {
+                let var = |key: &str| std::env::var(key)
+                    .map_err(|_| MediaError::Config(format!("{} is invalid!", key)));
+
+                let storage = S3Storage::new(
+                    &var("S3_ENDPOINT")?,
+                    &var("S3_BUCKET")?,
+                    &std::env::var("S3_REGION").unwrap_or_else(|_| "us-east-1".to_owned()),
+                    &var("S3_ACCESS_KEY")?,
+                    &var("S3_SECRET_KEY")?,
+                )?;
+                Ok(Arc::new(storage))
+            },
+            _ => Err(MediaError::Config(format!("Unknown MEDIA_STORAGE: {}", kind))),
+        }
+    }
+
+    /// Сохранение картинки в хранилище
+    ///
+    /// ### Возвращает:
+    /// Если [`Ok`], то [`StoredImage`] - ключ и `Content-Type`, которые нужно записать в `images`
+    pub async fn store_image(storage: &dyn MediaStorage, data: &[u8]) -> Result<StoredImage, MediaError> {
+        let image = StoredImage {
+            hash: image_hash(data),
+            content_type: sniff_content_type(data).to_owned(),
+        };
+
+        storage.put(&image.hash, data).await?;
+
+        Ok(image)
+    }
+
+    /// Сохранение картинки из временного файла в хранилище. Хеш и `Content-Type` уже посчитаны при записи файла
+    ///
+    /// ### Возвращает:
+    /// Если [`Ok`], то [`StoredImage`] - ключ и `Content-Type`, которые нужно записать в `images`
+    pub async fn store_staged_image(storage: &dyn MediaStorage, file: &StagedFile) -> Result<StoredImage, MediaError> {
+        let image = StoredImage {
+            hash: file.hash().to_owned(),
+            content_type: file.content_type().to_owned(),
+        };
+
+        storage.put_file(&image.hash, file.path(), file.size()).await?;
+
+        Ok(image)
+    }
+
+    /// Перенос картинок, которые еще лежат в базе данных (`images.data`), в хранилище
+    ///
+    /// ### Возвращает:
+    /// Если [`Ok`], то количество перенесенных картинок. При ошибки [`MediaError`]
+    pub async fn migrate_database_media(conn: &Connect, storage: &dyn MediaStorage) -> Result<u64, MediaError> {
+        let mut migrated = 0;
+
+        loop {
+            let images = conn.get_images_with_data(MIGRATE_BATCH_SIZE).await?;
+
+            if images.is_empty() {
+                return Ok(migrated);
+            }
+
+            for image in images {
+                if let Some(data) = &image.data {
+                    storage.put(&image.hash, data).await?;
+                }
+
+                conn.clear_image_data(image.id).await?;
+                migrated += 1;
+            }
+        }
+    }
+}
@@ -0,0 +1,411 @@
+pub mod staging {
+    use std::collections::HashMap;
+    use std::io;
+    use std::path::{Path, PathBuf};
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, SystemTime};
+    use rand::Rng;
+    use sha2::{Digest, Sha256};
+    use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+    use tokio::sync::OwnedMutexGuard;
+    use crate::postgresql::models::model_image::image::sniff_content_type;
+    use crate::storage::storage_media::media::local_path_from_env;
+
+    /// Сколько первых байт файла нужно, чтобы определить `Content-Type`
+    const SNIFF_LENGTH: usize = 16;
+
+    /// Размер буфера при чтении файла загрузки
+    const READ_BUFFER_SIZE: usize = 64 * 1024;
+
+    /// Каталог для файлов, которые еще не сохранены в хранилище.
+    /// Файлы пишутся сюда по мере поступления, поэтому тело запроса не держится в памяти целиком
+    pub struct Staging {
+        root: PathBuf,
+        upload_locks: Mutex<HashMap<i32, Arc<tokio::sync::Mutex<()>>>>,
+    }
+
+    impl Staging {
+        pub fn new<P: Into<PathBuf>>(root: P) -> Staging {
+            Staging {
+                root: root.into(),
+                upload_locks: Mutex::new(HashMap::new()),
+            }
+        }
+
+        /// Каталог из `MEDIA_STAGING_PATH`, по умолчанию `.staging` внутри `MEDIA_LOCAL_PATH`
+        pub fn from_env() -> Staging {
+            match std::env::var("MEDIA_STAGING_PATH") {
+                Ok(o) => Staging::new(o),
+                Err(_) => Staging::new(Path::new(&local_path_from_env()).join(".staging")),
+            }
+        }
+
+        /// Создание временного файла со случайным именем
+        pub async fn create(&self) -> io::Result<StagingWriter> {
+            tokio::fs::create_dir_all(&self.root).await?;
+
+            let path = self.root.join(format!("{:016x}.part", rand::thread_rng().gen::<u64>()));
+            let file = tokio::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path).await?;
+
+            Ok(StagingWriter {
+                staged: StagedFile::new(path, true),
+                file,
+                fingerprint: Fingerprint::new(),
+            })
+        }
+
+        /// Файл докачиваемой загрузки. Части из разных запросов пишутся в один файл
+        pub fn upload_path(&self, upload_id: i32) -> PathBuf {
+            self.root.join(format!("upload-{}.part", upload_id))
+        }
+
+        /// Блокировка файла загрузки до освобождения guard. Части и завершение одной загрузки выполняются
+        /// по очереди, поэтому не пишут в файл одновременно и не читают недописанный файл.
+        /// Строка загрузки в базе данных при этом не блокируется
+        pub async fn lock_upload(&self, upload_id: i32) -> OwnedMutexGuard<()> {
+            let lock = {
+                let mut locks = self.upload_locks.lock().unwrap();
+                // блокировки, которые никто не держит и не ждет, больше не нужны
+                locks.retain(|_, o| Arc::strong_count(o) > 1);
+                locks.entry(upload_id).or_default().clone()
+            };
+
+            lock.lock_owned().await
+        }
+
+        /// Запись части загрузки с указанного смещения. Все, что было записано после смещения, отбрасывается,
+        /// поэтому часть, которая не попала в базу данных, при повторе перезаписывается
+        pub async fn write_upload_chunk(&self, upload_id: i32, offset: u64, data: &[u8]) -> io::Result<()> {
+            tokio::fs::create_dir_all(&self.root).await?;
+
+            let mut file = tokio::fs::OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .open(self.upload_path(upload_id)).await?;
+
+            if file.metadata().await?.len() < offset {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof,
+                    format!("Upload {} file is shorter than offset {}", upload_id, offset)
+                ));
+            }
+
+            file.set_len(offset).await?;
+            file.seek(io::SeekFrom::Start(offset)).await?;
+            file.write_all(data).await?;
+            file.flush().await?;
+
+            Ok(())
+        }
+
+        /// Файл полученной загрузки. sha256 и начало файла считаются при чтении по частям.
+        /// Файл остается на месте, пока его не удалит [`Staging::remove_upload`]
+        ///
+        /// ### Принимает:
+        /// ID загрузки, ожидаемый размер файла
+        pub async fn open_upload(&self, upload_id: i32, size: u64) -> io::Result<StagedFile> {
+            let path = self.upload_path(upload_id);
+            let mut file = tokio::fs::File::open(&path).await?;
+
+            let mut fingerprint = Fingerprint::new();
+            let mut buffer = vec![0; READ_BUFFER_SIZE];
+
+            loop {
+                let read = file.read(&mut buffer).await?;
+
+                if read == 0 {
+                    break;
+                }
+
+                fingerprint.update(&buffer[..read]);
+            }
+
+            if fingerprint.size != size {
+                return Err(io::Error::new(io::ErrorKind::InvalidData,
+                    format!("Upload {} file has {} of {} bytes", upload_id, fingerprint.size, size)
+                ));
+            }
+
+            let mut staged = StagedFile::new(path, false);
+            fingerprint.finish(&mut staged);
+
+            Ok(staged)
+        }
+
+        /// Удаление файла загрузки. Если файла уже нет, то это не ошибка
+        pub async fn remove_upload(&self, upload_id: i32) -> io::Result<()> {
+            match tokio::fs::remove_file(self.upload_path(upload_id)).await {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+                _ => Ok(()),
+            }
+        }
+
+        /// Удаление файлов, которые не менялись дольше указанного времени: загрузок, которые удалены из базы данных,
+        /// и временных файлов, которые остались после падения сервера
+        ///
+        /// ### Возвращает:
+        /// Если [`Ok`], то количество удаленных файлов. При ошибки [`io::Error`]
+        pub async fn remove_stale(&self, ttl: Duration) -> io::Result<u64> {
+            let before = SystemTime::now() - ttl;
+
+            let mut entries = match tokio::fs::read_dir(&self.root).await {
+                Ok(o) => o,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+                Err(e) => return Err(e),
+            };
+
+            let mut removed = 0;
+
+            while let Some(entry) = entries.next_entry().await? {
+                let metadata = entry.metadata().await?;
+
+                if metadata.is_file() && metadata.modified()? < before {
+                    tokio::fs::remove_file(entry.path()).await?;
+                    removed += 1;
+                }
+            }
+
+            Ok(removed)
+        }
+    }
+
+    /// sha256, начало и размер файла, которые считаются по мере записи или чтения
+    struct Fingerprint {
+        hasher: Sha256,
+        head: Vec<u8>,
+        size: u64,
+    }
+
+    impl Fingerprint {
+        fn new() -> Fingerprint {
+            Fingerprint { hasher: Sha256::new(), head: Vec::with_capacity(SNIFF_LENGTH), size: 0 }
+        }
+
+        fn update(&mut self, data: &[u8]) {
+            self.hasher.update(data);
+            let head = SNIFF_LENGTH.saturating_sub(self.head.len()).min(data.len());
+            self.head.extend_from_slice(&data[..head]);
+            self.size += data.len() as u64;
+        }
+
+        fn finish(self, staged: &mut StagedFile) {
+            staged.size = self.size;
+            staged.hash = format!("{:x}", self.hasher.finalize());
+            staged.content_type = sniff_content_type(&self.head);
+        }
+    }
+
+    /// Запись временного файла. sha256 и начало файла считаются по мере записи, поэтому файл потом не перечитывается.
+    /// Если запись не завершена через [`StagingWriter::finish`], то файл удаляется
+    pub struct StagingWriter {
+        staged: StagedFile,
+        file: tokio::fs::File,
+        fingerprint: Fingerprint,
+    }
+
+    impl StagingWriter {
+        pub async fn write(&mut self, data: &[u8]) -> io::Result<()> {
+            self.file.write_all(data).await?;
+            self.fingerprint.update(data);
+
+            Ok(())
+        }
+
+        pub async fn finish(self) -> io::Result<StagedFile> {
+            let StagingWriter { mut staged, mut file, fingerprint } = self;
+
+            file.flush().await?;
+            fingerprint.finish(&mut staged);
+
+            Ok(staged)
+        }
+    }
+
+    /// Записанный временный файл. Удаляется вместе со значением, кроме файлов загрузок
+    pub struct StagedFile {
+        path: PathBuf,
+        size: u64,
+        hash: String,
+        content_type: &'static str,
+        temporary: bool,
+    }
+
+    impl StagedFile {
+        fn new(path: PathBuf, temporary: bool) -> StagedFile {
+            StagedFile { path, size: 0, hash: String::new(), content_type: "", temporary }
+        }
+
+        pub fn path(&self) -> &Path {
+            &self.path
+        }
+
+        pub fn size(&self) -> u64 {
+            self.size
+        }
+
+        /// sha256 содержимого в hex, как у [`image_hash`](crate::postgresql::models::model_image::image::image_hash)
+        pub fn hash(&self) -> &str {
+            &self.hash
+        }
+
+        /// `Content-Type` по первым байтам файла
+        pub fn content_type(&self) -> &'static str {
+            self.content_type
+        }
+
+        /// Чтение файла целиком, для обработки в памяти (например аватаров)
+        pub async fn read(&self) -> io::Result<Vec<u8>> {
+            tokio::fs::read(&self.path).await
+        }
+    }
+
+    impl Drop for StagedFile {
+        fn drop(&mut self) {
+            if self.temporary {
+                let _ = std::fs::remove_file(&self.path);
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::postgresql::models::model_image::image::image_hash;
+
+        const PNG_HEADER: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+
+        async fn stage(chunks: &[&[u8]]) -> StagedFile {
+            let staging = Staging::new(std::env::temp_dir().join("xamarin-news-api-test-staging"));
+            let mut writer = staging.create().await.unwrap();
+
+            for chunk in chunks {
+                writer.write(chunk).await.unwrap();
+            }
+
+            writer.finish().await.unwrap()
+        }
+
+        /// Отдельный каталог, чтобы файлы загрузок и удаление устаревших файлов не пересекались с другими тестами
+        fn unique_staging() -> Staging {
+            Staging::new(std::env::temp_dir().join(format!("xamarin-news-api-test-staging-{:016x}", rand::thread_rng().gen::<u64>())))
+        }
+
+        #[actix_web::test]
+        async fn staged_file_is_hashed_while_written() {
+            let data = [&PNG_HEADER[..], b"rest of the image"].concat();
+            let file = stage(&[&data[..3], &data[3..5], &data[5..]]).await;
+
+            assert_eq!(file.size(), data.len() as u64);
+            assert_eq!(file.hash(), image_hash(&data));
+            assert_eq!(file.content_type(), "image/png");
+            assert_eq!(file.read().await.unwrap(), data);
+        }
+
+        #[actix_web::test]
+        async fn staged_file_of_text_is_not_image() {
+            let file = stage(&[b"<svg onload=alert(1)>"]).await;
+
+            assert_eq!(file.content_type(), "application/octet-stream");
+        }
+
+        #[actix_web::test]
+        async fn staged_file_is_removed_on_drop() {
+            let file = stage(&[b"data"]).await;
+            let path = file.path().to_owned();
+            assert!(path.exists());
+
+            drop(file);
+            assert!(!path.exists());
+        }
+
+        #[actix_web::test]
+        async fn upload_chunks_are_joined_in_file() {
+            let staging = unique_staging();
+            let data = [&PNG_HEADER[..], b"rest of the upload"].concat();
+
+            staging.write_upload_chunk(1, 0, &data[..5]).await.unwrap();
+            staging.write_upload_chunk(1, 5, &data[5..]).await.unwrap();
+
+            let file = staging.open_upload(1, data.len() as u64).await.unwrap();
+            assert_eq!(file.hash(), image_hash(&data));
+            assert_eq!(file.content_type(), "image/png");
+            assert_eq!(file.read().await.unwrap(), data);
+        }
+
+        #[actix_web::test]
+        async fn repeated_upload_chunk_replaces_tail() {
+            let staging = unique_staging();
+
+            staging.write_upload_chunk(1, 0, b"head").await.unwrap();
+            staging.write_upload_chunk(1, 4, b"lost tail").await.unwrap();
+            staging.write_upload_chunk(1, 4, b"tail").await.unwrap();
+
+            let file = staging.open_upload(1, 8).await.unwrap();
+            assert_eq!(file.read().await.unwrap(), b"headtail");
+        }
+
+        #[actix_web::test]
+        async fn upload_chunk_after_gap_is_rejected() {
+            let staging = unique_staging();
+
+            staging.write_upload_chunk(1, 0, b"head").await.unwrap();
+
+            let error = staging.write_upload_chunk(1, 8, b"tail").await.unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+        }
+
+        #[actix_web::test]
+        async fn upload_lock_is_held_per_upload() {
+            let staging = unique_staging();
+
+            let guard = staging.lock_upload(1).await;
+            drop(staging.lock_upload(2).await);
+
+            let waiting = tokio::time::timeout(Duration::from_millis(50), staging.lock_upload(1)).await;
+            assert!(waiting.is_err());
+
+            drop(guard);
+            drop(staging.lock_upload(1).await);
+            assert!(staging.upload_locks.lock().unwrap().len() <= 1);
+        }
+
+        #[actix_web::test]
+        async fn upload_of_unexpected_size_is_rejected() {
+            let staging = unique_staging();
+
+            staging.write_upload_chunk(1, 0, b"head").await.unwrap();
+
+            let Err(error) = staging.open_upload(1, 8).await else { panic!("short upload must be rejected") };
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        }
+
+        #[actix_web::test]
+        async fn upload_file_is_kept_until_removed() {
+            let staging = unique_staging();
+
+            staging.write_upload_chunk(1, 0, b"data").await.unwrap();
+            drop(staging.open_upload(1, 4).await.unwrap());
+            assert!(staging.upload_path(1).exists());
+
+            staging.remove_upload(1).await.unwrap();
+            assert!(!staging.upload_path(1).exists());
+            staging.remove_upload(1).await.unwrap();
+        }
+
+        #[actix_web::test]
+        async fn stale_files_are_removed() {
+            let staging = unique_staging();
+
+            staging.write_upload_chunk(1, 0, b"data").await.unwrap();
+            assert_eq!(staging.remove_stale(Duration::from_secs(3600)).await.unwrap(), 0);
+            assert!(staging.upload_path(1).exists());
+
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            assert_eq!(staging.remove_stale(Duration::from_millis(10)).await.unwrap(), 1);
+            assert!(!staging.upload_path(1).exists());
+        }
+    }
+}
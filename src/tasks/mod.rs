@@ -0,0 +1,63 @@
+pub mod background {
+    use std::time::Duration;
+    use actix_web::web;
+    use crate::logger::log::{Level, log};
+    use crate::postgresql::postgresql_manager::Connect;
+    use crate::storage::storage_staging::staging::Staging;
+
+    /// Запуск фоновой задачи, которая публикует запланированные записи, время которых наступило
+    /// ### Принимает:
+    /// Подключение к базе данных, интервал проверки
+    pub fn spawn_scheduled_publisher(conn: Connect, interval: Duration) {
+        actix_web::rt::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+            loop {
+                ticker.tick().await;
+
+                match conn.publish_scheduled_articles().await {
+                    Ok(0) => (),
+                    Ok(count) => log(Level::Debug, "[Task][scheduled-publisher] >>> published articles", &count),
+                    Err(e) => log(Level::Error, "[Task][scheduled-publisher] >>> conn.publish_scheduled_articles",
+                        &format!("Handle: {}", e)
+                    ),
+                }
+            }
+        });
+    }
+
+    /// Запуск фоновой задачи, которая удаляет брошенные и давно завершенные загрузки,
+    /// а также временные файлы, которые не менялись дольше времени жизни незавершенной загрузки
+    /// ### Принимает:
+    /// Подключение к базе данных, каталог временных файлов, интервал проверки,
+    /// сколько живет незавершенная загрузка после последнего изменения, сколько действует media ID завершенной загрузки
+    pub fn spawn_upload_collector(conn: Connect, staging: web::Data<Staging>, interval: Duration, ttl: chrono::Duration, media_ttl: chrono::Duration) {
+        actix_web::rt::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+            loop {
+                ticker.tick().await;
+
+                let now = chrono::Utc::now();
+
+                match conn.remove_expired_uploads(now - ttl, now - media_ttl).await {
+                    Ok(0) => (),
+                    Ok(count) => log(Level::Debug, "[Task][upload-collector] >>> removed uploads", &count),
+                    Err(e) => log(Level::Error, "[Task][upload-collector] >>> conn.remove_expired_uploads",
+                        &format!("Handle: {}", e)
+                    ),
+                }
+
+                match staging.remove_stale(ttl.to_std().unwrap_or_default()).await {
+                    Ok(0) => (),
+                    Ok(count) => log(Level::Debug, "[Task][upload-collector] >>> removed staged files", &count),
+                    Err(e) => log(Level::Error, "[Task][upload-collector] >>> staging.remove_stale",
+                        &format!("Handle: {}", e)
+                    ),
+                }
+            }
+        });
+    }
+}
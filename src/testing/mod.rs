@@ -0,0 +1,130 @@
+pub mod fixtures {
+    use rand::Rng;
+    use sqlx::Row;
+    use sqlx::postgres::PgPoolOptions;
+    use crate::auth::auth_session::session::{issue_tokens, AuthUser};
+    use crate::markdown::render::render_markdown;
+    use crate::postgresql::postgresql_manager::Connect;
+    use crate::postgresql::models::model_article::article::{ArticleStatus, InsertArticle, InsertComment};
+    use crate::postgresql::models::model_image::image::{image_hash, StoredImage};
+    use crate::postgresql::models::model_user::user::{RegisterUser, Role};
+
+    /// Пользователь с активной сессией
+    pub struct TestUser {
+        pub auth: AuthUser,
+        /// Токен доступа для заголовка `Authorization: Bearer <token>`
+        pub token: String,
+    }
+
+    /// Подключение к тестовой базе данных с примененными миграциями.
+    ///
+    /// Параметры берутся из `TEST_POSTGRES_DB_USER`, `TEST_POSTGRES_DB_PASSWORD`, `TEST_POSTGRES_DB_HOST`,
+    /// `TEST_POSTGRES_DB_PORT` и `TEST_POSTGRES_DB_NAME`. Если `TEST_POSTGRES_DB_NAME` не задано, то вернется `None`
+    /// и тест, которому нужна база данных, пропускается
+    pub async fn connect() -> Option<Connect> {
+        dotenv::dotenv().ok();
+
+        let db_name = match std::env::var("TEST_POSTGRES_DB_NAME") {
+            Ok(o) => o,
+            Err(_) => {
+                eprintln!("[Test] TEST_POSTGRES_DB_NAME is not set, test skipped");
+                return None;
+            },
+        };
+
+        let var = |key: &str| std::env::var(key).unwrap_or_else(|_| panic!("{} is invalid!", key));
+
+        let pool = PgPoolOptions::new()
+            .max_connections(2)
+            .connect(&format!("postgres://{}:{}@{}:{}/{}",
+                var("TEST_POSTGRES_DB_USER"),
+                var("TEST_POSTGRES_DB_PASSWORD"),
+                var("TEST_POSTGRES_DB_HOST"),
+                var("TEST_POSTGRES_DB_PORT"),
+                db_name,
+            ))
+            .await
+            .expect("[Test] Can not connect to test database!");
+
+        let conn = Connect { pool };
+
+        conn.migrate_up().await.expect("[Test] Error apply migrations!");
+
+        Some(conn)
+    }
+
+    /// Создание пользователя с указанной ролью и сессией для него
+    pub async fn insert_user(conn: &Connect, role: Role) -> TestUser {
+        let login = format!("test_{:016x}", rand::thread_rng().gen::<u64>());
+
+        conn.insert_user(&RegisterUser {
+            first_name: "Test".to_owned(),
+            last_name: "User".to_owned(),
+            about: None,
+            password: "not a hash".to_owned(),
+            login: login.clone(),
+        }).await.expect("[Test] conn.insert_user");
+
+        let user = conn.get_full_user_by_login(&login).await.expect("[Test] conn.get_full_user_by_login");
+
+        if role != Role::User {
+            conn.set_user_role(user.id, role).await.expect("[Test] conn.set_user_role");
+        }
+
+        let (pair, tokens) = issue_tokens();
+        let session_id = conn.insert_session(user.id, None, &tokens).await.expect("[Test] conn.insert_session");
+
+        TestUser {
+            auth: AuthUser { id: user.id, session_id, role },
+            token: pair.access_token,
+        }
+    }
+
+    /// Создание опубликованной записи
+    ///
+    /// ### Возвращает:
+    /// ID записи
+    pub async fn insert_article(conn: &Connect, author_id: i32) -> i32 {
+        let article = InsertArticle {
+            image: None,
+            media_id: None,
+            title: "Test".to_owned(),
+            description: "Test article".to_owned(),
+            summary: None,
+            status: ArticleStatus::Published,
+            publish_at: None,
+            tags: Vec::new(),
+        };
+        let image = StoredImage {
+            hash: image_hash(b"test"),
+            content_type: "image/png".to_owned(),
+        };
+
+        conn.insert_article(author_id, &article, &image, &render_markdown(&article.description)).await
+            .expect("[Test] conn.insert_article");
+
+        let row = sqlx::query("SELECT id FROM articles WHERE author_id = $1 ORDER BY id DESC LIMIT 1;")
+            .bind(author_id)
+            .fetch_one(&conn.pool).await
+            .expect("[Test] select article id");
+
+        row.get("id")
+    }
+
+    /// Создание комментария к записи
+    ///
+    /// ### Возвращает:
+    /// ID комментария
+    pub async fn insert_comment(conn: &Connect, user_id: i32, article_id: i32) -> i32 {
+        conn.insert_comment_to_article(user_id, &InsertComment { article_id, message: "Test comment".to_owned() }).await
+            .expect("[Test] conn.insert_comment_to_article");
+
+        let row = sqlx::query("SELECT id FROM articles_comments WHERE users_id = $1 AND articles_id = $2 ORDER BY id DESC LIMIT 1;")
+            .bind(user_id)
+            .bind(article_id)
+            .fetch_one(&conn.pool).await
+            .expect("[Test] select comment id");
+
+        row.get("id")
+    }
+}